@@ -7,7 +7,7 @@ use core::iter::Step;
 use core::ops::{Add, AddAssign, Sub, SubAssign};
 
 use crate::structures::paging::page_table::PageTableLevel;
-use crate::structures::paging::{PageOffset, PageTableIndex};
+use crate::structures::paging::{PageOffset, PageSize, PageTableIndex};
 use bit_field::BitField;
 
 const ADDRESS_SPACE_SIZE: u64 = 0x1_0000_0000_0000;
@@ -26,6 +26,19 @@ const ADDRESS_SPACE_SIZE: u64 = 0x1_0000_0000_0000;
 #[repr(transparent)]
 pub struct VirtAddr(u64);
 
+/// A canonical 57-bit virtual memory address, as used with 5-level paging (LA57).
+///
+/// With 5-level paging, the 57 lower bits of a virtual address can be used and the top 7 bits
+/// need to be copies of bit 56. Every canonical [`VirtAddr`] is also a canonical `VirtAddr57`,
+/// but not the other way around.
+///
+/// Unlike [`VirtAddr`], this type does not assume 4-level paging, so its arithmetic keeps the
+/// sign extension from bit 56. It is not used by the page table types, which only support
+/// addresses that can be represented as [`VirtAddr`].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct VirtAddr57(u64);
+
 /// A 64-bit physical memory address.
 ///
 /// This is a wrapper type around an `u64`, so it is always 8 bytes, even when compiled
@@ -57,6 +70,19 @@ impl core::fmt::Debug for VirtAddrNotValid {
     }
 }
 
+/// A virtual address was not aligned for the requested pointer type.
+///
+/// Contains the unaligned address.
+pub struct VirtAddrNotAligned(pub u64);
+
+impl core::fmt::Debug for VirtAddrNotAligned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("VirtAddrNotAligned")
+            .field(&format_args!("{:#x}", self.0))
+            .finish()
+    }
+}
+
 impl VirtAddr {
     /// Creates a new canonical virtual address.
     ///
@@ -92,6 +118,15 @@ impl VirtAddr {
         }
     }
 
+    /// Returns whether the given address is canonical, i.e. whether bits 48 to 64 are a correct
+    /// sign extension of bit 47.
+    ///
+    /// If this returns `true`, [`new`](Self::new) does not panic for the address.
+    #[inline]
+    pub const fn is_canonical(addr: u64) -> bool {
+        Self::new_truncate(addr).0 == addr
+    }
+
     /// Creates a new canonical virtual address, throwing out bits 48..64.
     ///
     /// This function performs sign extension of bit 47 to make the address
@@ -147,16 +182,97 @@ impl VirtAddr {
         self.as_ptr::<T>() as *mut T
     }
 
+    /// Converts the address to a raw pointer, checking that it is aligned to
+    /// `align_of::<T>()`.
+    ///
+    /// Returns a [`VirtAddrNotAligned`] error otherwise.
+    #[cfg(target_pointer_width = "64")]
+    #[inline]
+    pub fn try_as_ptr<T>(self) -> Result<*const T, VirtAddrNotAligned> {
+        if self.is_aligned(core::mem::align_of::<T>() as u64) {
+            Ok(self.as_ptr())
+        } else {
+            Err(VirtAddrNotAligned(self.0))
+        }
+    }
+
+    /// Converts the address to a mutable raw pointer, checking that it is
+    /// aligned to `align_of::<T>()`.
+    ///
+    /// Returns a [`VirtAddrNotAligned`] error otherwise.
+    #[cfg(target_pointer_width = "64")]
+    #[inline]
+    pub fn try_as_mut_ptr<T>(self) -> Result<*mut T, VirtAddrNotAligned> {
+        self.try_as_ptr::<T>().map(|ptr| ptr as *mut T)
+    }
+
     /// Convenience method for checking if a virtual address is null.
     #[inline]
     pub const fn is_null(self) -> bool {
         self.0 == 0
     }
 
+    /// Checked addition. Computes `self + rhs`, returning `None` if the
+    /// addition overflows or if the result is not a canonical address.
+    ///
+    /// Unlike `Step::forward`, this function does not jump the gap of
+    /// non-canonical addresses between `0x0000_8000_0000_0000` and
+    /// `0xffff_7fff_ffff_ffff`.
+    #[inline]
+    pub const fn checked_add(self, rhs: u64) -> Option<Self> {
+        match self.0.checked_add(rhs) {
+            Some(addr) => match Self::try_new(addr) {
+                Ok(addr) => Some(addr),
+                Err(_) => None,
+            },
+            None => None,
+        }
+    }
+
+    /// Checked subtraction. Computes `self - rhs`, returning `None` if the
+    /// subtraction underflows or if the result is not a canonical address.
+    ///
+    /// Unlike `Step::backward`, this function does not jump the gap of
+    /// non-canonical addresses between `0x0000_8000_0000_0000` and
+    /// `0xffff_7fff_ffff_ffff`.
+    #[inline]
+    pub const fn checked_sub(self, rhs: u64) -> Option<Self> {
+        match self.0.checked_sub(rhs) {
+            Some(addr) => match Self::try_new(addr) {
+                Ok(addr) => Some(addr),
+                Err(_) => None,
+            },
+            None => None,
+        }
+    }
+
+    /// Calculates `self + rhs`, wrapping around at `u64::MAX` and sign
+    /// extending bit 47 of the result like [`new_truncate`](Self::new_truncate).
+    ///
+    /// Returns a tuple of the result along with a boolean indicating whether
+    /// an overflow occurred or the raw result was not canonical.
+    #[inline]
+    pub const fn overflowing_add(self, rhs: u64) -> (Self, bool) {
+        let (addr, overflowed) = self.0.overflowing_add(rhs);
+        let result = Self::new_truncate(addr);
+        (result, overflowed || result.0 != addr)
+    }
+
+    /// Calculates `self + rhs`, wrapping around at `u64::MAX` and sign
+    /// extending bit 47 of the result like [`new_truncate`](Self::new_truncate).
+    #[inline]
+    pub const fn wrapping_add(self, rhs: u64) -> Self {
+        Self::new_truncate(self.0.wrapping_add(rhs))
+    }
+
     /// Aligns the virtual address upwards to the given alignment.
     ///
     /// See the `align_up` function for more information.
     ///
+    /// The result is sign extended, so aligning an address just below the gap of non-canonical
+    /// addresses (e.g. `0x7fff_ffff_f000` to `0x1_0000`) yields the first address of the
+    /// higher half, `0xffff_8000_0000_0000`.
+    ///
     /// # Panics
     ///
     /// This function panics if the resulting address is higher than
@@ -188,6 +304,29 @@ impl VirtAddr {
         VirtAddr::new_truncate(align_down(self.0, align))
     }
 
+    /// Aligns the virtual address upwards to the alignment `ALIGN`, which is checked to be a
+    /// power of two at compile time.
+    ///
+    /// See the [`align_up_const`] function for more information.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the resulting address is higher than
+    /// `0xffff_ffff_ffff_ffff`.
+    #[inline]
+    pub const fn align_up_const<const ALIGN: u64>(self) -> Self {
+        VirtAddr::new_truncate(align_up_const::<ALIGN>(self.0))
+    }
+
+    /// Aligns the virtual address downwards to the alignment `ALIGN`, which is checked to be a
+    /// power of two at compile time.
+    ///
+    /// See the [`align_down_const`] function for more information.
+    #[inline]
+    pub const fn align_down_const<const ALIGN: u64>(self) -> Self {
+        VirtAddr::new_truncate(align_down_const::<ALIGN>(self.0))
+    }
+
     /// Checks whether the virtual address has the demanded alignment.
     #[inline]
     pub fn is_aligned<U>(self, align: U) -> bool
@@ -203,6 +342,22 @@ impl VirtAddr {
         self.align_down_u64(align).as_u64() == self.as_u64()
     }
 
+    /// Checks whether the virtual address is aligned to the page size `S`.
+    ///
+    /// ```
+    /// use x86_64::structures::paging::{Size2MiB, Size4KiB};
+    /// use x86_64::VirtAddr;
+    ///
+    /// let addr = VirtAddr::new(0x20_1000);
+    /// assert!(addr.is_aligned_to::<Size4KiB>());
+    /// assert!(!addr.is_aligned_to::<Size2MiB>());
+    /// ```
+    #[inline]
+    #[rustversion::attr(since(1.61), const)]
+    pub fn is_aligned_to<S: PageSize>(self) -> bool {
+        self.is_aligned_u64(S::SIZE)
+    }
+
     /// Returns the 12-bit page offset of this virtual address.
     #[inline]
     pub const fn page_offset(self) -> PageOffset {
@@ -233,6 +388,17 @@ impl VirtAddr {
         PageTableIndex::new_truncate((self.0 >> 12 >> 9 >> 9 >> 9) as u16)
     }
 
+    /// Returns the 9-bit level 5 page table index.
+    ///
+    /// This index is only used when 5-level paging is enabled. Because the
+    /// address is sign extended from bit 47, it is always either 0 (lower
+    /// half) or 511 (higher half). See [`VirtAddr57`] for addresses that use
+    /// the other entries.
+    #[inline]
+    pub const fn p5_index(self) -> PageTableIndex {
+        PageTableIndex::new_truncate((self.0 >> 12 >> 9 >> 9 >> 9 >> 9) as u16)
+    }
+
     /// Returns the 9-bit level page table index.
     #[inline]
     pub const fn page_table_index(self, level: PageTableLevel) -> PageTableIndex {
@@ -327,6 +493,13 @@ impl fmt::Debug for VirtAddr {
     }
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for VirtAddr {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "VirtAddr({=u64:#x})", self.0);
+    }
+}
+
 impl fmt::Binary for VirtAddr {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -362,6 +535,45 @@ impl fmt::Pointer for VirtAddr {
     }
 }
 
+impl TryFrom<u64> for VirtAddr {
+    type Error = VirtAddrNotValid;
+
+    /// Tries to create a new canonical virtual address.
+    ///
+    /// Unlike [`VirtAddr::new`], this fails instead of panicking if the
+    /// address is not canonical. See [`VirtAddr::try_new`].
+    #[inline]
+    fn try_from(addr: u64) -> Result<Self, Self::Error> {
+        Self::try_new(addr)
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl TryFrom<usize> for VirtAddr {
+    type Error = VirtAddrNotValid;
+
+    /// Tries to create a new canonical virtual address. See [`VirtAddr::try_new`].
+    #[inline]
+    fn try_from(addr: usize) -> Result<Self, Self::Error> {
+        Self::try_new(addr as u64)
+    }
+}
+
+impl From<VirtAddr> for u64 {
+    #[inline]
+    fn from(addr: VirtAddr) -> u64 {
+        addr.as_u64()
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl From<VirtAddr> for usize {
+    #[inline]
+    fn from(addr: VirtAddr) -> usize {
+        addr.as_u64() as usize
+    }
+}
+
 impl Add<u64> for VirtAddr {
     type Output = Self;
     #[inline]
@@ -418,6 +630,169 @@ impl Step for VirtAddr {
     }
 }
 
+impl VirtAddr57 {
+    /// Creates a new canonical 57-bit virtual address.
+    ///
+    /// ## Panics
+    ///
+    /// This function panics if the bits in the range 57 to 64 are not a proper sign extension
+    /// of bit 56.
+    #[inline]
+    pub const fn new(addr: u64) -> VirtAddr57 {
+        match Self::try_new(addr) {
+            Ok(v) => v,
+            Err(_) => panic!("virtual address must be sign extended in bits 57 to 64"),
+        }
+    }
+
+    /// Tries to create a new canonical 57-bit virtual address.
+    ///
+    /// Returns an error if bits 57 to 64 are not a correct sign extension of bit 56.
+    #[inline]
+    pub const fn try_new(addr: u64) -> Result<VirtAddr57, VirtAddrNotValid> {
+        let v = Self::new_truncate(addr);
+        if v.0 == addr {
+            Ok(v)
+        } else {
+            Err(VirtAddrNotValid(addr))
+        }
+    }
+
+    /// Creates a new canonical 57-bit virtual address, throwing out bits 57..64.
+    ///
+    /// This function performs sign extension of bit 56, overwriting bits 57 to 64.
+    #[inline]
+    pub const fn new_truncate(addr: u64) -> VirtAddr57 {
+        VirtAddr57(((addr << 7) as i64 >> 7) as u64)
+    }
+
+    /// Converts the address to an `u64`.
+    #[inline]
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    /// Returns the 12-bit page offset of this virtual address.
+    #[inline]
+    pub const fn page_offset(self) -> PageOffset {
+        PageOffset::new_truncate(self.0 as u16)
+    }
+
+    /// Returns the 9-bit level 1 page table index.
+    #[inline]
+    pub const fn p1_index(self) -> PageTableIndex {
+        PageTableIndex::new_truncate((self.0 >> 12) as u16)
+    }
+
+    /// Returns the 9-bit level 2 page table index.
+    #[inline]
+    pub const fn p2_index(self) -> PageTableIndex {
+        PageTableIndex::new_truncate((self.0 >> 12 >> 9) as u16)
+    }
+
+    /// Returns the 9-bit level 3 page table index.
+    #[inline]
+    pub const fn p3_index(self) -> PageTableIndex {
+        PageTableIndex::new_truncate((self.0 >> 12 >> 9 >> 9) as u16)
+    }
+
+    /// Returns the 9-bit level 4 page table index.
+    #[inline]
+    pub const fn p4_index(self) -> PageTableIndex {
+        PageTableIndex::new_truncate((self.0 >> 12 >> 9 >> 9 >> 9) as u16)
+    }
+
+    /// Returns the 9-bit level 5 page table index.
+    #[inline]
+    pub const fn p5_index(self) -> PageTableIndex {
+        PageTableIndex::new_truncate((self.0 >> 12 >> 9 >> 9 >> 9 >> 9) as u16)
+    }
+}
+
+impl fmt::Debug for VirtAddr57 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("VirtAddr57")
+            .field(&format_args!("{:#x}", self.0))
+            .finish()
+    }
+}
+
+impl fmt::LowerHex for VirtAddr57 {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+impl fmt::UpperHex for VirtAddr57 {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::UpperHex::fmt(&self.0, f)
+    }
+}
+
+impl From<VirtAddr> for VirtAddr57 {
+    #[inline]
+    fn from(addr: VirtAddr) -> Self {
+        VirtAddr57(addr.as_u64())
+    }
+}
+
+impl TryFrom<VirtAddr57> for VirtAddr {
+    type Error = VirtAddrNotValid;
+
+    /// Tries to convert the address to a [`VirtAddr`], which fails if it uses bits 48 to 56.
+    #[inline]
+    fn try_from(addr: VirtAddr57) -> Result<Self, Self::Error> {
+        VirtAddr::try_new(addr.as_u64())
+    }
+}
+
+impl From<VirtAddr57> for u64 {
+    #[inline]
+    fn from(addr: VirtAddr57) -> Self {
+        addr.0
+    }
+}
+
+impl Add<u64> for VirtAddr57 {
+    type Output = Self;
+    #[inline]
+    fn add(self, rhs: u64) -> Self::Output {
+        VirtAddr57::new(self.0.checked_add(rhs).unwrap())
+    }
+}
+
+impl AddAssign<u64> for VirtAddr57 {
+    #[inline]
+    fn add_assign(&mut self, rhs: u64) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub<u64> for VirtAddr57 {
+    type Output = Self;
+    #[inline]
+    fn sub(self, rhs: u64) -> Self::Output {
+        VirtAddr57::new(self.0.checked_sub(rhs).unwrap())
+    }
+}
+
+impl SubAssign<u64> for VirtAddr57 {
+    #[inline]
+    fn sub_assign(&mut self, rhs: u64) {
+        *self = *self - rhs;
+    }
+}
+
+impl Sub<VirtAddr57> for VirtAddr57 {
+    type Output = u64;
+    #[inline]
+    fn sub(self, rhs: VirtAddr57) -> Self::Output {
+        self.as_u64().checked_sub(rhs.as_u64()).unwrap()
+    }
+}
+
 /// A passed `u64` was not a valid physical address.
 ///
 /// This means that bits 52 to 64 were not all null.
@@ -477,6 +852,15 @@ impl PhysAddr {
         }
     }
 
+    /// Returns whether the given address is a valid physical address, i.e. whether all bits
+    /// in the range 52 to 64 are zero.
+    ///
+    /// If this returns `true`, [`new`](Self::new) does not panic for the address.
+    #[inline]
+    pub const fn is_valid(addr: u64) -> bool {
+        Self::new_truncate(addr).0 == addr
+    }
+
     /// Creates a physical address that points to `0`.
     #[inline]
     pub const fn zero() -> PhysAddr {
@@ -530,6 +914,29 @@ impl PhysAddr {
         PhysAddr(align_down(self.0, align))
     }
 
+    /// Aligns the physical address upwards to the alignment `ALIGN`, which is checked to be a
+    /// power of two at compile time.
+    ///
+    /// See the [`align_up_const`] function for more information.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the resulting address has a bit in the range 52
+    /// to 64 set.
+    #[inline]
+    pub const fn align_up_const<const ALIGN: u64>(self) -> Self {
+        PhysAddr::new(align_up_const::<ALIGN>(self.0))
+    }
+
+    /// Aligns the physical address downwards to the alignment `ALIGN`, which is checked to be a
+    /// power of two at compile time.
+    ///
+    /// See the [`align_down_const`] function for more information.
+    #[inline]
+    pub const fn align_down_const<const ALIGN: u64>(self) -> Self {
+        PhysAddr(align_down_const::<ALIGN>(self.0))
+    }
+
     /// Checks whether the physical address has the demanded alignment.
     #[inline]
     pub fn is_aligned<U>(self, align: U) -> bool
@@ -544,6 +951,22 @@ impl PhysAddr {
     pub(crate) const fn is_aligned_u64(self, align: u64) -> bool {
         self.align_down_u64(align).as_u64() == self.as_u64()
     }
+
+    /// Checks whether the physical address is aligned to the page size `S`.
+    ///
+    /// ```
+    /// use x86_64::structures::paging::{Size2MiB, Size4KiB};
+    /// use x86_64::PhysAddr;
+    ///
+    /// let addr = PhysAddr::new(0x20_1000);
+    /// assert!(addr.is_aligned_to::<Size4KiB>());
+    /// assert!(!addr.is_aligned_to::<Size2MiB>());
+    /// ```
+    #[inline]
+    #[rustversion::attr(since(1.61), const)]
+    pub fn is_aligned_to<S: PageSize>(self) -> bool {
+        self.is_aligned_u64(S::SIZE)
+    }
 }
 
 impl fmt::Debug for PhysAddr {
@@ -554,6 +977,13 @@ impl fmt::Debug for PhysAddr {
     }
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for PhysAddr {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "PhysAddr({=u64:#x})", self.0);
+    }
+}
+
 impl fmt::Binary for PhysAddr {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -589,6 +1019,34 @@ impl fmt::Pointer for PhysAddr {
     }
 }
 
+impl TryFrom<u64> for PhysAddr {
+    type Error = PhysAddrNotValid;
+
+    /// Tries to create a new physical address, failing if any of the bits 52 to 63 are set.
+    #[inline]
+    fn try_from(addr: u64) -> Result<Self, Self::Error> {
+        Self::try_new(addr)
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+impl TryFrom<usize> for PhysAddr {
+    type Error = PhysAddrNotValid;
+
+    /// Tries to create a new physical address, failing if any of the bits 52 to 63 are set.
+    #[inline]
+    fn try_from(addr: usize) -> Result<Self, Self::Error> {
+        Self::try_new(addr as u64)
+    }
+}
+
+impl From<PhysAddr> for u64 {
+    #[inline]
+    fn from(addr: PhysAddr) -> u64 {
+        addr.as_u64()
+    }
+}
+
 impl Add<u64> for PhysAddr {
     type Output = Self;
     #[inline]
@@ -627,6 +1085,46 @@ impl Sub<PhysAddr> for PhysAddr {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for VirtAddr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for VirtAddr {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let addr = <u64 as serde::Deserialize>::deserialize(deserializer)?;
+        VirtAddr::try_new(addr).map_err(|_| {
+            serde::de::Error::invalid_value(
+                serde::de::Unexpected::Unsigned(addr),
+                &"a canonical virtual address",
+            )
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for PhysAddr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PhysAddr {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let addr = <u64 as serde::Deserialize>::deserialize(deserializer)?;
+        PhysAddr::try_new(addr).map_err(|_| {
+            serde::de::Error::invalid_value(
+                serde::de::Unexpected::Unsigned(addr),
+                &"a physical address without bits 52 to 64 set",
+            )
+        })
+    }
+}
+
 /// Align address downwards.
 ///
 /// Returns the greatest `x` with alignment `align` so that `x <= addr`.
@@ -659,10 +1157,108 @@ pub const fn align_up(addr: u64, align: u64) -> u64 {
     }
 }
 
+/// Fails to compile if `ALIGN` is not a power of two.
+struct AssertPowerOfTwo<const ALIGN: u64>;
+
+impl<const ALIGN: u64> AssertPowerOfTwo<ALIGN> {
+    const ASSERT: () = assert!(ALIGN.is_power_of_two(), "`ALIGN` must be a power of two");
+}
+
+/// Align address downwards to the alignment `ALIGN`.
+///
+/// Returns the greatest `x` with alignment `ALIGN` so that `x <= addr`.
+///
+/// Unlike [`align_down`], the alignment is checked to be a power of two at compile time:
+///
+/// ```compile_fail
+/// use x86_64::addr::align_down_const;
+///
+/// let _ = align_down_const::<3>(0x1234);
+/// ```
+#[inline]
+pub const fn align_down_const<const ALIGN: u64>(addr: u64) -> u64 {
+    #[allow(clippy::let_unit_value)]
+    let () = AssertPowerOfTwo::<ALIGN>::ASSERT;
+    addr & !(ALIGN - 1)
+}
+
+/// Align address upwards to the alignment `ALIGN`.
+///
+/// Returns the smallest `x` with alignment `ALIGN` so that `x >= addr`.
+///
+/// Unlike [`align_up`], the alignment is checked to be a power of two at compile time:
+///
+/// ```compile_fail
+/// use x86_64::addr::align_up_const;
+///
+/// let _ = align_up_const::<0x3000>(0x1234);
+/// ```
+///
+/// Panics if an overflow occurs.
+#[inline]
+pub const fn align_up_const<const ALIGN: u64>(addr: u64) -> u64 {
+    #[allow(clippy::let_unit_value)]
+    let () = AssertPowerOfTwo::<ALIGN>::ASSERT;
+    let align_mask = ALIGN - 1;
+    if addr & align_mask == 0 {
+        addr // already aligned
+    } else {
+        // FIXME: Replace with .expect, once `Option::expect` is const.
+        if let Some(aligned) = (addr | align_mask).checked_add(1) {
+            aligned
+        } else {
+            panic!("attempt to add with overflow")
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn align_const_matches_runtime() {
+        for &addr in &[
+            0,
+            1,
+            0xfff,
+            0x1000,
+            0x1001,
+            0x1234_5678,
+            0xffff_ffff_ffff_f000,
+        ] {
+            assert_eq!(align_up_const::<1>(addr), align_up(addr, 1));
+            assert_eq!(align_up_const::<0x1000>(addr), align_up(addr, 0x1000));
+            assert_eq!(align_down_const::<1>(addr), align_down(addr, 1));
+            assert_eq!(align_down_const::<0x1000>(addr), align_down(addr, 0x1000));
+            assert_eq!(
+                align_down_const::<0x20_0000>(addr),
+                align_down(addr, 0x20_0000)
+            );
+        }
+
+        for &addr in &[0x7fff_ffff_f123, 0xffff_8000_0000_0123] {
+            let addr = VirtAddr::new(addr);
+            assert_eq!(addr.align_up_const::<0x1000>(), addr.align_up(0x1000u64));
+            assert_eq!(
+                addr.align_down_const::<0x1000>(),
+                addr.align_down(0x1000u64)
+            );
+        }
+        // Both truncate the result in the same way when aligning past the lower half.
+        let addr = VirtAddr::new(0x7fff_ffff_f123);
+        assert_eq!(
+            addr.align_up_const::<0x1_0000_0000_0000>(),
+            addr.align_up(0x1_0000_0000_0000u64)
+        );
+        let addr = PhysAddr::new(0x1234_5678);
+        assert_eq!(addr.align_up_const::<0x1000>(), addr.align_up(0x1000u64));
+        assert_eq!(
+            addr.align_down_const::<0x1000>(),
+            addr.align_down(0x1000u64)
+        );
+    }
+
     #[test]
     #[should_panic]
     pub fn add_overflow_virtaddr() {
@@ -695,6 +1291,148 @@ mod tests {
         assert_eq!(VirtAddr::new_truncate(123 << 47), VirtAddr(0xfffff << 47));
     }
 
+    #[test]
+    fn virtaddr_checked_add() {
+        assert_eq!(
+            VirtAddr::new(0x1000).checked_add(0x1000),
+            Some(VirtAddr::new(0x2000))
+        );
+        // Landing on the last canonical byte of the lower half is fine.
+        assert_eq!(
+            VirtAddr::new(0x7fff_ffff_fffe).checked_add(1),
+            Some(VirtAddr::new(0x7fff_ffff_ffff))
+        );
+        // Landing on the first non-canonical byte is not.
+        assert_eq!(VirtAddr::new(0x7fff_ffff_ffff).checked_add(1), None);
+        assert_eq!(VirtAddr::new(0).checked_add(0x8000_0000_0000), None);
+        // Wrapping past `u64::MAX` is an overflow.
+        assert_eq!(VirtAddr::new(0xffff_ffff_ffff_ffff).checked_add(1), None);
+        assert_eq!(
+            VirtAddr::new(0xffff_8000_0000_0000).checked_add(0x7fff_ffff_ffff),
+            Some(VirtAddr::new(0xffff_ffff_ffff_ffff))
+        );
+    }
+
+    #[test]
+    fn virtaddr_checked_sub() {
+        assert_eq!(
+            VirtAddr::new(0x2000).checked_sub(0x1000),
+            Some(VirtAddr::new(0x1000))
+        );
+        assert_eq!(VirtAddr::new(0).checked_sub(1), None);
+        // Landing on the last non-canonical byte below the upper half.
+        assert_eq!(VirtAddr::new(0xffff_8000_0000_0000).checked_sub(1), None);
+        assert_eq!(
+            VirtAddr::new(0xffff_8000_0000_0001).checked_sub(1),
+            Some(VirtAddr::new(0xffff_8000_0000_0000))
+        );
+    }
+
+    #[test]
+    fn virtaddr_overflowing_add() {
+        assert_eq!(
+            VirtAddr::new(0x1000).overflowing_add(0x1000),
+            (VirtAddr::new(0x2000), false)
+        );
+        // The first non-canonical byte is sign extended.
+        assert_eq!(
+            VirtAddr::new(0x7fff_ffff_ffff).overflowing_add(1),
+            (VirtAddr::new(0xffff_8000_0000_0000), true)
+        );
+        assert_eq!(
+            VirtAddr::new(0xffff_ffff_ffff_ffff).overflowing_add(1),
+            (VirtAddr::new(0), true)
+        );
+    }
+
+    #[test]
+    fn virtaddr_wrapping_add() {
+        assert_eq!(
+            VirtAddr::new(0x7fff_ffff_ffff).wrapping_add(1),
+            VirtAddr::new(0xffff_8000_0000_0000)
+        );
+        assert_eq!(
+            VirtAddr::new(0xffff_ffff_ffff_ffff).wrapping_add(2),
+            VirtAddr::new(1)
+        );
+    }
+
+    #[test]
+    fn virtaddr_page_table_indices() {
+        let addr = VirtAddr::new(0xffff_8123_4567_8abc);
+        let indices = [
+            addr.p5_index(),
+            addr.p4_index(),
+            addr.p3_index(),
+            addr.p2_index(),
+            addr.p1_index(),
+        ];
+        assert_eq!(u16::from(indices[0]), 511);
+
+        // Reassemble the address from its five indices and sign extend it
+        // from bit 56, as is done for 57-bit addresses with 5-level paging.
+        let raw = indices
+            .iter()
+            .fold(0, |raw, &index| (raw << 9) | u64::from(index));
+        let raw = (raw << 12) | u64::from(addr.page_offset());
+        let raw = ((raw << 7) as i64 >> 7) as u64;
+        assert_eq!(raw, addr.as_u64());
+
+        assert_eq!(u16::from(VirtAddr::new(0x7fff_ffff_ffff).p5_index()), 0);
+    }
+
+    #[test]
+    fn virtaddr57_page_table_indices() {
+        // Bits 48 to 56 are used, bit 56 is clear.
+        let addr = VirtAddr57::new(0x00ab_cdef_1234_5678);
+        let indices = [
+            addr.p5_index(),
+            addr.p4_index(),
+            addr.p3_index(),
+            addr.p2_index(),
+            addr.p1_index(),
+        ];
+        assert_eq!(u16::from(indices[0]), 0xab);
+        assert!(VirtAddr::try_from(addr).is_err());
+
+        let raw = indices
+            .iter()
+            .fold(0, |raw, &index| (raw << 9) | u64::from(index));
+        let raw = (raw << 12) | u64::from(addr.page_offset());
+        assert_eq!(VirtAddr57::new_truncate(raw), addr);
+
+        // Bit 56 is set, so bits 57 to 63 must be set as well.
+        let addr = VirtAddr57::new(0xff01_0000_0000_0000);
+        assert_eq!(u16::from(addr.p5_index()), 0x101);
+        assert_eq!(
+            VirtAddr57::try_new(0x0101_0000_0000_0000).unwrap_err().0,
+            0x0101_0000_0000_0000
+        );
+        assert_eq!(VirtAddr57::new_truncate(0x0101_0000_0000_0000), addr);
+    }
+
+    #[test]
+    fn virtaddr57_arithmetic() {
+        // Crossing bit 47 doesn't need a sign extension with 5-level paging.
+        let addr = VirtAddr57::new(0x7fff_ffff_fff8);
+        assert_eq!((addr + 8).as_u64(), 0x8000_0000_0000);
+        assert_eq!(addr + 8 - addr, 8);
+        assert_eq!(
+            VirtAddr57::new(0xffff_8000_0000_0000) - 8,
+            VirtAddr57::new(0xffff_7fff_ffff_fff8)
+        );
+
+        let addr = VirtAddr::new(0xffff_8123_4567_8abc);
+        assert_eq!(VirtAddr57::from(addr).as_u64(), addr.as_u64());
+        assert_eq!(VirtAddr::try_from(VirtAddr57::from(addr)).unwrap(), addr);
+    }
+
+    #[test]
+    #[should_panic]
+    fn virtaddr57_add_non_canonical() {
+        let _ = VirtAddr57::new(0x00ff_ffff_ffff_fff8) + 8;
+    }
+
     #[test]
     #[cfg(feature = "step_trait")]
     fn virtaddr_step_forward() {
@@ -839,6 +1577,107 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_addr() {
+        use serde_test::{assert_de_tokens_error, assert_tokens, Token};
+
+        assert_tokens(
+            &VirtAddr::new(0xffff_8000_0000_1000),
+            &[Token::U64(0xffff_8000_0000_1000)],
+        );
+        assert_tokens(&PhysAddr::new(0x1234_5000), &[Token::U64(0x1234_5000)]);
+        assert_de_tokens_error::<VirtAddr>(
+            &[Token::U64(0x0000_8000_0000_0000)],
+            "invalid value: integer `140737488355328`, expected a canonical virtual address",
+        );
+        assert_de_tokens_error::<PhysAddr>(
+            &[Token::U64(1 << 52)],
+            "invalid value: integer `4503599627370496`, expected a physical address without bits 52 to 64 set",
+        );
+    }
+
+    #[test]
+    fn virtaddr_is_canonical() {
+        assert!(VirtAddr::is_canonical(0));
+        assert!(VirtAddr::is_canonical(0x0000_7fff_ffff_ffff));
+        assert!(!VirtAddr::is_canonical(0x0000_8000_0000_0000));
+        assert!(!VirtAddr::is_canonical(0xffff_7fff_ffff_ffff));
+        assert!(VirtAddr::is_canonical(0xffff_8000_0000_0000));
+        assert!(VirtAddr::is_canonical(0xffff_ffff_ffff_ffff));
+        assert!(!VirtAddr::is_canonical(0x0001_0000_0000_0000));
+    }
+
+    #[test]
+    fn virtaddr_try_as_ptr() {
+        #[repr(align(64))]
+        struct Registers;
+
+        let addr = VirtAddr::new(0xfee0_0040);
+        assert_eq!(addr.try_as_ptr::<Registers>().unwrap(), addr.as_ptr());
+        assert_eq!(
+            addr.try_as_mut_ptr::<Registers>().unwrap(),
+            addr.as_mut_ptr()
+        );
+
+        let misaligned = VirtAddr::new(0xfee0_0020);
+        assert_eq!(
+            misaligned.try_as_ptr::<Registers>().unwrap_err().0,
+            0xfee0_0020
+        );
+        assert!(misaligned.try_as_ptr::<u64>().is_ok());
+        assert!(VirtAddr::new(0x1001).try_as_mut_ptr::<u16>().is_err());
+        assert!(VirtAddr::new(0x1001).try_as_ptr::<u8>().is_ok());
+    }
+
+    #[test]
+    fn addr_try_from_integer() {
+        let virt = VirtAddr::try_from(0xffff_8000_0000_1000u64).unwrap();
+        assert_eq!(u64::from(virt), 0xffff_8000_0000_1000);
+        assert_eq!(
+            VirtAddr::try_from(0x0000_8000_0000_0000u64).unwrap_err().0,
+            0x0000_8000_0000_0000
+        );
+
+        let phys = PhysAddr::try_from(0x000f_ffff_ffff_f000u64).unwrap();
+        assert_eq!(u64::from(phys), 0x000f_ffff_ffff_f000);
+        assert_eq!(
+            PhysAddr::try_from(0x0010_0000_0000_0000u64).unwrap_err().0,
+            0x0010_0000_0000_0000
+        );
+
+        #[cfg(target_pointer_width = "64")]
+        {
+            let virt = VirtAddr::try_from(0x1000usize).unwrap();
+            assert_eq!(usize::from(virt), 0x1000);
+            assert!(VirtAddr::try_from(0x1234_5678_9abc_def0usize).is_err());
+            assert!(PhysAddr::try_from(usize::MAX).is_err());
+        }
+    }
+
+    #[test]
+    fn addr_format_flags() {
+        let virt = VirtAddr::new(0xbeef);
+        assert_eq!(format!("{:#010x}", virt), "0x0000beef");
+        assert_eq!(format!("{:08X}", virt), "0000BEEF");
+        assert_eq!(format!("{:o}", virt), "137357");
+        assert_eq!(format!("{:#b}", VirtAddr::new(5)), "0b101");
+        assert_eq!(format!("{:p}", virt), "0xbeef");
+
+        let phys = PhysAddr::new(0x1000);
+        assert_eq!(format!("{:#010x}", phys), "0x00001000");
+        assert_eq!(format!("{:x}", phys), "1000");
+        assert_eq!(format!("{:p}", phys), "0x1000");
+    }
+
+    #[test]
+    fn physaddr_is_valid() {
+        assert!(PhysAddr::is_valid(0));
+        assert!(PhysAddr::is_valid(0x000f_ffff_ffff_ffff));
+        assert!(!PhysAddr::is_valid(0x0010_0000_0000_0000));
+        assert!(!PhysAddr::is_valid(0x8000_0000_0000_0000));
+    }
+
     #[test]
     pub fn test_align_up() {
         // align 1
@@ -874,6 +1713,61 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_addr_align_page_sizes() {
+        let virt = VirtAddr::new(0xffff_8000_0020_1234);
+        assert_eq!(
+            virt.align_down(0x1000u64),
+            VirtAddr::new(0xffff_8000_0020_1000)
+        );
+        assert_eq!(
+            virt.align_up(0x1000u64),
+            VirtAddr::new(0xffff_8000_0020_2000)
+        );
+        assert_eq!(
+            virt.align_down(0x20_0000u64),
+            VirtAddr::new(0xffff_8000_0020_0000)
+        );
+        assert_eq!(
+            virt.align_up(0x20_0000u64),
+            VirtAddr::new(0xffff_8000_0040_0000)
+        );
+        assert!(virt.align_down(0x20_0000u64).is_aligned(0x20_0000u64));
+        assert!(!virt.is_aligned(0x1000u64));
+        assert_eq!(
+            VirtAddr::new(0x7fff_ffff_f000).align_up(0x20_0000u64),
+            VirtAddr::new(0xffff_8000_0000_0000)
+        );
+
+        let phys = PhysAddr::new(0x3f_1234);
+        assert_eq!(phys.align_down(0x1000u64), PhysAddr::new(0x3f_1000));
+        assert_eq!(phys.align_up(0x1000u64), PhysAddr::new(0x3f_2000));
+        assert_eq!(phys.align_down(0x20_0000u64), PhysAddr::new(0x20_0000));
+        assert_eq!(phys.align_up(0x20_0000u64), PhysAddr::new(0x40_0000));
+        assert!(PhysAddr::new(0x40_0000).is_aligned(0x20_0000u64));
+        assert!(!phys.is_aligned(0x1000u64));
+    }
+
+    #[test]
+    fn test_addr_is_aligned_to() {
+        use crate::structures::paging::{Size1GiB, Size2MiB, Size4KiB};
+
+        let virt = VirtAddr::new(0xffff_ffff_c000_0000);
+        assert!(virt.is_aligned_to::<Size4KiB>());
+        assert!(virt.is_aligned_to::<Size2MiB>());
+        assert!(virt.is_aligned_to::<Size1GiB>());
+        let virt = VirtAddr::new(0xffff_ffff_c020_0000);
+        assert!(virt.is_aligned_to::<Size2MiB>());
+        assert!(!virt.is_aligned_to::<Size1GiB>());
+        assert!(!VirtAddr::new(0x1008).is_aligned_to::<Size4KiB>());
+
+        assert!(PhysAddr::new(0x4000_0000).is_aligned_to::<Size1GiB>());
+        assert!(PhysAddr::new(0x60_0000).is_aligned_to::<Size2MiB>());
+        assert!(!PhysAddr::new(0x60_0000).is_aligned_to::<Size1GiB>());
+        assert!(PhysAddr::new(0x3000).is_aligned_to::<Size4KiB>());
+        assert!(!PhysAddr::new(0x3000).is_aligned_to::<Size2MiB>());
+    }
+
     #[test]
     #[should_panic]
     fn test_virt_addr_align_up_overflow() {
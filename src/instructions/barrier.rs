@@ -0,0 +1,48 @@
+//! Memory fence instructions.
+//!
+//! For ordinary memory ordering between threads, the portable
+//! [`core::sync::atomic::fence`] should be preferred. The functions in this
+//! module are needed for ordering operations that are not covered by the Rust
+//! memory model, such as non-temporal stores or cache line flushes (see
+//! [`cache`](crate::instructions::cache)).
+
+use core::arch::asm;
+
+/// Serializes all load and store operations issued before this instruction
+/// using the `mfence` instruction.
+///
+/// All loads and stores that precede the `mfence` become globally visible
+/// before any load or store that follows it.
+#[inline]
+pub fn mfence() {
+    unsafe {
+        asm!("mfence", options(nostack, preserves_flags));
+    }
+}
+
+/// Serializes all store operations issued before this instruction using the
+/// `sfence` instruction.
+///
+/// All stores that precede the `sfence` become globally visible before any
+/// store that follows it. This is required for ordering non-temporal stores and
+/// [`clflushopt`](crate::instructions::cache::clflushopt) or
+/// [`clwb`](crate::instructions::cache::clwb) operations.
+#[inline]
+pub fn sfence() {
+    unsafe {
+        asm!("sfence", options(nostack, preserves_flags));
+    }
+}
+
+/// Serializes all load operations issued before this instruction using the
+/// `lfence` instruction.
+///
+/// No instruction following the `lfence` starts executing until all preceding
+/// instructions have completed locally. Note that this does not wait for
+/// preceding stores to become globally visible.
+#[inline]
+pub fn lfence() {
+    unsafe {
+        asm!("lfence", options(nostack, preserves_flags));
+    }
+}
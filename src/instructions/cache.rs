@@ -0,0 +1,89 @@
+//! Functions to flush and write back CPU caches.
+
+use crate::VirtAddr;
+use core::arch::asm;
+
+/// Flushes the cache line containing the given address from all levels of the
+/// cache hierarchy using the `clflush` instruction.
+///
+/// If the cache line contains modified data, it is written back to memory
+/// before being invalidated.
+///
+/// This instruction requires CPUID.01H:EDX.CLFSH\[bit 19\] to be set.
+///
+/// ## Example
+///
+/// ```
+/// use x86_64::{instructions::cache::clflush, VirtAddr};
+///
+/// let value = 42u64;
+/// unsafe { clflush(VirtAddr::from_ptr(&value)) };
+/// ```
+///
+/// ## Safety
+///
+/// The caller must ensure that the given address is mapped. Otherwise, a page
+/// fault is raised.
+#[inline]
+pub unsafe fn clflush(addr: VirtAddr) {
+    unsafe {
+        asm!("clflush [{}]", in(reg) addr.as_u64(), options(nostack, preserves_flags));
+    }
+}
+
+/// Flushes the cache line containing the given address from all levels of the
+/// cache hierarchy using the `clflushopt` instruction.
+///
+/// Unlike [`clflush`], `clflushopt` is only ordered with respect to fences,
+/// locked instructions and other flushes of the same cache line, so multiple
+/// flushes can be executed concurrently. Issue an `sfence` after the flushes if
+/// they need to be complete before subsequent stores.
+///
+/// This instruction requires CPUID.(EAX=07H,ECX=0H):EBX.CLFLUSHOPT\[bit 23\] to
+/// be set, otherwise an invalid opcode exception is raised.
+///
+/// ## Safety
+///
+/// The caller must ensure that the given address is mapped and that the
+/// `clflushopt` instruction is supported by the processor.
+#[inline]
+pub unsafe fn clflushopt(addr: VirtAddr) {
+    unsafe {
+        asm!("clflushopt [{}]", in(reg) addr.as_u64(), options(nostack, preserves_flags));
+    }
+}
+
+/// Writes back the cache line containing the given address to memory if it
+/// contains modified data using the `clwb` instruction.
+///
+/// In contrast to [`clflush`] and [`clflushopt`], the cache line may be
+/// retained in the cache hierarchy. Like `clflushopt`, `clwb` is only ordered
+/// with respect to fences, locked instructions and other flushes of the same
+/// cache line.
+///
+/// This instruction requires CPUID.(EAX=07H,ECX=0H):EBX.CLWB\[bit 24\] to be
+/// set, otherwise an invalid opcode exception is raised.
+///
+/// ## Safety
+///
+/// The caller must ensure that the given address is mapped and that the `clwb`
+/// instruction is supported by the processor.
+#[inline]
+pub unsafe fn clwb(addr: VirtAddr) {
+    unsafe {
+        asm!("clwb [{}]", in(reg) addr.as_u64(), options(nostack, preserves_flags));
+    }
+}
+
+/// Writes back all modified cache lines to memory and invalidates all caches
+/// using the `wbinvd` instruction.
+///
+/// This is a privileged instruction and can take a very long time to complete,
+/// so it should only be used when flushing individual cache lines is not
+/// feasible.
+#[inline]
+pub fn wbinvd() {
+    unsafe {
+        asm!("wbinvd", options(nostack, preserves_flags));
+    }
+}
@@ -0,0 +1,238 @@
+//! Processor identification and feature detection using `cpuid`.
+
+use bit_field::BitField;
+
+/// The register values returned by the `cpuid` instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CpuidResult {
+    /// The value of EAX.
+    pub eax: u32,
+    /// The value of EBX.
+    pub ebx: u32,
+    /// The value of ECX.
+    pub ecx: u32,
+    /// The value of EDX.
+    pub edx: u32,
+}
+
+impl CpuidResult {
+    /// Returns the value of the given register.
+    #[inline]
+    pub const fn register(&self, register: CpuidRegister) -> u32 {
+        match register {
+            CpuidRegister::Eax => self.eax,
+            CpuidRegister::Ebx => self.ebx,
+            CpuidRegister::Ecx => self.ecx,
+            CpuidRegister::Edx => self.edx,
+        }
+    }
+
+    /// Returns whether the bit at `index` of the given register is set.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `index` is not smaller than 32.
+    #[inline]
+    pub fn bit(&self, register: CpuidRegister, index: usize) -> bool {
+        self.register(register).get_bit(index)
+    }
+}
+
+impl From<core::arch::x86_64::CpuidResult> for CpuidResult {
+    #[inline]
+    fn from(result: core::arch::x86_64::CpuidResult) -> Self {
+        Self {
+            eax: result.eax,
+            ebx: result.ebx,
+            ecx: result.ecx,
+            edx: result.edx,
+        }
+    }
+}
+
+/// A register returned by `cpuid`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CpuidRegister {
+    /// EAX
+    Eax,
+    /// EBX
+    Ebx,
+    /// ECX
+    Ecx,
+    /// EDX
+    Edx,
+}
+
+/// Processor features used by the instructions and registers of this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CpuFeature {
+    /// `monitor` and `mwait` instructions.
+    Mwait,
+    /// Process-context identifiers ([`Cr4Flags::PCID`](crate::registers::control::Cr4Flags::PCID)).
+    Pcid,
+    /// x2APIC mode of the local APIC.
+    X2Apic,
+    /// TSC-deadline mode of the local APIC timer.
+    TscDeadline,
+    /// `xsave` and related instructions.
+    Xsave,
+    /// `rdrand` instruction.
+    RdRand,
+    /// Global pages ([`Cr4Flags::PAGE_GLOBAL`](crate::registers::control::Cr4Flags::PAGE_GLOBAL)).
+    GlobalPages,
+    /// Page attribute table.
+    Pat,
+    /// `rdfsbase`, `wrfsbase`, `rdgsbase` and `wrgsbase` instructions.
+    FsGsBase,
+    /// Supervisor-mode execution prevention.
+    Smep,
+    /// `invpcid` instruction.
+    Invpcid,
+    /// `rdseed` instruction.
+    RdSeed,
+    /// Supervisor-mode access prevention.
+    Smap,
+    /// User-mode instruction prevention.
+    Umip,
+    /// Protection keys for user-mode pages.
+    Pku,
+    /// 5-level paging.
+    La57,
+    /// `rdpid` instruction.
+    RdPid,
+    /// No-execute page protection.
+    NoExecute,
+    /// 1 GiB pages.
+    HugePages1GiB,
+    /// `rdtscp` instruction.
+    Rdtscp,
+    /// `invlpgb` and `tlbsync` instructions.
+    Invlpgb,
+}
+
+impl CpuFeature {
+    /// Returns the leaf, subleaf, register and bit index reporting this feature.
+    const fn location(self) -> (u32, u32, CpuidRegister, usize) {
+        use CpuidRegister::*;
+        match self {
+            Self::Mwait => (0x1, 0, Ecx, 3),
+            Self::Pcid => (0x1, 0, Ecx, 17),
+            Self::X2Apic => (0x1, 0, Ecx, 21),
+            Self::TscDeadline => (0x1, 0, Ecx, 24),
+            Self::Xsave => (0x1, 0, Ecx, 26),
+            Self::RdRand => (0x1, 0, Ecx, 30),
+            Self::GlobalPages => (0x1, 0, Edx, 13),
+            Self::Pat => (0x1, 0, Edx, 16),
+            Self::FsGsBase => (0x7, 0, Ebx, 0),
+            Self::Smep => (0x7, 0, Ebx, 7),
+            Self::Invpcid => (0x7, 0, Ebx, 10),
+            Self::RdSeed => (0x7, 0, Ebx, 18),
+            Self::Smap => (0x7, 0, Ebx, 20),
+            Self::Umip => (0x7, 0, Ecx, 2),
+            Self::Pku => (0x7, 0, Ecx, 3),
+            Self::La57 => (0x7, 0, Ecx, 16),
+            Self::RdPid => (0x7, 0, Ecx, 22),
+            Self::NoExecute => (0x8000_0001, 0, Edx, 20),
+            Self::HugePages1GiB => (0x8000_0001, 0, Edx, 26),
+            Self::Rdtscp => (0x8000_0001, 0, Edx, 27),
+            Self::Invlpgb => (0x8000_0008, 0, Ebx, 3),
+        }
+    }
+
+    /// Returns the `cpuid` leaf reporting this feature.
+    #[inline]
+    pub const fn leaf(self) -> u32 {
+        self.location().0
+    }
+
+    /// Returns the `cpuid` subleaf reporting this feature.
+    #[inline]
+    pub const fn subleaf(self) -> u32 {
+        self.location().1
+    }
+
+    /// Returns whether the feature is reported in `result`, which must be the
+    /// result of [`cpuid`] for [`leaf`](Self::leaf) and [`subleaf`](Self::subleaf).
+    #[inline]
+    pub fn is_set_in(self, result: &CpuidResult) -> bool {
+        let (_, _, register, index) = self.location();
+        result.bit(register, index)
+    }
+}
+
+/// Executes `cpuid` for the given leaf and subleaf.
+///
+/// Leaves that do not take a subleaf ignore it. Requesting a leaf above the
+/// maximum supported leaf (see [`max_leaf`]) returns unspecified values.
+///
+/// `cpuid` clobbers RBX, which LLVM may reserve, so this is implemented with
+/// [`__cpuid_count`](core::arch::x86_64::__cpuid_count), which saves and
+/// restores it.
+#[inline]
+pub fn cpuid(leaf: u32, subleaf: u32) -> CpuidResult {
+    // `__cpuid_count` is a safe function on newer compilers.
+    #[allow(unused_unsafe)]
+    unsafe { core::arch::x86_64::__cpuid_count(leaf, subleaf) }.into()
+}
+
+/// Returns the maximum supported leaf in the range of `leaf`.
+///
+/// Pass 0 for the basic leaves and `0x8000_0000` for the extended leaves.
+#[inline]
+pub fn max_leaf(leaf: u32) -> u32 {
+    cpuid(leaf & 0x8000_0000, 0).eax
+}
+
+/// Returns whether the processor supports the given feature.
+///
+/// Leaves above the maximum supported leaf are treated as reporting no
+/// features.
+#[inline]
+pub fn has_feature(feature: CpuFeature) -> bool {
+    let leaf = feature.leaf();
+    leaf <= max_leaf(leaf) && feature.is_set_in(&cpuid(leaf, feature.subleaf()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feature_bits() {
+        let leaf_1 = CpuidResult {
+            eax: 0,
+            ebx: 0,
+            ecx: (1 << 30) | (1 << 17),
+            edx: 1 << 13,
+        };
+        assert_eq!(leaf_1.register(CpuidRegister::Ecx), 0x4002_0000);
+        assert!(CpuFeature::RdRand.is_set_in(&leaf_1));
+        assert!(CpuFeature::Pcid.is_set_in(&leaf_1));
+        assert!(CpuFeature::GlobalPages.is_set_in(&leaf_1));
+        assert!(!CpuFeature::Xsave.is_set_in(&leaf_1));
+
+        let leaf_7 = CpuidResult {
+            eax: 0,
+            ebx: 1 << 18,
+            ecx: 1 << 16,
+            edx: 0,
+        };
+        assert_eq!(CpuFeature::La57.leaf(), 7);
+        assert!(CpuFeature::La57.is_set_in(&leaf_7));
+        assert!(CpuFeature::RdSeed.is_set_in(&leaf_7));
+        assert!(!CpuFeature::FsGsBase.is_set_in(&leaf_7));
+    }
+
+    #[test]
+    fn detect_features() {
+        assert!(max_leaf(0) >= 1);
+        assert!(max_leaf(0x8000_0001) >= 0x8000_0001);
+        // Every processor supporting long mode supports no-execute pages.
+        assert!(has_feature(CpuFeature::NoExecute));
+        assert_eq!(
+            has_feature(CpuFeature::RdRand),
+            cpuid(1, 0).bit(CpuidRegister::Ecx, 30)
+        );
+    }
+}
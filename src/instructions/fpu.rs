@@ -0,0 +1,187 @@
+//! Access to the control and status words of the x87 FPU.
+
+use crate::registers::mxcsr::RoundingMode;
+use bitflags::bitflags;
+use core::arch::asm;
+
+bitflags! {
+    /// The x87 floating-point exceptions.
+    ///
+    /// In the control word these bits mask the corresponding exception, in the
+    /// status word they indicate that the exception occurred.
+    #[repr(transparent)]
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    pub struct X87Exceptions: u16 {
+        /// Invalid operation.
+        const INVALID_OPERATION = 1 << 0;
+        /// Denormal operand.
+        const DENORMAL = 1 << 1;
+        /// Divide-by-zero.
+        const DIVIDE_BY_ZERO = 1 << 2;
+        /// Numeric overflow.
+        const OVERFLOW = 1 << 3;
+        /// Numeric underflow.
+        const UNDERFLOW = 1 << 4;
+        /// Inexact result (precision).
+        const PRECISION = 1 << 5;
+    }
+}
+
+/// The precision used by x87 arithmetic instructions, stored in bits 8 and 9
+/// of the control word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum PrecisionControl {
+    /// Single precision (24-bit significand).
+    Single = 0,
+    /// Reserved encoding.
+    Reserved = 1,
+    /// Double precision (53-bit significand).
+    Double = 2,
+    /// Double extended precision (64-bit significand). This is the default.
+    DoubleExtended = 3,
+}
+
+/// The x87 FPU control word.
+///
+/// The [`Default`] value is the control word set by [`fninit`] (`0x037F`):
+/// all exceptions masked, double extended precision, round to nearest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct X87ControlWord {
+    /// The masked exceptions.
+    pub exception_masks: X87Exceptions,
+    /// The precision control field.
+    pub precision_control: PrecisionControl,
+    /// The rounding control field.
+    pub rounding_control: RoundingMode,
+}
+
+impl X87ControlWord {
+    /// Bit 6 is reserved and always set by `fninit`.
+    const RESERVED_ONE: u16 = 1 << 6;
+
+    /// Decodes a raw control word. Reserved bits are ignored.
+    #[inline]
+    pub const fn from_bits(bits: u16) -> Self {
+        let precision_control = match (bits >> 8) & 0b11 {
+            0 => PrecisionControl::Single,
+            1 => PrecisionControl::Reserved,
+            2 => PrecisionControl::Double,
+            _ => PrecisionControl::DoubleExtended,
+        };
+        let rounding_control = match (bits >> 10) & 0b11 {
+            0 => RoundingMode::Nearest,
+            1 => RoundingMode::Down,
+            2 => RoundingMode::Up,
+            _ => RoundingMode::TowardZero,
+        };
+        Self {
+            exception_masks: X87Exceptions::from_bits_truncate(bits),
+            precision_control,
+            rounding_control,
+        }
+    }
+
+    /// Encodes the control word.
+    #[inline]
+    pub const fn bits(self) -> u16 {
+        self.exception_masks.bits()
+            | Self::RESERVED_ONE
+            | (self.precision_control as u16) << 8
+            | (self.rounding_control as u16) << 10
+    }
+}
+
+impl Default for X87ControlWord {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            exception_masks: X87Exceptions::all(),
+            precision_control: PrecisionControl::DoubleExtended,
+            rounding_control: RoundingMode::Nearest,
+        }
+    }
+}
+
+/// Reads the x87 control word using `fnstcw`.
+#[inline]
+pub fn read_control_word() -> X87ControlWord {
+    let mut cw: u16 = 0;
+    unsafe {
+        asm!("fnstcw [{}]", in(reg) &mut cw, options(nostack, preserves_flags));
+    }
+    X87ControlWord::from_bits(cw)
+}
+
+/// Writes the x87 control word using `fldcw`.
+///
+/// Note that unmasking an exception whose flag is already set in the status
+/// word raises a floating-point error on the next waiting x87 instruction.
+#[inline]
+pub fn write_control_word(cw: X87ControlWord) {
+    let bits = cw.bits();
+    unsafe {
+        asm!("fldcw [{}]", in(reg) &bits, options(nostack, readonly, preserves_flags));
+    }
+}
+
+/// Reads the x87 status word using `fnstsw`.
+///
+/// Bits 0 to 5 contain the sticky exception flags, which can be decoded with
+/// [`X87Exceptions::from_bits_truncate`].
+#[inline]
+pub fn read_status_word() -> u16 {
+    let sw: u16;
+    unsafe {
+        asm!("fnstsw ax", out("ax") sw, options(nomem, nostack, preserves_flags));
+    }
+    sw
+}
+
+/// Resets the x87 FPU to its default state using `fninit`.
+///
+/// This sets the control word to [`X87ControlWord::default`], clears the
+/// status word and marks all data registers as empty.
+#[inline]
+pub fn fninit() {
+    unsafe {
+        asm!("fninit", options(nomem, nostack, preserves_flags));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_control_word() {
+        assert_eq!(X87ControlWord::default().bits(), 0x037f);
+        assert_eq!(X87ControlWord::from_bits(0x037f), X87ControlWord::default());
+    }
+
+    #[test]
+    fn control_word_fields() {
+        let cw = X87ControlWord {
+            exception_masks: X87Exceptions::PRECISION | X87Exceptions::UNDERFLOW,
+            precision_control: PrecisionControl::Double,
+            rounding_control: RoundingMode::TowardZero,
+        };
+        assert_eq!(cw.bits(), 0x0e70);
+        assert_eq!(X87ControlWord::from_bits(cw.bits()), cw);
+    }
+
+    #[test]
+    fn fninit_control_word() {
+        fninit();
+        assert_eq!(read_control_word(), X87ControlWord::default());
+        assert_eq!(read_status_word() & 0x3f, 0);
+
+        let cw = X87ControlWord {
+            rounding_control: RoundingMode::Up,
+            ..X87ControlWord::default()
+        };
+        write_control_word(cw);
+        assert_eq!(read_control_word(), cw);
+        fninit();
+    }
+}
@@ -13,6 +13,9 @@ pub fn are_enabled() -> bool {
 /// Enable interrupts.
 ///
 /// This is a wrapper around the `sti` instruction.
+///
+/// Don't use this function followed by [`hlt`](super::hlt) to wait for the next
+/// interrupt; use [`enable_and_hlt`] instead.
 #[inline]
 pub fn enable() {
     // Omit `nomem` to imitate a lock release. Otherwise, the compiler
@@ -129,7 +132,10 @@ pub fn enable_and_hlt() {
     }
 }
 
-/// Cause a breakpoint exception by invoking the `int3` instruction.
+/// Cause a breakpoint exception (`#BP`, vector 3) by invoking the `int3` instruction.
+///
+/// Once the breakpoint handler returns, execution continues after the `int3`
+/// instruction.
 #[inline]
 pub fn int3() {
     unsafe {
@@ -151,3 +157,44 @@ pub unsafe fn software_interrupt<const NUM: u8>() {
         asm!("int {num}", num = const NUM, options(nomem, nostack));
     }
 }
+
+/// Generate a software interrupt for a vector chosen at runtime.
+///
+/// As the `int` instruction only accepts an immediate operand, this calls into
+/// a table of 256 stubs, each containing an `int` instruction for one vector.
+/// If the vector is known at compile time, [`software_interrupt`] should be
+/// preferred.
+///
+/// ## Safety
+///
+/// Invoking an arbitrary interrupt is unsafe. It can cause your system to
+/// crash if you invoke a double-fault (#8) or machine-check (#18) exception.
+/// It can also cause memory/register corruption depending on the interrupt
+/// implementation (if it expects values/pointers to be passed in registers).
+#[cfg_attr(not(feature = "asm_const"), allow(rustdoc::broken_intra_doc_links))]
+// The stub table is 1 KiB, so it must not be duplicated into every caller.
+#[inline(never)]
+pub unsafe fn software_interrupt_dynamic(vector: u8) {
+    unsafe {
+        asm!(
+            "lea {stub}, [rip + 2f]",
+            "lea {stub}, [{stub} + {vector} * 4]",
+            "call {stub}",
+            "jmp 3f",
+            // Each stub is `int imm8` (encoded explicitly, as `int 3` would be
+            // assembled to `int3`) followed by `ret`, padded to 4 bytes.
+            ".balign 4",
+            "2:",
+            ".set .Lsoftware_interrupt_vector, 0",
+            ".rept 256",
+            ".balign 4",
+            ".byte 0xcd, .Lsoftware_interrupt_vector",
+            "ret",
+            ".set .Lsoftware_interrupt_vector, .Lsoftware_interrupt_vector + 1",
+            ".endr",
+            "3:",
+            vector = in(reg) u64::from(vector),
+            stub = out(reg) _,
+        );
+    }
+}
@@ -2,16 +2,30 @@
 
 //! Special x86_64 instructions.
 
+pub mod barrier;
+pub mod cache;
+pub mod cpuid;
+pub mod fpu;
 pub mod interrupts;
+pub mod mwait;
 pub mod port;
 pub mod random;
+pub mod rdpmc;
 pub mod segmentation;
+pub mod smap;
 pub mod tables;
 pub mod tlb;
+pub mod tsc;
+pub mod xsave;
 
 use core::arch::asm;
 
 /// Halts the CPU until the next interrupt arrives.
+///
+/// To idle until an interrupt arrives after checking for pending work with interrupts disabled,
+/// use [`interrupts::enable_and_hlt`] instead of enabling interrupts and calling this function.
+/// Otherwise an interrupt that arrives between the two calls is handled before `hlt`, which
+/// then waits for the next one.
 #[inline]
 pub fn hlt() {
     unsafe {
@@ -19,6 +33,32 @@ pub fn hlt() {
     }
 }
 
+/// Disables interrupts and halts the CPU forever.
+///
+/// Because maskable interrupts are disabled first, the core does not resume after an
+/// interrupt, unlike an idle loop of [`hlt`] calls with interrupts enabled. Only NMIs and
+/// similar events can still wake it up, in which case it halts again. This makes it a
+/// suitable dead end for panic handlers and shutdown paths.
+///
+/// ## Example
+///
+/// ```ignore
+/// use core::panic::PanicInfo;
+/// use x86_64::instructions::hlt_loop;
+///
+/// #[panic_handler]
+/// fn panic(_info: &PanicInfo) -> ! {
+///     hlt_loop()
+/// }
+/// ```
+#[inline]
+pub fn hlt_loop() -> ! {
+    interrupts::disable();
+    loop {
+        hlt();
+    }
+}
+
 /// Executes the `nop` instructions, which performs no operation (i.e. does nothing).
 ///
 /// This operation is useful to work around the LLVM bug that endless loops are illegally
@@ -32,6 +72,35 @@ pub fn nop() {
     }
 }
 
+/// Executes the `pause` instruction, a hint that the current code is a spin-wait loop.
+///
+/// This avoids the memory order violation when the loop exits, reduces power consumption
+/// while spinning and frees execution resources for the other logical processor of an
+/// SMT core. It is the instruction emitted by [`core::hint::spin_loop`] on `x86_64`.
+///
+/// ## Example
+///
+/// ```
+/// use core::sync::atomic::{AtomicU64, Ordering};
+/// use x86_64::instructions::pause;
+///
+/// static COUNTER: AtomicU64 = AtomicU64::new(0);
+///
+/// let mut current = COUNTER.load(Ordering::Relaxed);
+/// while let Err(actual) =
+///     COUNTER.compare_exchange_weak(current, current + 1, Ordering::AcqRel, Ordering::Relaxed)
+/// {
+///     current = actual;
+///     pause();
+/// }
+/// ```
+#[inline]
+pub fn pause() {
+    unsafe {
+        asm!("pause", options(nomem, nostack, preserves_flags));
+    }
+}
+
 /// Emits a '[magic breakpoint](https://wiki.osdev.org/Bochs#Magic_Breakpoint)'
 /// instruction for the [Bochs](http://bochs.sourceforge.net/) CPU
 /// emulator.
@@ -44,6 +113,22 @@ pub fn bochs_breakpoint() {
     }
 }
 
+/// Raises an invalid opcode exception (`#UD`, vector 6) by executing the `ud2`
+/// instruction.
+///
+/// Unlike other invalid encodings, `ud2` is guaranteed to be undefined on all
+/// processors, so it can be used to mark code paths that must trap
+/// deterministically instead of continuing execution.
+#[inline]
+pub fn ud2() -> ! {
+    unsafe {
+        asm!("ud2", options(nomem, nostack, preserves_flags, noreturn));
+    }
+}
+
+pub use self::cpuid::cpuid;
+pub use self::interrupts::int3;
+
 /// Gets the current instruction pointer. Note that this is only approximate as it requires a few
 /// instructions to execute.
 #[inline(always)]
@@ -54,3 +139,20 @@ pub fn read_rip() -> crate::VirtAddr {
     }
     crate::VirtAddr::new(rip)
 }
+
+/// Reads the machine status word, i.e. the low 16 bits of CR0, using the `smsw` instruction.
+///
+/// Unlike [`Cr0::read`](crate::registers::control::Cr0::read), which uses `mov` from `cr0` and
+/// requires ring 0, `smsw` is not privileged. Only the flags in the low 16 bits of CR0 (e.g.
+/// `PROTECTED_MODE_ENABLE` and `TASK_SWITCHED`) are returned, all other flags are always unset.
+///
+/// If [`Cr4Flags::USER_MODE_INSTRUCTION_PREVENTION`](crate::registers::control::Cr4Flags::USER_MODE_INSTRUCTION_PREVENTION)
+/// is set, this instruction can only be executed in ring 0.
+#[inline]
+pub fn smsw() -> crate::registers::control::Cr0Flags {
+    let msw: u16;
+    unsafe {
+        asm!("smsw {0:x}", out(reg) msw, options(nomem, nostack, preserves_flags));
+    }
+    crate::registers::control::Cr0Flags::from_bits_truncate(u64::from(msw))
+}
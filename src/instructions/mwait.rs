@@ -0,0 +1,92 @@
+//! Waiting for writes to a memory range using `monitor` and `mwait`.
+//!
+//! Both instructions require CPUID.01H:ECX.MONITOR\[bit 3\] to be set,
+//! otherwise an invalid opcode exception is raised. Unless enabled otherwise by
+//! the processor, they can only be executed in ring 0.
+//!
+//! ## Example
+//!
+//! Wait until another processor sets a flag:
+//!
+//! ```no_run
+//! use core::sync::atomic::{AtomicBool, Ordering};
+//! use x86_64::{
+//!     instructions::mwait::{monitor, mwait},
+//!     VirtAddr,
+//! };
+//!
+//! static WAKEUP: AtomicBool = AtomicBool::new(false);
+//!
+//! while !WAKEUP.load(Ordering::Acquire) {
+//!     unsafe {
+//!         monitor(VirtAddr::from_ptr(&WAKEUP), 0, 0);
+//!         // Check again in case the flag was set before the monitor was armed.
+//!         if WAKEUP.load(Ordering::Acquire) {
+//!             break;
+//!         }
+//!         mwait(0, 0);
+//!     }
+//! }
+//! ```
+
+use crate::VirtAddr;
+use core::arch::asm;
+
+/// Arms the address monitoring hardware on the given linear address using the
+/// `monitor` instruction.
+///
+/// A subsequent [`mwait`] waits until a store to the monitored address range
+/// occurs. The size of the monitored range can be determined using CPUID leaf
+/// 05H.
+///
+/// `ecx` contains optional extensions and `edx` optional hints. No extensions
+/// or hints are currently defined, so both should be 0.
+///
+/// ## Safety
+///
+/// The caller must ensure that the `monitor` instruction is supported, that
+/// the given address is mapped, and that `ecx` and `edx` are valid.
+#[inline]
+pub unsafe fn monitor(addr: VirtAddr, ecx: u32, edx: u32) {
+    unsafe {
+        asm!(
+            "monitor rax, ecx, edx",
+            in("rax") addr.as_u64(),
+            in("ecx") ecx,
+            in("edx") edx,
+            options(readonly, nostack, preserves_flags),
+        );
+    }
+}
+
+/// Enters an implementation-dependent optimized state until a store to the
+/// address range armed by [`monitor`] occurs, using the `mwait` instruction.
+///
+/// The processor also leaves the optimized state on interrupts and some other
+/// events, so the wake-up condition must always be checked after this
+/// function returns.
+///
+/// `eax` contains hints: bits 7:4 select the target C-state minus one (e.g. 0
+/// for C1) and bits 3:0 select the sub C-state.
+///
+/// `ecx` contains extensions: if bit 0 is set, interrupts are treated as
+/// break events even if they are masked (e.g. because interrupts are disabled
+/// via `cli`). This extension is only available if
+/// CPUID.05H:ECX\[bit 1\] is set. All other bits are reserved and must be 0.
+///
+/// ## Safety
+///
+/// The caller must ensure that the `mwait` instruction is supported and that
+/// `eax` and `ecx` are valid. Reserved bits must not be set, otherwise a
+/// general protection fault is raised.
+#[inline]
+pub unsafe fn mwait(eax: u32, ecx: u32) {
+    unsafe {
+        asm!(
+            "mwait eax, ecx",
+            in("eax") eax,
+            in("ecx") ecx,
+            options(nostack, preserves_flags),
+        );
+    }
+}
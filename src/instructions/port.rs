@@ -16,6 +16,19 @@ impl PortRead for u8 {
         }
         value
     }
+
+    #[inline]
+    unsafe fn read_from_port_buffer(port: u16, buf: &mut [u8]) {
+        unsafe {
+            asm!(
+                "rep insb",
+                inout("rdi") buf.as_mut_ptr() => _,
+                inout("rcx") buf.len() => _,
+                in("dx") port,
+                options(nostack, preserves_flags),
+            );
+        }
+    }
 }
 
 impl PortRead for u16 {
@@ -27,6 +40,19 @@ impl PortRead for u16 {
         }
         value
     }
+
+    #[inline]
+    unsafe fn read_from_port_buffer(port: u16, buf: &mut [u16]) {
+        unsafe {
+            asm!(
+                "rep insw",
+                inout("rdi") buf.as_mut_ptr() => _,
+                inout("rcx") buf.len() => _,
+                in("dx") port,
+                options(nostack, preserves_flags),
+            );
+        }
+    }
 }
 
 impl PortRead for u32 {
@@ -38,6 +64,19 @@ impl PortRead for u32 {
         }
         value
     }
+
+    #[inline]
+    unsafe fn read_from_port_buffer(port: u16, buf: &mut [u32]) {
+        unsafe {
+            asm!(
+                "rep insd",
+                inout("rdi") buf.as_mut_ptr() => _,
+                inout("rcx") buf.len() => _,
+                in("dx") port,
+                options(nostack, preserves_flags),
+            );
+        }
+    }
 }
 
 impl PortWrite for u8 {
@@ -47,6 +86,19 @@ impl PortWrite for u8 {
             asm!("out dx, al", in("dx") port, in("al") value, options(nomem, nostack, preserves_flags));
         }
     }
+
+    #[inline]
+    unsafe fn write_to_port_buffer(port: u16, buf: &[u8]) {
+        unsafe {
+            asm!(
+                "rep outsb",
+                inout("rsi") buf.as_ptr() => _,
+                inout("rcx") buf.len() => _,
+                in("dx") port,
+                options(readonly, nostack, preserves_flags),
+            );
+        }
+    }
 }
 
 impl PortWrite for u16 {
@@ -56,6 +108,19 @@ impl PortWrite for u16 {
             asm!("out dx, ax", in("dx") port, in("ax") value, options(nomem, nostack, preserves_flags));
         }
     }
+
+    #[inline]
+    unsafe fn write_to_port_buffer(port: u16, buf: &[u16]) {
+        unsafe {
+            asm!(
+                "rep outsw",
+                inout("rsi") buf.as_ptr() => _,
+                inout("rcx") buf.len() => _,
+                in("dx") port,
+                options(readonly, nostack, preserves_flags),
+            );
+        }
+    }
 }
 
 impl PortWrite for u32 {
@@ -65,6 +130,19 @@ impl PortWrite for u32 {
             asm!("out dx, eax", in("dx") port, in("eax") value, options(nomem, nostack, preserves_flags));
         }
     }
+
+    #[inline]
+    unsafe fn write_to_port_buffer(port: u16, buf: &[u32]) {
+        unsafe {
+            asm!(
+                "rep outsd",
+                inout("rsi") buf.as_ptr() => _,
+                inout("rcx") buf.len() => _,
+                in("dx") port,
+                options(readonly, nostack, preserves_flags),
+            );
+        }
+    }
 }
 
 /// A marker trait for access types which allow accessing port values.
@@ -127,9 +205,27 @@ pub struct PortGeneric<T, A> {
 pub type Port<T> = PortGeneric<T, ReadWriteAccess>;
 
 /// A read-only I/O port.
+///
+/// Writing to a read-only port does not compile:
+///
+/// ```compile_fail
+/// use x86_64::instructions::port::PortReadOnly;
+///
+/// let mut port = PortReadOnly::<u8>::new(0x60);
+/// unsafe { port.write(0) };
+/// ```
 pub type PortReadOnly<T> = PortGeneric<T, ReadOnlyAccess>;
 
 /// A write-only I/O port.
+///
+/// Reading from a write-only port does not compile:
+///
+/// ```compile_fail
+/// use x86_64::instructions::port::PortWriteOnly;
+///
+/// let mut port = PortWriteOnly::<u8>::new(0x20);
+/// let _value = unsafe { port.read() };
+/// ```
 pub type PortWriteOnly<T> = PortGeneric<T, WriteOnlyAccess>;
 
 impl<T, A> PortGeneric<T, A> {
@@ -158,6 +254,24 @@ impl<T: PortRead, A: PortReadAccess> PortGeneric<T, A> {
     pub unsafe fn read(&mut self) -> T {
         unsafe { T::read_from_port(self.port) }
     }
+
+    /// Reads `buf.len()` values from the port into `buf`.
+    ///
+    /// For `u8`, `u16` and `u32` this uses the `rep ins` instructions. These rely on the direction
+    /// flag being clear, which is guaranteed on entry to inline assembly.
+    ///
+    /// ## Safety
+    ///
+    /// This function is unsafe because the I/O port could have side effects that violate memory
+    /// safety.
+    #[doc(alias = "ins")]
+    #[doc(alias = "insb")]
+    #[doc(alias = "insw")]
+    #[doc(alias = "insd")]
+    #[inline]
+    pub unsafe fn read_buffer(&mut self, buf: &mut [T]) {
+        unsafe { T::read_from_port_buffer(self.port, buf) }
+    }
 }
 
 impl<T: PortWrite, A: PortWriteAccess> PortGeneric<T, A> {
@@ -175,6 +289,24 @@ impl<T: PortWrite, A: PortWriteAccess> PortGeneric<T, A> {
     pub unsafe fn write(&mut self, value: T) {
         unsafe { T::write_to_port(self.port, value) }
     }
+
+    /// Writes all values in `buf` to the port.
+    ///
+    /// For `u8`, `u16` and `u32` this uses the `rep outs` instructions. These rely on the direction
+    /// flag being clear, which is guaranteed on entry to inline assembly.
+    ///
+    /// ## Safety
+    ///
+    /// This function is unsafe because the I/O port could have side effects that violate memory
+    /// safety. See also [`PortWrite::write_to_port_buffer`].
+    #[doc(alias = "outs")]
+    #[doc(alias = "outsb")]
+    #[doc(alias = "outsw")]
+    #[doc(alias = "outsd")]
+    #[inline]
+    pub unsafe fn write_buffer(&mut self, buf: &[T]) {
+        unsafe { T::write_to_port_buffer(self.port, buf) }
+    }
 }
 
 impl<T, A: PortAccess> fmt::Debug for PortGeneric<T, A> {
@@ -203,3 +335,47 @@ impl<T, A> PartialEq for PortGeneric<T, A> {
 }
 
 impl<T, A> Eq for PortGeneric<T, A> {}
+
+/// Waits a short time (about 1 µs) by writing a zero byte to port `0x80`.
+///
+/// Legacy devices like the 8259 PIC need some time to process commands, but
+/// have no status register that could be polled. Port `0x80` is used for POST
+/// codes during boot and is unused afterwards, so writing to it has no effect
+/// besides the delay of an I/O bus cycle. This relies on no device on the
+/// platform using the port after boot.
+///
+/// ## Example
+///
+/// ```no_run
+/// use x86_64::instructions::port::{io_wait, Port};
+///
+/// let mut command = Port::<u8>::new(0x20);
+/// let mut data = Port::<u8>::new(0x21);
+/// unsafe {
+///     // Start the initialization sequence of the master PIC.
+///     command.write(0x11);
+///     io_wait();
+///     // Map its IRQs to interrupt vector 32.
+///     data.write(32);
+///     io_wait();
+/// }
+/// ```
+#[inline]
+pub fn io_wait() {
+    unsafe { u8::write_to_port(0x80, 0) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffer_instantiations() {
+        let _: unsafe fn(&mut Port<u8>, &mut [u8]) = Port::<u8>::read_buffer;
+        let _: unsafe fn(&mut Port<u16>, &mut [u16]) = Port::<u16>::read_buffer;
+        let _: unsafe fn(&mut Port<u32>, &mut [u32]) = Port::<u32>::read_buffer;
+        let _: unsafe fn(&mut Port<u8>, &[u8]) = Port::<u8>::write_buffer;
+        let _: unsafe fn(&mut Port<u16>, &[u16]) = Port::<u16>::write_buffer;
+        let _: unsafe fn(&mut Port<u32>, &[u32]) = Port::<u32>::write_buffer;
+    }
+}
@@ -1,17 +1,20 @@
 //! Support for build-in RNGs
 
+use crate::instructions::cpuid::{has_feature, CpuFeature};
+
 #[derive(Copy, Clone, Debug)]
 /// Used to obtain random numbers using x86_64's RDRAND opcode
 pub struct RdRand(());
 
 impl RdRand {
+    /// The number of attempts made by the `get_*` methods before giving up,
+    /// as recommended by Intel.
+    pub const RETRIES: usize = 10;
+
     /// Creates Some(RdRand) if RDRAND is supported, None otherwise
     #[inline]
     pub fn new() -> Option<Self> {
-        // RDRAND support indicated by CPUID page 01h, ecx bit 30
-        // https://en.wikipedia.org/wiki/RdRand#Overview
-        let cpuid = unsafe { core::arch::x86_64::__cpuid(0x1) };
-        if cpuid.ecx & (1 << 30) != 0 {
+        if has_feature(CpuFeature::RdRand) {
             Some(RdRand(()))
         } else {
             None
@@ -19,50 +22,160 @@ impl RdRand {
     }
 
     /// Uniformly sampled u64.
-    /// May fail in rare circumstances or heavy load.
+    ///
+    /// Retries up to [`RdRand::RETRIES`] times if no random number is
+    /// available. May fail in rare circumstances or heavy load.
     #[inline]
     pub fn get_u64(self) -> Option<u64> {
-        let mut res: u64 = 0;
-        unsafe {
-            match core::arch::x86_64::_rdrand64_step(&mut res) {
-                1 => Some(res),
-                x => {
-                    debug_assert_eq!(x, 0, "rdrand64 returned non-binary value");
-                    None
+        retry(Self::RETRIES, || {
+            let mut res: u64 = 0;
+            unsafe {
+                match core::arch::x86_64::_rdrand64_step(&mut res) {
+                    1 => Some(res),
+                    x => {
+                        debug_assert_eq!(x, 0, "rdrand64 returned non-binary value");
+                        None
+                    }
                 }
             }
-        }
+        })
     }
     /// Uniformly sampled u32.
-    /// May fail in rare circumstances or heavy load.
+    ///
+    /// Retries up to [`RdRand::RETRIES`] times if no random number is
+    /// available. May fail in rare circumstances or heavy load.
     #[inline]
     pub fn get_u32(self) -> Option<u32> {
-        let mut res: u32 = 0;
-        unsafe {
-            match core::arch::x86_64::_rdrand32_step(&mut res) {
-                1 => Some(res),
-                x => {
-                    debug_assert_eq!(x, 0, "rdrand32 returned non-binary value");
-                    None
+        retry(Self::RETRIES, || {
+            let mut res: u32 = 0;
+            unsafe {
+                match core::arch::x86_64::_rdrand32_step(&mut res) {
+                    1 => Some(res),
+                    x => {
+                        debug_assert_eq!(x, 0, "rdrand32 returned non-binary value");
+                        None
+                    }
                 }
             }
-        }
+        })
     }
     /// Uniformly sampled u16.
-    /// May fail in rare circumstances or heavy load.
+    ///
+    /// Retries up to [`RdRand::RETRIES`] times if no random number is
+    /// available. May fail in rare circumstances or heavy load.
     #[inline]
     pub fn get_u16(self) -> Option<u16> {
-        let mut res: u16 = 0;
-        unsafe {
-            match core::arch::x86_64::_rdrand16_step(&mut res) {
-                1 => Some(res),
-                x => {
-                    debug_assert_eq!(x, 0, "rdrand16 returned non-binary value");
-                    None
+        retry(Self::RETRIES, || {
+            let mut res: u16 = 0;
+            unsafe {
+                match core::arch::x86_64::_rdrand16_step(&mut res) {
+                    1 => Some(res),
+                    x => {
+                        debug_assert_eq!(x, 0, "rdrand16 returned non-binary value");
+                        None
+                    }
                 }
             }
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+/// Used to obtain random seeds using x86_64's RDSEED opcode
+///
+/// Both RDRAND and RDSEED set the carry flag if a value was returned and clear
+/// it (and the destination register) if no value was available. RDRAND returns
+/// the output of a deterministic random bit generator that is periodically
+/// reseeded by the hardware entropy source, so it is suitable as a fast
+/// cryptographically secure random number generator. RDSEED returns values
+/// directly from the conditioned entropy source and is intended for seeding
+/// other generators. It runs out of entropy much more easily, so it fails more
+/// often under load.
+pub struct RdSeed(());
+
+impl RdSeed {
+    /// The number of attempts made by the `get_*` methods before giving up.
+    pub const RETRIES: usize = 100;
+
+    /// Creates Some(RdSeed) if RDSEED is supported, None otherwise
+    #[inline]
+    pub fn new() -> Option<Self> {
+        if has_feature(CpuFeature::RdSeed) {
+            Some(RdSeed(()))
+        } else {
+            None
         }
     }
+
+    /// Uniformly sampled u64 from the hardware entropy source.
+    ///
+    /// Retries up to [`RdSeed::RETRIES`] times, pausing between attempts, if no
+    /// seed is available.
+    #[inline]
+    pub fn get_u64(self) -> Option<u64> {
+        retry(Self::RETRIES, || {
+            let mut res: u64 = 0;
+            unsafe {
+                match core::arch::x86_64::_rdseed64_step(&mut res) {
+                    1 => Some(res),
+                    x => {
+                        debug_assert_eq!(x, 0, "rdseed64 returned non-binary value");
+                        None
+                    }
+                }
+            }
+        })
+    }
+    /// Uniformly sampled u32 from the hardware entropy source.
+    ///
+    /// Retries up to [`RdSeed::RETRIES`] times, pausing between attempts, if no
+    /// seed is available.
+    #[inline]
+    pub fn get_u32(self) -> Option<u32> {
+        retry(Self::RETRIES, || {
+            let mut res: u32 = 0;
+            unsafe {
+                match core::arch::x86_64::_rdseed32_step(&mut res) {
+                    1 => Some(res),
+                    x => {
+                        debug_assert_eq!(x, 0, "rdseed32 returned non-binary value");
+                        None
+                    }
+                }
+            }
+        })
+    }
+    /// Uniformly sampled u16 from the hardware entropy source.
+    ///
+    /// Retries up to [`RdSeed::RETRIES`] times, pausing between attempts, if no
+    /// seed is available.
+    #[inline]
+    pub fn get_u16(self) -> Option<u16> {
+        retry(Self::RETRIES, || {
+            let mut res: u16 = 0;
+            unsafe {
+                match core::arch::x86_64::_rdseed16_step(&mut res) {
+                    1 => Some(res),
+                    x => {
+                        debug_assert_eq!(x, 0, "rdseed16 returned non-binary value");
+                        None
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Calls `f` until it returns a value, at most `attempts` times.
+#[inline]
+fn retry<T>(attempts: usize, mut f: impl FnMut() -> Option<T>) -> Option<T> {
+    for _ in 0..attempts {
+        if let Some(value) = f() {
+            return Some(value);
+        }
+        core::hint::spin_loop();
+    }
+    None
 }
 
 #[cfg(test)]
@@ -81,4 +194,46 @@ mod tests {
             assert!(rand.is_none());
         }
     }
+
+    #[test]
+    pub fn test_rdseed() {
+        let seed = RdSeed::new();
+        if is_x86_feature_detected!("rdseed") {
+            let seed = seed.unwrap();
+            assert!(seed.get_u16().is_some());
+            assert!(seed.get_u32().is_some());
+            assert!(seed.get_u64().is_some());
+        } else {
+            assert!(seed.is_none());
+        }
+    }
+
+    #[test]
+    pub fn test_retry() {
+        // Gives up after the given number of attempts.
+        let mut calls = 0;
+        assert_eq!(
+            retry::<u64>(RdRand::RETRIES, || {
+                calls += 1;
+                None
+            }),
+            None
+        );
+        assert_eq!(calls, RdRand::RETRIES);
+
+        // Stops as soon as a value is available.
+        let mut calls = 0;
+        assert_eq!(
+            retry(RdRand::RETRIES, || {
+                calls += 1;
+                if calls == 3 {
+                    Some(42)
+                } else {
+                    None
+                }
+            }),
+            Some(42)
+        );
+        assert_eq!(calls, 3);
+    }
 }
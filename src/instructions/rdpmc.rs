@@ -0,0 +1,51 @@
+//! Reading performance-monitoring counters.
+
+use core::arch::asm;
+
+/// Reads the performance-monitoring counter selected by `counter` using the
+/// `rdpmc` instruction.
+///
+/// For general-purpose counters, `counter` is the index of the counter. The
+/// event counted by general-purpose counter `n` is selected by programming the
+/// `IA32_PERFEVTSELn` MSR. If bit 30 of `counter` is set, the remaining bits
+/// select a fixed-function counter instead (e.g. `0x4000_0000` for fixed
+/// counter 0, which counts retired instructions on Intel processors). The
+/// width of the counters is implementation specific; unused upper bits are
+/// returned as 0.
+///
+/// Like `rdtsc`, `rdpmc` is not a serializing instruction. When measuring a
+/// code sequence, surround it with serializing instructions or fences (e.g.
+/// [`lfence`](crate::instructions::barrier::lfence)) to prevent the counter
+/// reads from being reordered with the measured instructions.
+///
+/// ## Example
+///
+/// ```no_run
+/// use x86_64::instructions::rdpmc::rdpmc;
+///
+/// // Read fixed-function counter 0.
+/// let instructions_retired = unsafe { rdpmc(0x4000_0000) };
+/// ```
+///
+/// ## Safety
+///
+/// Unless [`Cr4Flags::PERFORMANCE_MONITOR_COUNTER`] is set, this instruction
+/// can only be executed in ring 0. The caller must ensure that `counter`
+/// selects a valid counter, otherwise a general protection fault is raised.
+///
+/// [`Cr4Flags::PERFORMANCE_MONITOR_COUNTER`]: crate::registers::control::Cr4Flags::PERFORMANCE_MONITOR_COUNTER
+#[inline]
+pub unsafe fn rdpmc(counter: u32) -> u64 {
+    let low: u32;
+    let high: u32;
+    unsafe {
+        asm!(
+            "rdpmc",
+            in("ecx") counter,
+            out("eax") low,
+            out("edx") high,
+            options(nomem, nostack, preserves_flags),
+        );
+    }
+    (u64::from(high) << 32) | u64::from(low)
+}
@@ -59,6 +59,58 @@ macro_rules! segment64_impl {
     };
 }
 
+macro_rules! segment_offset_impl {
+    ($seg:literal, $base:ident, $read:ident, $write:ident, $ty:ty, $ptr:literal, $mod:literal) => {
+        #[doc = concat!("Reads a `", stringify!($ty), "` at `offset` relative to the ", $seg, " base.")]
+        ///
+        #[doc = concat!("This executes `mov reg, ", $ptr, " ptr ", $seg, ":[offset]`, so the value is")]
+        /// read without computing the base address first, which makes it suitable for accessing
+        /// per-cpu or thread-local data.
+        ///
+        /// Since `offset` is a runtime value, it is passed in a register instead of being encoded
+        /// as an immediate displacement.
+        ///
+        /// ## Safety
+        ///
+        #[doc = concat!("The ", $seg, " base must be configured (e.g. through [`", stringify!($base), "`]),")]
+        #[doc = concat!("and base + `offset` must be valid for reading a `", stringify!($ty), "`.")]
+        #[inline]
+        pub unsafe fn $read(offset: usize) -> $ty {
+            let value: $ty;
+            unsafe {
+                asm!(
+                    concat!("mov {0", $mod, "}, ", $ptr, " ptr ", $seg, ":[{1}]"),
+                    out(reg) value,
+                    in(reg) offset,
+                    options(readonly, nostack, preserves_flags),
+                );
+            }
+            value
+        }
+
+        #[doc = concat!("Writes a `", stringify!($ty), "` at `offset` relative to the ", $seg, " base.")]
+        ///
+        #[doc = concat!("This executes `mov ", $ptr, " ptr ", $seg, ":[offset], reg`. Like for")]
+        #[doc = concat!("[`", stringify!($read), "`], `offset` is passed in a register.")]
+        ///
+        /// ## Safety
+        ///
+        #[doc = concat!("The ", $seg, " base must be configured (e.g. through [`", stringify!($base), "`]),")]
+        #[doc = concat!("and base + `offset` must be valid for writing a `", stringify!($ty), "`.")]
+        #[inline]
+        pub unsafe fn $write(offset: usize, value: $ty) {
+            unsafe {
+                asm!(
+                    concat!("mov ", $ptr, " ptr ", $seg, ":[{0}], {1", $mod, "}"),
+                    in(reg) offset,
+                    in(reg) value,
+                    options(nostack, preserves_flags),
+                );
+            }
+        }
+    };
+}
+
 impl Segment for CS {
     get_reg_impl!("cs");
 
@@ -95,13 +147,81 @@ segment64_impl!(FS, "fs", FsBase);
 segment_impl!(GS, "gs");
 segment64_impl!(GS, "gs", GsBase);
 
+segment_offset_impl!(
+    "fs",
+    FsBase,
+    read_fs_offset_u64,
+    write_fs_offset_u64,
+    u64,
+    "qword",
+    ""
+);
+segment_offset_impl!(
+    "fs",
+    FsBase,
+    read_fs_offset_u32,
+    write_fs_offset_u32,
+    u32,
+    "dword",
+    ":e"
+);
+segment_offset_impl!(
+    "gs",
+    GsBase,
+    read_gs_offset_u64,
+    write_gs_offset_u64,
+    u64,
+    "qword",
+    ""
+);
+segment_offset_impl!(
+    "gs",
+    GsBase,
+    read_gs_offset_u32,
+    write_gs_offset_u32,
+    u32,
+    "dword",
+    ":e"
+);
+
 impl GS {
     /// Swap `KernelGsBase` MSR and `GsBase` MSR.
     ///
+    /// This is usually the first instruction of a system call or interrupt
+    /// entry from user mode, so that the kernel can find its per-cpu data
+    /// through the GS base, and the last instruction before returning to user
+    /// mode.
+    ///
+    /// Because the GS base is not touched by `syscall` or interrupts, the
+    /// `swapgs` has to be done in assembly before any code that uses the GS
+    /// base runs, for example:
+    ///
+    /// ```no_run
+    /// core::arch::global_asm!(
+    ///     ".global syscall_entry",
+    ///     "syscall_entry:",
+    ///     "swapgs",
+    ///     // Save the user stack pointer and switch to the kernel stack, both
+    ///     // stored in the per-cpu data pointed to by the kernel GS base.
+    ///     "mov gs:[8], rsp",
+    ///     "mov rsp, gs:[0]",
+    ///     // ... handle the system call ...
+    ///     "mov rsp, gs:[8]",
+    ///     "swapgs",
+    ///     "sysretq",
+    /// );
+    /// ```
+    ///
     /// ## Safety
     ///
     /// This function is unsafe because the caller must ensure that the
     /// swap operation cannot lead to undefined behavior.
+    ///
+    /// Exactly one swap must happen on each transition between user mode and
+    /// kernel mode. In particular, an interrupt handler must only swap if the
+    /// interrupt arrived in user mode (which can be checked through the saved
+    /// code segment), as nested interrupts or interrupts during the kernel
+    /// entry would otherwise swap the bases back.
     #[inline]
     pub unsafe fn swap() {
         unsafe {
@@ -109,3 +229,54 @@ impl GS {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PrivilegeLevel;
+
+    #[test]
+    fn segment_instantiations() {
+        fn assert_segment<S: Segment>() {
+            let _: fn() -> SegmentSelector = S::get_reg;
+            let _: unsafe fn(SegmentSelector) = S::set_reg;
+        }
+        assert_segment::<CS>();
+        assert_segment::<SS>();
+        assert_segment::<DS>();
+        assert_segment::<ES>();
+        assert_segment::<FS>();
+        assert_segment::<GS>();
+    }
+
+    #[test]
+    fn segment_offset_instantiations() {
+        let _: unsafe fn(usize) -> u64 = read_gs_offset_u64;
+        let _: unsafe fn(usize, u64) = write_gs_offset_u64;
+        let _: unsafe fn(usize) -> u32 = read_gs_offset_u32;
+        let _: unsafe fn(usize, u32) = write_gs_offset_u32;
+    }
+
+    #[test]
+    fn segment_offset_access_compiles() {
+        // Accessing the FS or GS base of the test process would be undefined behavior, so the
+        // accesses are only compiled, not executed.
+        let _: fn() = || unsafe {
+            read_gs_offset_u64(0);
+            write_gs_offset_u64(8, 0);
+            read_gs_offset_u32(0x7f);
+            write_gs_offset_u32(0x1000, 0);
+            read_fs_offset_u64(0x28);
+            write_fs_offset_u64(usize::MAX - 7, 0);
+            read_fs_offset_u32(4);
+            write_fs_offset_u32(0, 0);
+        };
+    }
+
+    #[test]
+    fn read_user_segments() {
+        // Tests run in user mode, so the code and stack segments have RPL 3.
+        assert_eq!(CS::get_reg().rpl(), PrivilegeLevel::Ring3);
+        assert_eq!(SS::get_reg().rpl(), PrivilegeLevel::Ring3);
+    }
+}
@@ -0,0 +1,97 @@
+//! Controlling supervisor-mode access prevention (SMAP) using `stac` and `clac`.
+//!
+//! If [`Cr4Flags::SUPERVISOR_MODE_ACCESS_PREVENTION`] is set, supervisor-mode
+//! accesses to user-mode pages cause a page fault unless the alignment check
+//! flag ([`RFlags::ALIGNMENT_CHECK`]) is set. The functions in this module set
+//! and clear that flag around deliberate accesses to user memory.
+//!
+//! Both instructions can only be executed in ring 0 and require
+//! CPUID.(EAX=07H,ECX=0H):EBX.SMAP\[bit 20\] to be set, otherwise an invalid
+//! opcode exception is raised.
+
+use core::{arch::asm, marker::PhantomData};
+// imports for intra doc links
+#[cfg(doc)]
+use crate::registers::{control::Cr4Flags, rflags::RFlags};
+
+/// Clears the alignment check flag using the `clac` instruction, which
+/// prevents supervisor-mode accesses to user-mode pages again.
+///
+/// ## Safety
+///
+/// SMAP must be supported and the processor must be in ring 0. Code that
+/// relies on a previous [`stac`] must not access user memory afterwards.
+#[inline]
+pub unsafe fn clac() {
+    // Omit `nomem` so that the compiler does not move accesses to user memory
+    // through this asm block.
+    unsafe {
+        asm!("clac", options(nostack));
+    }
+}
+
+/// Sets the alignment check flag using the `stac` instruction, which allows
+/// supervisor-mode accesses to user-mode pages.
+///
+/// ## Safety
+///
+/// SMAP must be supported and the processor must be in ring 0.
+#[inline]
+pub unsafe fn stac() {
+    // Omit `nomem` so that the compiler does not move accesses to user memory
+    // through this asm block.
+    unsafe {
+        asm!("stac", options(nostack));
+    }
+}
+
+/// Allows supervisor-mode accesses to user-mode pages while it is alive.
+///
+/// Creating the guard executes [`stac`] and dropping it executes [`clac`].
+/// The guard should only be held for the duration of the intended accesses to
+/// user memory.
+///
+/// ```no_run
+/// use x86_64::instructions::smap::AccessUserMemory;
+///
+/// # let user_ptr = 0x1000 as *const u64;
+/// let value = {
+///     let _guard = unsafe { AccessUserMemory::new() };
+///     unsafe { user_ptr.read_volatile() }
+/// };
+/// ```
+#[derive(Debug)]
+pub struct AccessUserMemory {
+    // The alignment check flag belongs to the current execution context, so the
+    // guard must not be sent to another thread.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl AccessUserMemory {
+    /// Allows supervisor-mode accesses to user-mode pages until the returned
+    /// guard is dropped.
+    ///
+    /// ## Safety
+    ///
+    /// SMAP must be supported and the processor must be in ring 0. The guard
+    /// must not outlive the accesses to user memory it is intended for and
+    /// guards must not be nested, as dropping the inner guard clears the flag.
+    #[inline]
+    pub unsafe fn new() -> Self {
+        unsafe {
+            stac();
+        }
+        AccessUserMemory {
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl Drop for AccessUserMemory {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            clac();
+        }
+    }
+}
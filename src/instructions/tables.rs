@@ -43,6 +43,13 @@ pub unsafe fn lidt(idt: &DescriptorTablePointer) {
 }
 
 /// Get the address of the current GDT.
+///
+/// The returned [`base`](DescriptorTablePointer::base) is the linear address of
+/// the GDT and the [`limit`](DescriptorTablePointer::limit) is its size in bytes
+/// minus one, as loaded by [`lgdt`].
+///
+/// If [`Cr4Flags::USER_MODE_INSTRUCTION_PREVENTION`](crate::registers::control::Cr4Flags::USER_MODE_INSTRUCTION_PREVENTION)
+/// is set, this instruction can only be executed in ring 0.
 #[inline]
 pub fn sgdt() -> DescriptorTablePointer {
     let mut gdt: DescriptorTablePointer = DescriptorTablePointer {
@@ -56,6 +63,13 @@ pub fn sgdt() -> DescriptorTablePointer {
 }
 
 /// Get the address of the current IDT.
+///
+/// The returned [`base`](DescriptorTablePointer::base) is the linear address of
+/// the IDT and the [`limit`](DescriptorTablePointer::limit) is its size in bytes
+/// minus one, as loaded by [`lidt`].
+///
+/// If [`Cr4Flags::USER_MODE_INSTRUCTION_PREVENTION`](crate::registers::control::Cr4Flags::USER_MODE_INSTRUCTION_PREVENTION)
+/// is set, this instruction can only be executed in ring 0.
 #[inline]
 pub fn sidt() -> DescriptorTablePointer {
     let mut idt: DescriptorTablePointer = DescriptorTablePointer {
@@ -90,3 +104,17 @@ pub unsafe fn load_tss(sel: SegmentSelector) {
         asm!("ltr {0:x}", in(reg) sel.0, options(nostack, preserves_flags));
     }
 }
+
+/// Read the segment selector of the current task state segment using the `str`
+/// instruction.
+///
+/// If [`Cr4Flags::USER_MODE_INSTRUCTION_PREVENTION`](crate::registers::control::Cr4Flags::USER_MODE_INSTRUCTION_PREVENTION)
+/// is set, this instruction can only be executed in ring 0.
+#[inline]
+pub fn read_tr() -> SegmentSelector {
+    let selector: u16;
+    unsafe {
+        asm!("str {0:x}", out(reg) selector, options(nomem, nostack, preserves_flags));
+    }
+    SegmentSelector(selector)
+}
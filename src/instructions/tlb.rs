@@ -3,15 +3,29 @@
 use bit_field::BitField;
 
 use crate::{
-    instructions::segmentation::{Segment, CS},
+    instructions::{
+        cpuid::{cpuid, has_feature, CpuFeature},
+        segmentation::{Segment, CS},
+    },
     structures::paging::{
-        page::{NotGiantPageSize, PageRange},
+        page::{NotGiantPageSize, PageRange, PageRangeInclusive},
         Page, PageSize, Size2MiB, Size4KiB,
     },
     PrivilegeLevel, VirtAddr,
 };
+
 use core::{arch::asm, cmp, convert::TryFrom, fmt};
 
+/// The default number of pages above which a range or batch of pages is not flushed page by
+/// page, but by flushing the complete TLB.
+///
+/// Used by [`flush_range`], [`MapperFlushRange::flush`] and as the default size of
+/// [`TlbFlushBatch`].
+///
+/// [`MapperFlushRange::flush`]: crate::structures::paging::mapper::MapperFlushRange::flush
+/// [`TlbFlushBatch`]: crate::structures::paging::mapper::TlbFlushBatch
+pub const FLUSH_ALL_THRESHOLD: usize = 32;
+
 /// Invalidate the given address in the TLB using the `invlpg` instruction.
 #[inline]
 pub fn flush(addr: VirtAddr) {
@@ -28,6 +42,81 @@ pub fn flush_all() {
     unsafe { Cr3::write(frame, flags) }
 }
 
+/// Invalidate the TLB completely, including global pages.
+///
+/// Unlike [`flush_all`], this also invalidates the translations of pages with the
+/// [`GLOBAL`](crate::structures::paging::PageTableFlags::GLOBAL) flag, by clearing and restoring
+/// [`Cr4Flags::PAGE_GLOBAL`](crate::registers::control::Cr4Flags::PAGE_GLOBAL). Interrupts are
+/// disabled in between.
+#[inline]
+pub fn flush_all_global() {
+    use crate::instructions::interrupts;
+    use crate::registers::control::{Cr4, Cr4Flags};
+    interrupts::without_interrupts(|| {
+        let flags = Cr4::read();
+        if flags.contains(Cr4Flags::PAGE_GLOBAL) {
+            unsafe {
+                Cr4::write(flags - Cr4Flags::PAGE_GLOBAL);
+                Cr4::write(flags);
+            }
+        } else {
+            // Without `PAGE_GLOBAL`, no translation is global.
+            flush_all();
+        }
+    })
+}
+
+/// Invalidate `count` consecutive 4KiB pages starting at `start` in the TLB.
+///
+/// If `count` is larger than [`FLUSH_ALL_THRESHOLD`], the complete TLB, including global pages,
+/// is flushed using [`flush_all_global`] instead.
+///
+/// Pages that would lie past the end of the lower half or of the address space are not
+/// flushed, since they can't be mapped.
+#[inline]
+pub fn flush_range(start: VirtAddr, count: usize) {
+    if exceeds_threshold(count as u64, FLUSH_ALL_THRESHOLD) {
+        flush_all_global();
+    } else {
+        for addr in range_addrs(start, count) {
+            flush(addr);
+        }
+    }
+}
+
+/// Returns the start addresses of `count` consecutive 4KiB pages starting at `start`, stopping
+/// at the first non-canonical address.
+fn range_addrs(start: VirtAddr, count: usize) -> impl Iterator<Item = VirtAddr> {
+    let start = start.align_down(Size4KiB::SIZE).as_u64();
+    (0..count as u64)
+        .map_while(move |i| {
+            i.checked_mul(Size4KiB::SIZE)
+                .and_then(|offset| start.checked_add(offset))
+        })
+        .map_while(|addr| VirtAddr::try_new(addr).ok())
+}
+
+/// Invalidate all pages of `range` in the TLB.
+///
+/// Every page is flushed using `invlpg`, unless the range contains more than `threshold`
+/// pages. In that case, the complete TLB, including global pages, is flushed using
+/// [`flush_all_global`] instead, which is cheaper than a large number of individual flushes.
+/// Pass `usize::MAX` to always flush page by page.
+#[inline]
+pub fn flush_page_range<S: PageSize>(range: PageRangeInclusive<S>, threshold: usize) {
+    if exceeds_threshold(range.len(), threshold) {
+        flush_all_global();
+    } else {
+        for page in range {
+            flush(page.start_address());
+        }
+    }
+}
+
+fn exceeds_threshold(count: u64, threshold: usize) -> bool {
+    count > threshold as u64
+}
+
 /// The Invalidate PCID Command to execute.
 #[derive(Debug)]
 pub enum InvPcidCommand {
@@ -99,31 +188,40 @@ impl fmt::Display for PcidTooBig {
 /// This function is unsafe as it requires CPUID.(EAX=07H, ECX=0H):EBX.INVPCID to be 1.
 #[inline]
 pub unsafe fn flush_pcid(command: InvPcidCommand) {
-    let mut desc = InvpcidDescriptor {
-        pcid: 0,
-        address: 0,
-    };
-
-    let kind: u64;
-    match command {
-        InvPcidCommand::Address(addr, pcid) => {
-            kind = 0;
-            desc.pcid = pcid.value().into();
-            desc.address = addr.as_u64()
-        }
-        InvPcidCommand::Single(pcid) => {
-            kind = 1;
-            desc.pcid = pcid.0.into()
-        }
-        InvPcidCommand::All => kind = 2,
-        InvPcidCommand::AllExceptGlobal => kind = 3,
-    }
+    let (kind, desc) = command.into_descriptor();
 
     unsafe {
         asm!("invpcid {0}, [{1}]", in(reg) kind, in(reg) &desc, options(nostack, preserves_flags));
     }
 }
 
+impl InvPcidCommand {
+    /// Returns the `invpcid` type and the descriptor for this command.
+    fn into_descriptor(self) -> (u64, InvpcidDescriptor) {
+        let mut desc = InvpcidDescriptor {
+            pcid: 0,
+            address: 0,
+        };
+
+        let kind: u64;
+        match self {
+            InvPcidCommand::Address(addr, pcid) => {
+                kind = 0;
+                desc.pcid = pcid.value().into();
+                desc.address = addr.as_u64()
+            }
+            InvPcidCommand::Single(pcid) => {
+                kind = 1;
+                desc.pcid = pcid.0.into()
+            }
+            InvPcidCommand::All => kind = 2,
+            InvPcidCommand::AllExceptGlobal => kind = 3,
+        }
+
+        (kind, desc)
+    }
+}
+
 /// Used to broadcast flushes to all logical processors.
 ///
 /// ```no_run
@@ -160,17 +258,15 @@ impl Invlpgb {
         assert_eq!(cs.rpl(), PrivilegeLevel::Ring0);
 
         // Check if the `INVLPGB` and `TLBSYNC` instruction are supported.
-        let cpuid = unsafe { core::arch::x86_64::__cpuid(0x8000_0008) };
-        if !cpuid.ebx.get_bit(3) {
+        if !has_feature(CpuFeature::Invlpgb) {
             return None;
         }
-
-        let tlb_flush_nested = cpuid.ebx.get_bit(21);
-        let invlpgb_count_max = cpuid.edx.get_bits(0..=15) as u16;
+        let limits = cpuid(0x8000_0008, 0);
+        let tlb_flush_nested = limits.ebx.get_bit(21);
+        let invlpgb_count_max = limits.edx.get_bits(0..=15) as u16;
 
         // Figure out the number of supported ASIDs.
-        let cpuid = unsafe { core::arch::x86_64::__cpuid(0x8000_000a) };
-        let nasid = cpuid.ebx;
+        let nasid = cpuid(0x8000_000a, 0).ebx;
 
         Some(Self {
             tlb_flush_nested,
@@ -287,13 +383,18 @@ where
         Ok(self)
     }
 
-    /// Also flush global pages.
+    /// Also flush global pages (bit 3 of RAX).
+    ///
+    /// By default, translations of pages with the `GLOBAL` flag are kept.
     pub fn include_global(&mut self) -> &mut Self {
         self.include_global = true;
         self
     }
 
-    /// Only flush the final translation and not the cached upper level TLB entries.
+    /// Only flush the final translation and not the cached upper level TLB entries (bit 4 of
+    /// RAX).
+    ///
+    /// This is sufficient if only the last level entries of the page tables were changed.
     pub fn final_translation_only(&mut self) -> &mut Self {
         self.final_translation_only = true;
         self
@@ -396,6 +497,38 @@ unsafe fn flush_broadcast<S>(
     include_nested_translations: bool,
 ) where
     S: NotGiantPageSize,
+{
+    let (rax, ecx, edx) = invlpgb_operands(
+        va_and_count,
+        pcid,
+        asid,
+        include_global,
+        final_translation_only,
+        include_nested_translations,
+    );
+
+    unsafe {
+        asm!(
+            "invlpgb",
+            in("rax") rax,
+            in("ecx") ecx,
+            in("edx") edx,
+            options(nostack, preserves_flags),
+        );
+    }
+}
+
+/// Encodes the operands of `invlpgb` into RAX, ECX and EDX.
+fn invlpgb_operands<S>(
+    va_and_count: Option<(Page<S>, u16)>,
+    pcid: Option<Pcid>,
+    asid: Option<u16>,
+    include_global: bool,
+    final_translation_only: bool,
+    include_nested_translations: bool,
+) -> (u64, u32, u32)
+where
+    S: NotGiantPageSize,
 {
     let mut rax = 0;
     let mut ecx = 0;
@@ -423,13 +556,79 @@ unsafe fn flush_broadcast<S>(
     rax.set_bit(4, final_translation_only);
     rax.set_bit(5, include_nested_translations);
 
-    unsafe {
-        asm!(
-            "invlpgb",
-            in("rax") rax,
-            in("ecx") ecx,
-            in("edx") edx,
-            options(nostack, preserves_flags),
+    (rax, ecx, edx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invpcid_address_descriptor() {
+        let addr = VirtAddr::new(0x1234_5678_9000);
+        let pcid = Pcid::new(0x123).unwrap();
+        let (kind, desc) = InvPcidCommand::Address(addr, pcid).into_descriptor();
+        assert_eq!(kind, 0);
+        assert_eq!(core::mem::size_of::<InvpcidDescriptor>(), 16);
+
+        let bytes: [u8; 16] = unsafe { core::mem::transmute(desc) };
+        assert_eq!(
+            bytes,
+            [
+                0x23, 0x01, 0, 0, 0, 0, 0, 0, // PCID
+                0x00, 0x90, 0x78, 0x56, 0x34, 0x12, 0, 0, // linear address
+            ]
+        );
+    }
+
+    #[test]
+    fn flush_range_threshold() {
+        assert!(!exceeds_threshold(0, FLUSH_ALL_THRESHOLD));
+        assert!(!exceeds_threshold(32, FLUSH_ALL_THRESHOLD));
+        assert!(exceeds_threshold(33, FLUSH_ALL_THRESHOLD));
+        assert!(exceeds_threshold(1, 0));
+        assert!(!exceeds_threshold(u64::MAX, usize::MAX));
+    }
+
+    #[test]
+    fn flush_range_addrs() {
+        let addrs = |start, count| {
+            range_addrs(VirtAddr::new(start), count)
+                .map(VirtAddr::as_u64)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(addrs(0x1234, 0), []);
+        assert_eq!(addrs(0x1234, 3), [0x1000, 0x2000, 0x3000]);
+        // The range stops at the end of the lower half and at the end of the address space.
+        assert_eq!(addrs(0x7fff_ffff_f000, 2), [0x7fff_ffff_f000]);
+        assert_eq!(addrs(0xffff_ffff_ffff_f000, 2), [0xffff_ffff_ffff_f000]);
+    }
+
+    #[test]
+    fn invlpgb_operand_encoding() {
+        let page = Page::<Size2MiB>::containing_address(VirtAddr::new(0xffff_8000_4020_0000));
+        let (rax, ecx, edx) = invlpgb_operands(
+            Some((page, 7)),
+            Some(Pcid::new(0xabc).unwrap()),
+            None,
+            true,
+            true,
+            false,
         );
+        assert_eq!(rax, 0xffff_8000_4020_0000 | 0b1_1011);
+        assert_eq!(ecx, (1 << 31) | 7);
+        assert_eq!(edx, 0xabc << 16);
+
+        let (rax, ecx, edx) =
+            invlpgb_operands::<Size4KiB>(None, None, Some(0x42), false, false, true);
+        assert_eq!(rax, 0b10_0100);
+        assert_eq!(ecx, 0);
+        assert_eq!(edx, 0x42);
+    }
+
+    #[test]
+    fn pcid_range() {
+        assert_eq!(Pcid::new(4095).unwrap().value(), 4095);
+        assert!(Pcid::new(4096).is_err());
     }
 }
@@ -0,0 +1,84 @@
+//! Reading the time stamp counter (TSC).
+
+use core::arch::asm;
+
+/// Reads the current value of the time stamp counter using the `rdtsc` instruction.
+///
+/// Note that `rdtsc` is not a serializing instruction: it does not wait until
+/// all previous instructions have executed before reading the counter, and
+/// subsequent instructions may begin execution before the counter is read.
+/// Use [`read_tsc_serialized`] if the read must not be reordered with
+/// previous instructions.
+///
+/// If [`Cr4Flags::TIMESTAMP_DISABLE`](crate::registers::control::Cr4Flags::TIMESTAMP_DISABLE)
+/// is set, this instruction can only be executed in ring 0.
+#[inline]
+pub fn read_tsc() -> u64 {
+    let low: u32;
+    let high: u32;
+    unsafe {
+        asm!("rdtsc", out("eax") low, out("edx") high, options(nomem, nostack, preserves_flags));
+    }
+    (u64::from(high) << 32) | u64::from(low)
+}
+
+/// Reads the current value of the time stamp counter after waiting for all
+/// previous instructions to complete.
+///
+/// This issues an `lfence` before the `rdtsc` instruction, which ensures that
+/// the counter is not read until all previous instructions have executed
+/// locally. Note that this does not wait for previous stores to become
+/// globally visible.
+#[inline]
+pub fn read_tsc_serialized() -> u64 {
+    let low: u32;
+    let high: u32;
+    unsafe {
+        asm!("lfence", "rdtsc", out("eax") low, out("edx") high, options(nomem, nostack, preserves_flags));
+    }
+    (u64::from(high) << 32) | u64::from(low)
+}
+
+/// Reads the current value of the time stamp counter and the value of the
+/// [`IA32_TSC_AUX`](crate::registers::model_specific::TscAux) MSR using the
+/// `rdtscp` instruction.
+///
+/// The `IA32_TSC_AUX` MSR is usually initialized by the operating system to
+/// contain the id of the current processor, so that it can be determined which
+/// processor's counter was read.
+///
+/// Unlike `rdtsc`, `rdtscp` waits until all previous instructions have executed
+/// before reading the counter. However, subsequent instructions may begin
+/// execution before the counter is read. Issue an `lfence` after this function
+/// if that is not desired.
+///
+/// This instruction requires CPUID.80000001H:EDX.RDTSCP\[bit 27\] to be set,
+/// otherwise an invalid opcode exception is raised.
+#[inline]
+pub fn read_tscp() -> (u64, u32) {
+    let low: u32;
+    let high: u32;
+    let aux: u32;
+    unsafe {
+        asm!(
+            "rdtscp",
+            out("eax") low,
+            out("edx") high,
+            out("ecx") aux,
+            options(nomem, nostack, preserves_flags),
+        );
+    }
+    ((u64::from(high) << 32) | u64::from(low), aux)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_read_tsc() {
+        let first = read_tsc();
+        let second = read_tsc_serialized();
+        assert!(second >= first);
+    }
+}
@@ -0,0 +1,105 @@
+//! Saving and restoring extended processor state using `xsave` and `xrstor`.
+//!
+//! The state components that can be saved are enabled in
+//! [`XCr0`](crate::registers::xcontrol::XCr0). The size of the save area for
+//! the currently enabled components can be determined using
+//! CPUID.(EAX=0DH,ECX=0):EBX.
+//!
+//! All functions in this module require
+//! [`Cr4Flags::OSXSAVE`](crate::registers::control::Cr4Flags::OSXSAVE) to be
+//! set, otherwise an invalid opcode exception is raised.
+
+use core::arch::asm;
+
+/// Saves the state components selected by `rfbm` that are enabled in XCR0 to
+/// `area` using the `xsave64` instruction.
+///
+/// ## Safety
+///
+/// `area` must be aligned to 64 bytes and valid for writes of the size of the
+/// save area, otherwise a general protection exception is raised or memory is
+/// corrupted.
+#[inline]
+pub unsafe fn xsave(area: *mut u8, rfbm: u64) {
+    unsafe {
+        asm!(
+            "xsave64 [{}]",
+            in(reg) area,
+            in("eax") rfbm as u32,
+            in("edx") (rfbm >> 32) as u32,
+            options(nostack, preserves_flags),
+        );
+    }
+}
+
+/// Saves the state components selected by `rfbm` that are enabled in XCR0 to
+/// `area` using the `xsaveopt64` instruction.
+///
+/// Unlike [`xsave`], this may skip state components that have not been
+/// modified since they were last restored from the same area using [`xrstor`].
+///
+/// This instruction requires CPUID.(EAX=0DH,ECX=1):EAX.XSAVEOPT\[bit 0\] to be
+/// set.
+///
+/// ## Safety
+///
+/// `area` must be aligned to 64 bytes and valid for writes of the size of the
+/// save area, otherwise a general protection exception is raised or memory is
+/// corrupted. The `xsaveopt` instruction must be supported.
+#[inline]
+pub unsafe fn xsaveopt(area: *mut u8, rfbm: u64) {
+    unsafe {
+        asm!(
+            "xsaveopt64 [{}]",
+            in(reg) area,
+            in("eax") rfbm as u32,
+            in("edx") (rfbm >> 32) as u32,
+            options(nostack, preserves_flags),
+        );
+    }
+}
+
+/// Restores the state components selected by `rfbm` that are enabled in XCR0
+/// from `area` using the `xrstor64` instruction.
+///
+/// ## Safety
+///
+/// `area` must be aligned to 64 bytes and contain a valid save area, e.g.
+/// one previously written by [`xsave`]. Otherwise, a general protection
+/// exception is raised. Restoring a state can change the behavior of
+/// floating point and vector instructions.
+#[inline]
+pub unsafe fn xrstor(area: *const u8, rfbm: u64) {
+    unsafe {
+        asm!(
+            "xrstor64 [{}]",
+            in(reg) area,
+            in("eax") rfbm as u32,
+            in("edx") (rfbm >> 32) as u32,
+            options(readonly, nostack, preserves_flags),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::TryInto;
+
+    #[repr(C, align(64))]
+    struct SaveArea([u8; 4096]);
+
+    #[test]
+    fn test_xsave_xrstor() {
+        // Save and restore the x87 and SSE state.
+        let rfbm = 0b11;
+        let mut area = SaveArea([0; 4096]);
+        unsafe {
+            xsave(area.0.as_mut_ptr(), rfbm);
+            xrstor(area.0.as_ptr(), rfbm);
+        }
+        // The MXCSR register is saved at offset 24.
+        let mxcsr = u32::from_le_bytes(area.0[24..28].try_into().unwrap());
+        assert_eq!(mxcsr, crate::registers::mxcsr::read().bits());
+    }
+}
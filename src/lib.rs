@@ -9,7 +9,26 @@
 #![deny(missing_debug_implementations)]
 #![deny(unsafe_op_in_unsafe_fn)]
 
-pub use crate::addr::{align_down, align_up, PhysAddr, VirtAddr};
+pub use crate::addr::{align_down, align_up, PhysAddr, VirtAddr, VirtAddr57};
+
+/// Implements `defmt::Format` for a `bitflags` type by printing the names of all set flags.
+#[cfg(feature = "defmt")]
+macro_rules! impl_defmt_format_for_flags {
+    ($ty:ident) => {
+        impl defmt::Format for $ty {
+            fn format(&self, f: defmt::Formatter) {
+                defmt::write!(f, "{=str}(", stringify!($ty));
+                for (i, (name, _)) in self.iter_names().enumerate() {
+                    if i != 0 {
+                        defmt::write!(f, " | ");
+                    }
+                    defmt::write!(f, "{=str}", name);
+                }
+                defmt::write!(f, ")");
+            }
+        }
+    };
+}
 
 pub mod addr;
 pub mod instructions;
@@ -65,3 +84,25 @@ impl PrivilegeLevel {
 pub(crate) mod sealed {
     pub trait Sealed {}
 }
+
+#[cfg(all(test, feature = "defmt"))]
+mod tests {
+    use crate::registers::control::{Cr0Flags, Cr4Flags};
+    use crate::registers::model_specific::EferFlags;
+    use crate::registers::segmentation::SegmentSelector;
+    use crate::structures::paging::PageTableFlags;
+    use crate::{PhysAddr, VirtAddr};
+
+    fn assert_format<T: defmt::Format>() {}
+
+    #[test]
+    fn defmt_format_impls() {
+        assert_format::<VirtAddr>();
+        assert_format::<PhysAddr>();
+        assert_format::<PageTableFlags>();
+        assert_format::<Cr0Flags>();
+        assert_format::<Cr4Flags>();
+        assert_format::<EferFlags>();
+        assert_format::<SegmentSelector>();
+    }
+}
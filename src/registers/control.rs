@@ -51,6 +51,9 @@ bitflags! {
     }
 }
 
+#[cfg(feature = "defmt")]
+impl_defmt_format_for_flags!(Cr0Flags);
+
 /// Contains the Page Fault Linear Address (PFLA).
 ///
 /// When a page fault occurs, the CPU sets this register to the faulting virtual address.
@@ -159,6 +162,18 @@ bitflags! {
     }
 }
 
+#[cfg(feature = "defmt")]
+impl_defmt_format_for_flags!(Cr4Flags);
+
+/// Contains the Task Priority Register (TPR).
+///
+/// The TPR is used to mask external interrupts by priority: interrupts whose
+/// priority class (bits 4 to 7 of the vector) is less than or equal to the
+/// value of the TPR are not delivered. Only the low 4 bits of the register
+/// are meaningful, so priority values range from 0 to 15.
+#[derive(Debug)]
+pub struct Cr8;
+
 #[cfg(all(feature = "instructions", target_arch = "x86_64"))]
 mod x86_64 {
     use super::*;
@@ -249,9 +264,15 @@ mod x86_64 {
         ///
         /// This method returns a [`VirtAddrNotValid`] error if the CR2 register contains a
         /// non-canonical address. Call [`Cr2::read_raw`] to handle such cases.
+        ///
+        /// Page fault handlers should not assume that CR2 is canonical: the register can be
+        /// written by software, a hypervisor may inject a page fault with an arbitrary (guest
+        /// controlled) address, and with 5-level paging faulting addresses can have more than
+        /// 48 significant bits. Panicking on such an address inside the handler would escalate
+        /// the page fault into a double fault.
         #[inline]
         pub fn read() -> Result<VirtAddr, VirtAddrNotValid> {
-            VirtAddr::try_new(Self::read_raw())
+            Self::decode(Self::read_raw())
         }
 
         /// Read the current page fault linear address from the CR2 register as a raw `u64`.
@@ -265,6 +286,12 @@ mod x86_64 {
 
             value
         }
+
+        /// Converts a raw CR2 value into a virtual address.
+        #[inline]
+        fn decode(value: u64) -> Result<VirtAddr, VirtAddrNotValid> {
+            VirtAddr::try_new(value)
+        }
     }
 
     impl Cr3 {
@@ -354,14 +381,19 @@ mod x86_64 {
 
         #[inline]
         unsafe fn write_raw_impl(top_bit: bool, frame: PhysFrame, val: u16) {
-            let addr = frame.start_address();
-            let value = ((top_bit as u64) << 63) | addr.as_u64() | val as u64;
+            let value = Self::encode(top_bit, frame, val);
 
             unsafe {
                 asm!("mov cr3, {}", in(reg) value, options(nostack, preserves_flags));
             }
         }
 
+        /// Assembles the raw CR3 value from the top bit, the P4 frame and the low 12 bits.
+        #[inline]
+        fn encode(top_bit: bool, frame: PhysFrame, val: u16) -> u64 {
+            ((top_bit as u64) << 63) | frame.start_address().as_u64() | val as u64
+        }
+
         /// Update the P4 table address in the CR3 register.
         ///
         /// ## Safety
@@ -420,6 +452,57 @@ mod x86_64 {
         }
     }
 
+    impl Cr8 {
+        /// Read the current task priority from the CR8 register.
+        #[inline]
+        pub fn read() -> u8 {
+            Self::decode(Self::read_raw())
+        }
+
+        /// Read the current raw CR8 value.
+        #[inline]
+        pub fn read_raw() -> u64 {
+            let value: u64;
+
+            unsafe {
+                asm!("mov {}, cr8", out(reg) value, options(nomem, nostack, preserves_flags));
+            }
+
+            value
+        }
+
+        /// Write a new task priority into the CR8 register.
+        ///
+        /// Only the low 4 bits of `priority` are used, so values above 15 are
+        /// truncated, e.g. writing `0x1f` sets the task priority to `0xf`.
+        ///
+        /// ## Safety
+        ///
+        /// This function is unsafe because changing the task priority affects
+        /// which interrupts are delivered, which can break assumptions made
+        /// by interrupt handlers.
+        #[inline]
+        pub unsafe fn write(priority: u8) {
+            let value = Self::encode(priority);
+
+            unsafe {
+                asm!("mov cr8, {}", in(reg) value, options(nostack, preserves_flags));
+            }
+        }
+
+        /// Extracts the task priority from a raw CR8 value.
+        #[inline]
+        const fn decode(value: u64) -> u8 {
+            (value & 0xf) as u8
+        }
+
+        /// Encodes a task priority as a CR8 value, truncating it to 4 bits.
+        #[inline]
+        const fn encode(priority: u8) -> u64 {
+            (priority & 0xf) as u64
+        }
+    }
+
     impl Cr4 {
         /// Read the current set of CR4 flags.
         #[inline]
@@ -496,4 +579,65 @@ mod x86_64 {
             }
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn cr2_non_canonical() {
+            // `Cr2::read` reports the raw address instead of panicking.
+            let raw = 0x0000_8000_0000_1000;
+            assert_eq!(Cr2::decode(raw).unwrap_err().0, raw);
+            assert_eq!(
+                Cr2::decode(0xffff_8000_0000_1000).unwrap(),
+                VirtAddr::new(0xffff_8000_0000_1000)
+            );
+        }
+
+        #[test]
+        fn cr8_priority_masking() {
+            assert_eq!(Cr8::encode(0), 0);
+            assert_eq!(Cr8::encode(0xf), 0xf);
+            assert_eq!(Cr8::encode(0x10), 0);
+            assert_eq!(Cr8::encode(0x1f), 0xf);
+            assert_eq!(Cr8::encode(u8::MAX), 0xf);
+            assert_eq!(Cr8::decode(0x7), 7);
+            assert_eq!(Cr8::decode(0xffff_fff3), 3);
+        }
+
+        #[test]
+        fn cr3_encode_pcid() {
+            let frame = PhysFrame::containing_address(PhysAddr::new(0x1234_5000));
+            let pcid = Pcid::new(0xabc).unwrap();
+            assert_eq!(Cr3::encode(false, frame, pcid.value()), 0x1234_5abc);
+            assert_eq!(
+                Cr3::encode(true, frame, pcid.value()),
+                0x8000_0000_1234_5abc
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cr4_bit_positions() {
+        assert_eq!(Cr4Flags::USER_MODE_INSTRUCTION_PREVENTION.bits(), 1 << 11);
+        assert_eq!(Cr4Flags::L5_PAGING.bits(), 1 << 12);
+        assert_eq!(Cr4Flags::SUPERVISOR_MODE_ACCESS_PREVENTION.bits(), 1 << 21);
+        assert_eq!(Cr4Flags::PROTECTION_KEY_USER.bits(), 1 << 22);
+        assert_eq!(Cr4Flags::CONTROL_FLOW_ENFORCEMENT.bits(), 1 << 23);
+    }
+
+    #[test]
+    fn cr4_debug_names() {
+        let flags = Cr4Flags::SUPERVISOR_MODE_ACCESS_PREVENTION | Cr4Flags::L5_PAGING;
+        assert_eq!(
+            format!("{:?}", flags),
+            "Cr4Flags(L5_PAGING | SUPERVISOR_MODE_ACCESS_PREVENTION)"
+        );
+    }
 }
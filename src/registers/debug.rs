@@ -429,6 +429,24 @@ impl Dr7Value {
         self.bits
             .set_bits(BreakpointSize::bit_range(n), size as u64);
     }
+
+    /// Configures the hardware breakpoint of a debug address register.
+    ///
+    /// This sets the condition and size fields of the debug address register and
+    /// inserts or removes its local breakpoint enable flag depending on `enabled`.
+    /// The breakpoint address itself has to be written to the corresponding
+    /// [`DebugAddressRegister`].
+    pub fn set_breakpoint(
+        &mut self,
+        n: DebugAddressRegisterNumber,
+        condition: BreakpointCondition,
+        size: BreakpointSize,
+        enabled: bool,
+    ) {
+        self.set_condition(n, condition);
+        self.set_size(n, size);
+        self.set_flags(Dr7Flags::local_breakpoint_enable(n), enabled);
+    }
 }
 
 /// Debug Control Register (DR7).
@@ -514,3 +532,38 @@ mod x86_64 {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dr7_write_watchpoint() {
+        let mut dr7 = Dr7Value::from_bits_truncate(0);
+        dr7.set_breakpoint(
+            DebugAddressRegisterNumber::Dr1,
+            BreakpointCondition::DataWrites,
+            BreakpointSize::Length4B,
+            true,
+        );
+        // L1 (bit 2), R/W1 = 0b01 (bits 20-21), LEN1 = 0b11 (bits 22-23)
+        assert_eq!(dr7.bits(), 0x00d0_0004);
+        assert!(dr7.flags().contains(Dr7Flags::LOCAL_BREAKPOINT_1_ENABLE));
+        assert_eq!(
+            dr7.condition(DebugAddressRegisterNumber::Dr1),
+            BreakpointCondition::DataWrites
+        );
+        assert_eq!(
+            dr7.size(DebugAddressRegisterNumber::Dr1),
+            BreakpointSize::Length4B
+        );
+
+        dr7.set_breakpoint(
+            DebugAddressRegisterNumber::Dr1,
+            BreakpointCondition::DataWrites,
+            BreakpointSize::Length4B,
+            false,
+        );
+        assert_eq!(dr7.bits(), 0x00d0_0000);
+    }
+}
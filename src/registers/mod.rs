@@ -4,6 +4,7 @@ pub mod control;
 pub mod debug;
 pub mod model_specific;
 pub mod mxcsr;
+pub mod pkru;
 pub mod rflags;
 pub mod segmentation;
 pub mod xcontrol;
@@ -1,10 +1,14 @@
 //! Functions to read and write model specific registers.
 
+use crate::VirtAddr;
 use bitflags::bitflags;
+use core::fmt;
 // imports for intra doc links
 #[cfg(doc)]
 use crate::registers::segmentation::{FS, GS};
 
+pub mod mtrr;
+
 /// A model specific register.
 #[cfg_attr(
     not(all(feature = "instructions", target_arch = "x86_64")),
@@ -19,6 +23,66 @@ impl Msr {
     pub const fn new(reg: u32) -> Msr {
         Msr(reg)
     }
+
+    /// Returns the number of the register.
+    #[inline]
+    pub const fn number(&self) -> u32 {
+        self.0
+    }
+
+    /// `IA32_TIME_STAMP_COUNTER`, the time stamp counter.
+    pub const IA32_TIME_STAMP_COUNTER: Msr = Msr(0x10);
+    /// `IA32_APIC_BASE`, see [`ApicBase`].
+    pub const IA32_APIC_BASE: Msr = Msr(0x1B);
+    /// `IA32_PAT`, see [`Pat`].
+    pub const IA32_PAT: Msr = Msr(0x277);
+    /// `IA32_EFER`, see [`Efer`].
+    pub const IA32_EFER: Msr = Msr(0xC000_0080);
+    /// `IA32_STAR`, see [`Star`].
+    pub const IA32_STAR: Msr = Msr(0xC000_0081);
+    /// `IA32_LSTAR`, see [`LStar`].
+    pub const IA32_LSTAR: Msr = Msr(0xC000_0082);
+    /// `IA32_FMASK`, see [`SFMask`].
+    pub const IA32_FMASK: Msr = Msr(0xC000_0084);
+    /// `IA32_FS_BASE`, see [`FsBase`].
+    pub const IA32_FS_BASE: Msr = Msr(0xC000_0100);
+    /// `IA32_GS_BASE`, see [`GsBase`].
+    pub const IA32_GS_BASE: Msr = Msr(0xC000_0101);
+    /// `IA32_KERNEL_GS_BASE`, see [`KernelGsBase`].
+    pub const IA32_KERNEL_GS_BASE: Msr = Msr(0xC000_0102);
+
+    /// Architectural registers with a fixed address on Intel and AMD processors.
+    ///
+    /// Like every MSR, each of these registers is only present if the corresponding
+    /// `cpuid` feature bit is set, e.g. `IA32_TIME_STAMP_COUNTER` requires
+    /// `CPUID.01H:EDX.TSC` and `IA32_PAT` requires `CPUID.01H:EDX.PAT`. Accessing a
+    /// register that is not present raises a general protection fault.
+    pub const ARCHITECTURAL: [Msr; 10] = [
+        Msr::IA32_TIME_STAMP_COUNTER,
+        Msr::IA32_APIC_BASE,
+        Msr::IA32_PAT,
+        Msr::IA32_EFER,
+        Msr::IA32_STAR,
+        Msr::IA32_LSTAR,
+        Msr::IA32_FMASK,
+        Msr::IA32_FS_BASE,
+        Msr::IA32_GS_BASE,
+        Msr::IA32_KERNEL_GS_BASE,
+    ];
+
+    /// Returns whether this register is one of the [`ARCHITECTURAL`](Msr::ARCHITECTURAL)
+    /// registers.
+    #[inline]
+    pub const fn is_architectural(&self) -> bool {
+        let mut i = 0;
+        while i < Self::ARCHITECTURAL.len() {
+            if Self::ARCHITECTURAL[i].0 == self.0 {
+                return true;
+            }
+            i += 1;
+        }
+        false
+    }
 }
 
 /// The Extended Feature Enable Register.
@@ -47,6 +111,43 @@ pub struct GsBase;
 #[derive(Debug)]
 pub struct KernelGsBase;
 
+/// The base address of the per-cpu data of the kernel.
+///
+/// While the kernel runs, the GS base points to its per-cpu data; while user
+/// code runs, the GS base belongs to user space and the per-cpu base is parked
+/// in [`KernelGsBase`]. Assembly entry stubs switch between the two with
+/// `swapgs`.
+#[cfg_attr(
+    all(feature = "instructions", target_arch = "x86_64"),
+    doc = "[`GsBase::swap`] executes it from Rust."
+)]
+/// Entry points written in Rust that do not touch GS before their first
+/// statement can instead program both registers from the stored base:
+///
+/// 1. `install` the base once per CPU before entering user mode for the first
+///    time.
+/// 2. On every `syscall` or interrupt from user mode, call `activate_on_entry`
+///    before accessing per-cpu data.
+/// 3. Before `sysret` or `iretq` to user mode, call `activate_on_exit`.
+///
+/// Interrupts that arrive while the kernel is running must do neither.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PerCpuBase(VirtAddr);
+
+impl PerCpuBase {
+    /// Creates a per-cpu base from the address of the per-cpu data.
+    #[inline]
+    pub const fn new(base: VirtAddr) -> Self {
+        Self(base)
+    }
+
+    /// Returns the address of the per-cpu data.
+    #[inline]
+    pub const fn base(self) -> VirtAddr {
+        self.0
+    }
+}
+
 /// Syscall Register: STAR
 #[derive(Debug)]
 pub struct Star;
@@ -77,6 +178,54 @@ pub struct Pat;
 #[derive(Debug)]
 pub struct ApicBase;
 
+/// IA32_TSC_DEADLINE: deadline of the local APIC timer in TSC-deadline mode
+#[derive(Debug)]
+pub struct TscDeadline;
+
+/// IA32_TSC_AUX: auxiliary value returned by `rdtscp` and `rdpid`
+#[derive(Debug)]
+pub struct TscAux;
+
+/// IA32_SYSENTER_CS: code segment used by `sysenter`
+///
+/// `sysenter` loads CS from this register and SS from this register + 8.
+/// `sysexit` loads the user CS from this register + 16 (or + 32 when returning
+/// to 64-bit mode) and the user SS from this register + 24 (or + 40).
+#[derive(Debug)]
+pub struct SysenterCs;
+
+/// IA32_SYSENTER_ESP: stack pointer loaded by `sysenter`
+#[derive(Debug)]
+pub struct SysenterEsp;
+
+/// IA32_SYSENTER_EIP: instruction pointer loaded by `sysenter`
+#[derive(Debug)]
+pub struct SysenterEip;
+
+/// IA32_PERFEVTSELn: event selection of general-purpose performance counter n
+///
+/// The event is identified by its event select and unit mask codes. Events
+/// enumerated by CPUID leaf 0AH, such as unhalted core cycles (`0x3C`/`0x00`)
+/// or instructions retired (`0xC0`/`0x00`), are architectural and have the same
+/// codes on all processors supporting them. All other codes are model specific
+/// and must be looked up for the exact processor model. The counter configured
+/// by this register can be read with `instructions::rdpmc::rdpmc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PerfEventSelect {
+    /// The event select code (bits 0 to 7).
+    pub event_select: u8,
+    /// The unit mask, which selects a sub-event of the event (bits 8 to 15).
+    pub umask: u8,
+    /// The control flags.
+    pub flags: PerfEventSelectFlags,
+    /// The counter mask (bits 24 to 31).
+    ///
+    /// If non-zero, the counter is only incremented in cycles in which the
+    /// event occurs at least this many times (or less often if
+    /// [`INVERT`](PerfEventSelectFlags::INVERT) is set).
+    pub counter_mask: u8,
+}
+
 impl Efer {
     /// The underlying model specific register.
     pub const MSR: Msr = Msr(0xC000_0080);
@@ -143,6 +292,74 @@ impl ApicBase {
     pub const MSR: Msr = Msr(0x1B);
 }
 
+impl TscDeadline {
+    /// The underlying model specific register.
+    pub const MSR: Msr = Msr(0x6E0);
+}
+
+impl TscAux {
+    /// The underlying model specific register.
+    pub const MSR: Msr = Msr(0xC000_0103);
+}
+
+impl SysenterCs {
+    /// The underlying model specific register.
+    pub const MSR: Msr = Msr(0x174);
+}
+
+impl SysenterEsp {
+    /// The underlying model specific register.
+    pub const MSR: Msr = Msr(0x175);
+}
+
+impl SysenterEip {
+    /// The underlying model specific register.
+    pub const MSR: Msr = Msr(0x176);
+}
+
+impl PerfEventSelect {
+    /// The underlying model specific register of counter 0. The register of
+    /// counter n is at `0x186 + n`.
+    pub const MSR: Msr = Msr(0x186);
+
+    /// Returns the model specific register of general-purpose counter `n`.
+    #[inline]
+    pub const fn msr(n: u8) -> Msr {
+        Msr(Self::MSR.0 + n as u32)
+    }
+
+    /// Creates a disabled event selection for the given event and unit mask.
+    #[inline]
+    pub const fn new(event_select: u8, umask: u8) -> Self {
+        Self {
+            event_select,
+            umask,
+            flags: PerfEventSelectFlags::empty(),
+            counter_mask: 0,
+        }
+    }
+
+    /// Decodes the raw value of the register.
+    #[inline]
+    pub const fn from_bits(bits: u64) -> Self {
+        Self {
+            event_select: bits as u8,
+            umask: (bits >> 8) as u8,
+            flags: PerfEventSelectFlags::from_bits_truncate(bits),
+            counter_mask: (bits >> 24) as u8,
+        }
+    }
+
+    /// Encodes the raw value of the register.
+    #[inline]
+    pub const fn bits(self) -> u64 {
+        self.event_select as u64
+            | (self.umask as u64) << 8
+            | self.flags.bits()
+            | (self.counter_mask as u64) << 24
+    }
+}
+
 bitflags! {
     /// Flags of the Extended Feature Enable Register.
     #[repr(transparent)]
@@ -153,6 +370,9 @@ bitflags! {
         /// Activates long mode, requires activating paging.
         const LONG_MODE_ENABLE = 1 << 8;
         /// Indicates that long mode is active.
+        ///
+        /// This bit is read-only: it is set by the processor when paging is
+        /// enabled with `LONG_MODE_ENABLE` set, and writes to it are ignored.
         const LONG_MODE_ACTIVE = 1 << 10;
         /// Enables the no-execute page-protection feature.
         const NO_EXECUTE_ENABLE = 1 << 11;
@@ -167,6 +387,9 @@ bitflags! {
     }
 }
 
+#[cfg(feature = "defmt")]
+impl_defmt_format_for_flags!(EferFlags);
+
 bitflags! {
     /// Flags stored in IA32_U_CET and IA32_S_CET (Table-2-2 in Intel SDM Volume
     /// 4). The Intel SDM-equivalent names are described in parentheses.
@@ -229,6 +452,47 @@ impl PatMemoryType {
     }
 }
 
+/// IA32_PAT contains a reserved memory type.
+///
+/// Contains the raw bits of the invalid entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidPatMemoryType(pub u8);
+
+impl fmt::Display for InvalidPatMemoryType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x} is not a valid PAT memory type", self.0)
+    }
+}
+
+bitflags! {
+    /// Control flags of the [`PerfEventSelect`] registers.
+    #[repr(transparent)]
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    pub struct PerfEventSelectFlags: u64 {
+        /// Count while the processor is running at privilege levels 1 to 3 (USR).
+        const USER_MODE = 1 << 16;
+        /// Count while the processor is running at privilege level 0 (OS).
+        const OS_MODE = 1 << 17;
+        /// Count rising edges of the event condition instead of cycles (E).
+        const EDGE_DETECT = 1 << 18;
+        /// Toggle the PMi pins when the event occurs (PC).
+        const PIN_CONTROL = 1 << 19;
+        /// Raise an interrupt through the local APIC when the counter overflows (INT).
+        const APIC_INTERRUPT = 1 << 20;
+        /// Enable the counter (EN).
+        const ENABLE = 1 << 22;
+        /// Invert the comparison against the counter mask (INV).
+        const INVERT = 1 << 23;
+    }
+}
+
+impl Default for PerfEventSelectFlags {
+    #[inline]
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
 bitflags! {
     /// Flags for the Advanced Programmable Interrupt Controler Base Register.
     #[repr(transparent)]
@@ -251,6 +515,7 @@ mod x86_64 {
     use super::*;
     use crate::addr::VirtAddr;
     use crate::registers::rflags::RFlags;
+    use crate::registers::segmentation::GS;
     use crate::structures::gdt::SegmentSelector;
     use crate::structures::paging::Page;
     use crate::structures::paging::PhysFrame;
@@ -271,10 +536,16 @@ mod x86_64 {
     impl Msr {
         /// Read 64 bits msr register.
         ///
+        /// This does not check whether the register exists: reading a register
+        /// that is not implemented by the processor, or executing this outside of
+        /// ring 0, raises a general protection fault. Use `cpuid` to check for
+        /// support first.
+        ///
         /// ## Safety
         ///
-        /// The caller must ensure that this read operation has no unsafe side
-        /// effects.
+        /// The caller must ensure that the register is implemented by the
+        /// processor (or that a #GP handler is installed that can recover from
+        /// the fault), and that this read operation has no unsafe side effects.
         #[inline]
         pub unsafe fn read(&self) -> u64 {
             let (high, low): (u32, u32);
@@ -291,10 +562,16 @@ mod x86_64 {
 
         /// Write 64 bits to msr register.
         ///
+        /// Like [`read`](Msr::read), this does not check whether the register
+        /// exists. Writing an unimplemented register, setting reserved bits or
+        /// writing a non-canonical address to an address register raises a
+        /// general protection fault.
+        ///
         /// ## Safety
         ///
-        /// The caller must ensure that this write operation has no unsafe side
-        /// effects.
+        /// The caller must ensure that the register is implemented by the
+        /// processor, that `value` is valid for it, and that this write
+        /// operation has no unsafe side effects.
         #[inline]
         pub unsafe fn write(&mut self, value: u64) {
             let low = value as u32;
@@ -334,15 +611,21 @@ mod x86_64 {
         /// safety with wrong flags, e.g. by disabling long mode.
         #[inline]
         pub unsafe fn write(flags: EferFlags) {
-            let old_value = Self::read_raw();
-            let reserved = old_value & !(EferFlags::all().bits());
-            let new_value = reserved | flags.bits();
+            let new_value = Self::merge(Self::read_raw(), flags);
 
             unsafe {
                 Self::write_raw(new_value);
             }
         }
 
+        /// Replaces the known flags in the raw value `old` with `flags`,
+        /// keeping all reserved bits.
+        #[inline]
+        fn merge(old: u64, flags: EferFlags) -> u64 {
+            let reserved = old & !(EferFlags::all().bits());
+            reserved | flags.bits()
+        }
+
         /// Write the EFER flags.
         ///
         /// Does not preserve any bits, including reserved fields.
@@ -420,6 +703,20 @@ mod x86_64 {
             let mut msr = Self::MSR;
             unsafe { msr.write(address.as_u64()) };
         }
+
+        /// Swap the GS base with [`KernelGsBase`] using `swapgs`.
+        ///
+        /// This is the same as [`GS::swap`], see there for details. See also
+        /// [`PerCpuBase`] for the sequence on kernel entry and exit.
+        ///
+        /// ## Safety
+        ///
+        /// Exactly one swap must happen on each transition between user mode
+        /// and kernel mode.
+        #[inline]
+        pub unsafe fn swap() {
+            unsafe { GS::swap() };
+        }
     }
 
     impl KernelGsBase {
@@ -437,6 +734,77 @@ mod x86_64 {
         }
     }
 
+    impl PerCpuBase {
+        /// Stores the base in [`KernelGsBase`], so that the next `swapgs`
+        /// makes it the active GS base.
+        ///
+        /// ## Safety
+        ///
+        /// This must be called in kernel mode while the user GS base is active,
+        /// i.e. outside of a swapped kernel entry. Otherwise the next `swapgs`
+        /// installs the wrong GS base.
+        #[inline]
+        pub unsafe fn install(self) {
+            KernelGsBase::write(self.0);
+        }
+
+        /// Returns whether this base is currently the active GS base.
+        #[inline]
+        pub fn is_active(self) -> bool {
+            GsBase::read() == self.0
+        }
+
+        /// Makes the per-cpu base the active GS base on kernel entry from user
+        /// mode and saves the user GS base in [`KernelGsBase`].
+        ///
+        /// This leaves the registers in the same state as a `swapgs` after
+        /// [`install`](Self::install), but does not depend on the current
+        /// content of [`KernelGsBase`]:
+        ///
+        /// ```no_run
+        /// use x86_64::registers::model_specific::PerCpuBase;
+        /// use x86_64::structures::idt::InterruptStackFrame;
+        /// use x86_64::PrivilegeLevel;
+        ///
+        /// fn handle_entry(per_cpu: PerCpuBase, frame: &InterruptStackFrame) {
+        ///     let from_user = frame.code_segment.rpl() == PrivilegeLevel::Ring3;
+        ///     if from_user {
+        ///         unsafe { per_cpu.activate_on_entry() };
+        ///     }
+        ///     // ... per-cpu data is now accessible through GS ...
+        ///     if from_user {
+        ///         unsafe { per_cpu.activate_on_exit() };
+        ///     }
+        /// }
+        /// ```
+        ///
+        /// ## Safety
+        ///
+        /// The caller must ensure that the processor just entered the kernel
+        /// from user mode and that the user GS base is still active, i.e. that
+        /// neither a swap nor another activation happened since.
+        #[inline]
+        pub unsafe fn activate_on_entry(self) {
+            KernelGsBase::write(GsBase::read());
+            GsBase::write(self.0);
+        }
+
+        /// Restores the user GS base saved by
+        /// [`activate_on_entry`](Self::activate_on_entry) before returning to
+        /// user mode and parks the per-cpu base in [`KernelGsBase`] again.
+        ///
+        /// ## Safety
+        ///
+        /// The caller must ensure that the per-cpu base is currently active
+        /// (i.e. [`activate_on_entry`](Self::activate_on_entry) was called on
+        /// this entry) and that the processor returns to user mode next.
+        #[inline]
+        pub unsafe fn activate_on_exit(self) {
+            GsBase::write(KernelGsBase::read());
+            KernelGsBase::write(self.0);
+        }
+    }
+
     impl Star {
         /// Read the Ring 0 and Ring 3 segment bases.
         /// The remaining fields are ignored because they are
@@ -512,6 +880,15 @@ mod x86_64 {
         /// This function will fail if the segment selectors are
         /// not in the correct offset of each other or if the
         /// segment selectors do not have correct privileges.
+        ///
+        /// `sysret` loads SS from the STAR field + 8 and CS from the field + 16,
+        /// so the user data segment must directly precede the user code segment
+        /// in the GDT and both selectors must have an RPL of 3. `syscall` loads
+        /// CS from its field and SS from the field + 8, so the kernel data
+        /// segment must directly follow the kernel code segment and both
+        /// selectors must have an RPL of 0. A GDT laid out as
+        /// `[null, kernel code, kernel data, user data, user code]` satisfies
+        /// these requirements.
         #[inline]
         pub fn write(
             cs_sysret: SegmentSelector,
@@ -519,6 +896,19 @@ mod x86_64 {
             cs_syscall: SegmentSelector,
             ss_syscall: SegmentSelector,
         ) -> Result<(), InvalidStarSegmentSelectors> {
+            let (sysret, syscall) = Self::encode(cs_sysret, ss_sysret, cs_syscall, ss_syscall)?;
+            unsafe { Self::write_raw(sysret, syscall) };
+
+            Ok(())
+        }
+
+        /// Validates the selectors and returns the raw SYSRET and SYSCALL fields.
+        fn encode(
+            cs_sysret: SegmentSelector,
+            ss_sysret: SegmentSelector,
+            cs_syscall: SegmentSelector,
+            ss_syscall: SegmentSelector,
+        ) -> Result<(u16, u16), InvalidStarSegmentSelectors> {
             // Convert to i32 to prevent underflows.
             let cs_sysret_cmp = i32::from(cs_sysret.0) - 16;
             let ss_sysret_cmp = i32::from(ss_sysret.0) - 8;
@@ -541,17 +931,21 @@ mod x86_64 {
                 return Err(InvalidStarSegmentSelectors::SyscallPrivilegeLevel);
             }
 
-            unsafe { Self::write_raw(ss_sysret.0 - 8, cs_syscall.0) };
-
-            Ok(())
+            Ok((ss_sysret.0 - 8, cs_syscall.0))
         }
     }
 
-    #[derive(Debug)]
+    /// An error returned by [`Star::write`] if the segment selectors do not
+    /// have the layout required by `syscall` and `sysret`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum InvalidStarSegmentSelectors {
+        /// The SYSRET CS selector is not 8 bytes after the SYSRET SS selector.
         SysretOffset,
+        /// The SYSCALL SS selector is not 8 bytes after the SYSCALL CS selector.
         SyscallOffset,
+        /// The SYSRET selectors do not have an RPL of 3.
         SysretPrivilegeLevel,
+        /// The SYSCALL selectors do not have an RPL of 0.
         SyscallPrivilegeLevel,
     }
 
@@ -729,11 +1123,23 @@ mod x86_64 {
         ///
         /// The PAT must be supported on the CPU, otherwise a general protection exception will
         /// occur. Support can be detected using the `cpuid` instruction.
+        ///
+        /// ## Panics
+        ///
+        /// Panics if an entry contains a reserved memory type. Use [`Pat::try_read`] to handle
+        /// this case.
         #[inline]
         pub fn read() -> [PatMemoryType; 8] {
-            unsafe { Self::MSR.read() }
-                .to_ne_bytes()
-                .map(|bits| PatMemoryType::from_bits(bits).unwrap())
+            Self::try_read().expect("IA32_PAT contains a reserved memory type")
+        }
+
+        /// Reads IA32_PAT, returning an error if an entry contains a reserved memory type.
+        ///
+        /// See [`Pat::read`] for details.
+        #[inline]
+        pub fn try_read() -> Result<[PatMemoryType; 8], InvalidPatMemoryType> {
+            let raw = unsafe { Self::MSR.read() };
+            Self::decode(raw)
         }
 
         /// Writes IA32_PAT.
@@ -748,11 +1154,67 @@ mod x86_64 {
         /// type.
         #[inline]
         pub unsafe fn write(table: [PatMemoryType; 8]) {
-            let bits = u64::from_ne_bytes(table.map(PatMemoryType::bits));
             let mut msr = Self::MSR;
             unsafe {
-                msr.write(bits);
+                msr.write(Self::encode(table));
+            }
+        }
+
+        /// Updates IA32_PAT.
+        ///
+        /// Returns an error without writing the register if it contains a reserved memory type.
+        ///
+        /// ## Safety
+        ///
+        /// See [`Pat::write`].
+        #[inline]
+        pub unsafe fn update<F>(f: F) -> Result<(), InvalidPatMemoryType>
+        where
+            F: FnOnce(&mut [PatMemoryType; 8]),
+        {
+            let mut table = Self::try_read()?;
+            f(&mut table);
+            unsafe {
+                Self::write(table);
             }
+            Ok(())
+        }
+
+        /// Sets the memory type of a single IA32_PAT entry.
+        ///
+        /// Returns an error without writing the register if it contains a reserved memory type.
+        ///
+        /// ## Panics
+        ///
+        /// Panics if `index` is not smaller than 8.
+        ///
+        /// ## Safety
+        ///
+        /// See [`Pat::write`].
+        #[inline]
+        pub unsafe fn write_entry(
+            index: usize,
+            memory_type: PatMemoryType,
+        ) -> Result<(), InvalidPatMemoryType> {
+            assert!(index < 8, "PAT index must be smaller than 8");
+            unsafe { Self::update(|table| table[index] = memory_type) }
+        }
+
+        /// Splits a raw IA32_PAT value into its entries, returning an error if any of
+        /// them contains a reserved memory type.
+        #[inline]
+        fn decode(raw: u64) -> Result<[PatMemoryType; 8], InvalidPatMemoryType> {
+            let mut table = Self::DEFAULT;
+            for (entry, bits) in table.iter_mut().zip(raw.to_le_bytes()) {
+                *entry = PatMemoryType::from_bits(bits).ok_or(InvalidPatMemoryType(bits))?;
+            }
+            Ok(table)
+        }
+
+        /// Assembles a raw IA32_PAT value from its entries.
+        #[inline]
+        fn encode(table: [PatMemoryType; 8]) -> u64 {
+            u64::from_le_bytes(table.map(PatMemoryType::bits))
         }
     }
 
@@ -760,30 +1222,43 @@ mod x86_64 {
         /// Reads the IA32_APIC_BASE MSR.
         #[inline]
         pub fn read() -> (PhysFrame, ApicBaseFlags) {
-            let (frame, flags) = Self::read_raw();
+            let (frame, flags) = Self::read_raw_flags();
             (frame, ApicBaseFlags::from_bits_truncate(flags))
         }
 
         /// Reads the raw IA32_APIC_BASE MSR.
+        ///
+        /// The returned raw value is unmodified, i.e. it still contains the base address.
         #[inline]
         pub fn read_raw() -> (PhysFrame, u64) {
             let raw = unsafe { Self::MSR.read() };
-            // extract bits 12 - 51 (incl.)
-            let addr = PhysAddr::new_truncate(raw);
-            let frame = PhysFrame::containing_address(addr);
-            (frame, raw)
+            (Self::decode(raw).0, raw)
+        }
+
+        /// Reads the IA32_APIC_BASE MSR and splits it into the base frame and the remaining raw
+        /// bits, i.e. the flags and reserved fields without the base address.
+        #[inline]
+        pub fn read_raw_flags() -> (PhysFrame, u64) {
+            let raw = unsafe { Self::MSR.read() };
+            Self::decode(raw)
         }
 
         /// Writes the IA32_APIC_BASE MSR preserving reserved values.
         ///
         /// Preserves the value of reserved fields.
         ///
+        /// The local APIC can only be switched from xAPIC to x2APIC mode while it is enabled, i.e.
+        /// [`ApicBaseFlags::LAPIC_ENABLE`] must already be set. Switching from x2APIC mode back to
+        /// xAPIC mode requires disabling the local APIC first (clearing both
+        /// [`ApicBaseFlags::LAPIC_ENABLE`] and [`ApicBaseFlags::X2APIC_ENABLE`]). Invalid
+        /// transitions cause a general protection exception.
+        ///
         /// ## Safety
         ///
         /// Unsafe because changing the APIC base address allows hijacking a page of physical memory space in ways that would violate Rust's memory rules.
         #[inline]
         pub unsafe fn write(frame: PhysFrame, flags: ApicBaseFlags) {
-            let (_, old_flags) = Self::read_raw();
+            let (_, old_flags) = Self::read_raw_flags();
             let reserved = old_flags & !(ApicBaseFlags::all().bits());
             let new_flags = reserved | flags.bits();
 
@@ -801,11 +1276,341 @@ mod x86_64 {
         /// Unsafe because it's possible to set reserved bits to `1` and changing the APIC base address allows hijacking a page of physical memory space in ways that would violate Rust's memory rules.
         #[inline]
         pub unsafe fn write_raw(frame: PhysFrame, flags: u64) {
-            let addr = frame.start_address();
             let mut msr = Self::MSR;
             unsafe {
-                msr.write(flags | addr.as_u64());
+                msr.write(Self::encode(frame, flags));
             }
         }
+
+        /// Bits 12 - 51 (incl.) containing the APIC base address.
+        const ADDRESS_MASK: u64 = 0x000f_ffff_ffff_f000;
+
+        /// Splits a raw IA32_APIC_BASE value into the base frame and the remaining bits.
+        #[inline]
+        fn decode(raw: u64) -> (PhysFrame, u64) {
+            let frame = PhysFrame::containing_address(PhysAddr::new(raw & Self::ADDRESS_MASK));
+            (frame, raw & !Self::ADDRESS_MASK)
+        }
+
+        /// Assembles a raw IA32_APIC_BASE value from the base frame and the remaining bits.
+        #[inline]
+        fn encode(frame: PhysFrame, flags: u64) -> u64 {
+            flags | frame.start_address().as_u64()
+        }
+    }
+
+    impl TscDeadline {
+        /// Read the current deadline.
+        ///
+        /// Returns 0 if the timer is disarmed or has already fired.
+        #[inline]
+        pub fn read() -> u64 {
+            unsafe { Self::MSR.read() }
+        }
+
+        /// Write the deadline of the local APIC timer.
+        ///
+        /// `deadline` is an absolute value of the time stamp counter (see
+        /// [`read_tsc`](crate::instructions::tsc::read_tsc)), not a relative
+        /// delay. If the LVT timer register of the local APIC is in
+        /// TSC-deadline mode, this arms a one-shot timer that fires once the
+        /// counter reaches `deadline`; a deadline in the past fires immediately.
+        /// Writing 0 disarms the timer.
+        ///
+        /// ## Safety
+        ///
+        /// The caller must ensure that the processor supports the TSC-deadline
+        /// timer (CPUID.01H:ECX.TSC_Deadline\[bit 24\]) and that an interrupt
+        /// handler is installed for the timer vector.
+        #[inline]
+        pub unsafe fn write(deadline: u64) {
+            let mut msr = Self::MSR;
+            unsafe { msr.write(deadline) };
+        }
+    }
+
+    impl TscAux {
+        /// Read the value returned by `rdtscp` and `rdpid`.
+        #[inline]
+        pub fn read() -> u32 {
+            Self::decode(unsafe { Self::MSR.read() })
+        }
+
+        /// Write the value returned by `rdtscp` and `rdpid`.
+        ///
+        /// This is usually set to the id of the current processor.
+        #[inline]
+        pub fn write(value: u32) {
+            let mut msr = Self::MSR;
+            unsafe { msr.write(Self::encode(value)) };
+        }
+
+        fn decode(raw: u64) -> u32 {
+            raw.get_bits(0..32) as u32
+        }
+
+        fn encode(value: u32) -> u64 {
+            u64::from(value)
+        }
+    }
+
+    impl SysenterCs {
+        /// Read the code segment selector used by `sysenter`.
+        #[inline]
+        pub fn read() -> SegmentSelector {
+            Self::decode(unsafe { Self::MSR.read() })
+        }
+
+        /// Write the code segment selector used by `sysenter`.
+        ///
+        /// Writing a null selector disables `sysenter`, which then raises a
+        /// general protection fault.
+        ///
+        /// ## Safety
+        ///
+        /// The caller must ensure that the GDT contains the kernel code and
+        /// data segments and the user segments at the offsets expected by
+        /// `sysenter` and `sysexit`.
+        #[inline]
+        pub unsafe fn write(selector: SegmentSelector) {
+            let mut msr = Self::MSR;
+            unsafe { msr.write(Self::encode(selector)) };
+        }
+
+        fn decode(raw: u64) -> SegmentSelector {
+            SegmentSelector(raw.get_bits(0..16) as u16)
+        }
+
+        fn encode(selector: SegmentSelector) -> u64 {
+            u64::from(selector.0)
+        }
+    }
+
+    impl SysenterEsp {
+        /// Read the stack pointer loaded by `sysenter`.
+        #[inline]
+        pub fn read() -> VirtAddr {
+            VirtAddr::new(unsafe { Self::MSR.read() })
+        }
+
+        /// Write the stack pointer loaded by `sysenter`.
+        ///
+        /// ## Safety
+        ///
+        /// The caller must ensure that `address` points to the top of a valid
+        /// kernel stack.
+        #[inline]
+        pub unsafe fn write(address: VirtAddr) {
+            let mut msr = Self::MSR;
+            unsafe { msr.write(address.as_u64()) };
+        }
+    }
+
+    impl SysenterEip {
+        /// Read the instruction pointer loaded by `sysenter`.
+        #[inline]
+        pub fn read() -> VirtAddr {
+            VirtAddr::new(unsafe { Self::MSR.read() })
+        }
+
+        /// Write the instruction pointer loaded by `sysenter`.
+        ///
+        /// ## Safety
+        ///
+        /// The caller must ensure that `address` points to a valid system call
+        /// entry point.
+        #[inline]
+        pub unsafe fn write(address: VirtAddr) {
+            let mut msr = Self::MSR;
+            unsafe { msr.write(address.as_u64()) };
+        }
+    }
+
+    impl PerfEventSelect {
+        /// Read the event selection of general-purpose counter `n`.
+        ///
+        /// The number of general-purpose counters is reported in
+        /// CPUID.0AH:EAX\[bits 15:8\]. Accessing a register beyond that count
+        /// raises a general protection fault.
+        #[inline]
+        pub fn read(n: u8) -> Self {
+            Self::from_bits(unsafe { Self::msr(n).read() })
+        }
+
+        /// Write the event selection of general-purpose counter `n`.
+        ///
+        /// ## Safety
+        ///
+        /// The caller must ensure that counter `n` exists and that the event
+        /// codes are valid for this processor. Setting
+        /// [`APIC_INTERRUPT`](PerfEventSelectFlags::APIC_INTERRUPT) requires an
+        /// interrupt handler for the performance monitoring vector of the local
+        /// APIC.
+        #[inline]
+        pub unsafe fn write(n: u8, value: Self) {
+            let mut msr = Self::msr(n);
+            unsafe { msr.write(value.bits()) };
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn tsc_aux_encoding() {
+            assert_eq!(TscAux::encode(0x1234_5678), 0x1234_5678);
+            assert_eq!(TscAux::decode(TscAux::encode(u32::MAX)), u32::MAX);
+            // The upper 32 bits are reserved.
+            assert_eq!(TscAux::decode(0xffff_ffff_0000_0007), 7);
+        }
+
+        #[test]
+        fn sysenter_encoding() {
+            let selector = SegmentSelector::new(1, PrivilegeLevel::Ring0);
+            assert_eq!(SysenterCs::encode(selector), 0x08);
+            assert_eq!(SysenterCs::decode(0x08), selector);
+            // Bits above the selector are ignored.
+            assert_eq!(SysenterCs::decode(0xdead_0000_0000_0008), selector);
+        }
+
+        #[test]
+        fn pat_default_encoding() {
+            assert_eq!(Pat::encode(Pat::DEFAULT), 0x0007_0406_0007_0406);
+            assert_eq!(Pat::decode(0x0007_0406_0007_0406), Ok(Pat::DEFAULT));
+            // 0x02 and 0x03 are reserved memory types.
+            assert_eq!(
+                Pat::decode(0x0007_0406_0007_0402),
+                Err(InvalidPatMemoryType(0x02))
+            );
+        }
+
+        #[test]
+        fn efer_bits() {
+            assert_eq!(EferFlags::SYSTEM_CALL_EXTENSIONS.bits(), 1 << 0);
+            assert_eq!(EferFlags::LONG_MODE_ENABLE.bits(), 1 << 8);
+            assert_eq!(EferFlags::LONG_MODE_ACTIVE.bits(), 1 << 10);
+            assert_eq!(EferFlags::NO_EXECUTE_ENABLE.bits(), 1 << 11);
+            assert_eq!(EferFlags::SECURE_VIRTUAL_MACHINE_ENABLE.bits(), 1 << 12);
+            assert_eq!(EferFlags::LONG_MODE_SEGMENT_LIMIT_ENABLE.bits(), 1 << 13);
+            assert_eq!(EferFlags::FAST_FXSAVE_FXRSTOR.bits(), 1 << 14);
+            assert_eq!(EferFlags::TRANSLATION_CACHE_EXTENSION.bits(), 1 << 15);
+        }
+
+        #[test]
+        fn efer_merge_preserves_reserved() {
+            // Bits 1 and 16 are reserved.
+            let old = (1 << 16) | (1 << 1) | (1 << 11) | (1 << 10) | (1 << 8);
+            let flags = EferFlags::from_bits_truncate(old) | EferFlags::SYSTEM_CALL_EXTENSIONS;
+            assert_eq!(Efer::merge(old, flags), old | 1);
+            assert_eq!(Efer::merge(old, EferFlags::empty()), (1 << 16) | (1 << 1));
+        }
+
+        #[test]
+        fn star_encode() {
+            let kernel_cs = SegmentSelector::new(1, PrivilegeLevel::Ring0);
+            let kernel_ss = SegmentSelector::new(2, PrivilegeLevel::Ring0);
+            let user_ss = SegmentSelector::new(3, PrivilegeLevel::Ring3);
+            let user_cs = SegmentSelector::new(4, PrivilegeLevel::Ring3);
+            assert_eq!(
+                Star::encode(user_cs, user_ss, kernel_cs, kernel_ss),
+                Ok((0x13, 0x08))
+            );
+
+            // User code before user data, as for `sysexit`.
+            assert_eq!(
+                Star::encode(user_ss, user_cs, kernel_cs, kernel_ss),
+                Err(InvalidStarSegmentSelectors::SysretOffset)
+            );
+            assert_eq!(
+                Star::encode(user_cs, user_ss, kernel_ss, kernel_cs),
+                Err(InvalidStarSegmentSelectors::SyscallOffset)
+            );
+            assert_eq!(
+                Star::encode(
+                    SegmentSelector::new(4, PrivilegeLevel::Ring0),
+                    SegmentSelector::new(3, PrivilegeLevel::Ring0),
+                    kernel_cs,
+                    kernel_ss
+                ),
+                Err(InvalidStarSegmentSelectors::SysretPrivilegeLevel)
+            );
+        }
+
+        #[test]
+        fn apic_base_round_trip() {
+            let raw = 0xfee0_0000 | (1 << 11) | (1 << 8);
+            let (frame, flags) = ApicBase::decode(raw);
+            assert_eq!(frame.start_address(), PhysAddr::new(0xfee0_0000));
+            assert_eq!(
+                ApicBaseFlags::from_bits_truncate(flags),
+                ApicBaseFlags::LAPIC_ENABLE | ApicBaseFlags::BSP
+            );
+            assert_eq!(ApicBase::encode(frame, flags), raw);
+
+            // Relocating the base must not keep any bits of the old address.
+            let new_frame = PhysFrame::containing_address(PhysAddr::new(0x1234_5000));
+            assert_eq!(
+                ApicBase::encode(new_frame, flags),
+                0x1234_5000 | (1 << 11) | (1 << 8)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn architectural_registry() {
+        assert_eq!(Msr::IA32_TIME_STAMP_COUNTER.number(), 0x10);
+        assert_eq!(Msr::IA32_APIC_BASE.number(), ApicBase::MSR.number());
+        assert_eq!(Msr::IA32_PAT.number(), Pat::MSR.number());
+        assert_eq!(Msr::IA32_EFER.number(), Efer::MSR.number());
+        assert_eq!(Msr::IA32_STAR.number(), Star::MSR.number());
+        assert_eq!(Msr::IA32_LSTAR.number(), LStar::MSR.number());
+        assert_eq!(Msr::IA32_FMASK.number(), SFMask::MSR.number());
+        assert_eq!(Msr::IA32_FS_BASE.number(), FsBase::MSR.number());
+        assert_eq!(Msr::IA32_GS_BASE.number(), GsBase::MSR.number());
+        assert_eq!(
+            Msr::IA32_KERNEL_GS_BASE.number(),
+            KernelGsBase::MSR.number()
+        );
+
+        assert!(Msr::ARCHITECTURAL.iter().all(Msr::is_architectural));
+        assert!(Efer::MSR.is_architectural());
+        assert!(!UCet::MSR.is_architectural());
+        assert!(!TscAux::MSR.is_architectural());
+    }
+
+    #[test]
+    fn perf_event_select_encoding() {
+        assert_eq!(PerfEventSelect::msr(0).number(), 0x186);
+        assert_eq!(PerfEventSelect::msr(3).number(), 0x189);
+
+        // Unhalted core cycles in all privilege levels.
+        let cycles = PerfEventSelect {
+            flags: PerfEventSelectFlags::USER_MODE
+                | PerfEventSelectFlags::OS_MODE
+                | PerfEventSelectFlags::ENABLE,
+            ..PerfEventSelect::new(0x3c, 0x00)
+        };
+        assert_eq!(cycles.bits(), 0x0043_003c);
+        assert_eq!(PerfEventSelect::from_bits(cycles.bits()), cycles);
+
+        let raw = 0x02d1_01c0 | (1 << 32);
+        let decoded = PerfEventSelect::from_bits(raw);
+        assert_eq!(decoded.event_select, 0xc0);
+        assert_eq!(decoded.umask, 0x01);
+        assert_eq!(decoded.counter_mask, 0x02);
+        assert_eq!(
+            decoded.flags,
+            PerfEventSelectFlags::USER_MODE
+                | PerfEventSelectFlags::APIC_INTERRUPT
+                | PerfEventSelectFlags::ENABLE
+                | PerfEventSelectFlags::INVERT
+        );
+        assert_eq!(decoded.bits(), raw & 0xffff_ffff);
     }
 }
@@ -0,0 +1,432 @@
+//! Memory Type Range Registers (MTRRs).
+//!
+//! MTRRs assign memory types to ranges of physical memory. They are usually
+//! configured by the firmware, so this is only needed in environments without
+//! one. See Intel 3a, Section 12.11 "Memory Type Range Registers (MTRRs)".
+
+use super::Msr;
+use crate::PhysAddr;
+use bitflags::bitflags;
+use core::fmt;
+
+/// IA32_MTRRCAP: MTRR capabilities.
+#[derive(Debug)]
+pub struct MtrrCap;
+
+/// IA32_MTRR_DEF_TYPE: default memory type and MTRR enable flags.
+#[derive(Debug)]
+pub struct MtrrDefType;
+
+/// IA32_MTRR_PHYSBASEn and IA32_MTRR_PHYSMASKn: variable range MTRRs.
+#[derive(Debug)]
+pub struct MtrrVariable;
+
+impl MtrrCap {
+    /// The underlying model specific register.
+    pub const MSR: Msr = Msr(0xFE);
+}
+
+impl MtrrDefType {
+    /// The underlying model specific register.
+    pub const MSR: Msr = Msr(0x2FF);
+}
+
+impl MtrrVariable {
+    /// Returns the IA32_MTRR_PHYSBASEn register of the variable range with the given index.
+    #[inline]
+    pub const fn base_msr(index: u8) -> Msr {
+        Msr(0x200 + 2 * index as u32)
+    }
+
+    /// Returns the IA32_MTRR_PHYSMASKn register of the variable range with the given index.
+    #[inline]
+    pub const fn mask_msr(index: u8) -> Msr {
+        Msr(0x201 + 2 * index as u32)
+    }
+}
+
+/// Memory types used in the MTRRs.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+#[repr(u8)]
+pub enum MtrrMemoryType {
+    /// Uncacheable (UC).
+    Uncacheable = 0x00,
+    /// Uses a write combining (WC) cache policy.
+    WriteCombining = 0x01,
+    /// Uses a write through (WT) cache policy.
+    WriteThrough = 0x04,
+    /// Uses a write protected (WP) cache policy.
+    WriteProtected = 0x05,
+    /// Uses a write back (WB) cache policy.
+    WriteBack = 0x06,
+}
+
+impl MtrrMemoryType {
+    /// Converts from bits, returning `None` if the value is invalid.
+    pub const fn from_bits(bits: u8) -> Option<Self> {
+        match bits {
+            0x00 => Some(Self::Uncacheable),
+            0x01 => Some(Self::WriteCombining),
+            0x04 => Some(Self::WriteThrough),
+            0x05 => Some(Self::WriteProtected),
+            0x06 => Some(Self::WriteBack),
+            _ => None,
+        }
+    }
+
+    /// Converts from bits, returning an error if the value is invalid.
+    pub const fn try_from_bits(bits: u8) -> Result<Self, InvalidMtrrMemoryType> {
+        match Self::from_bits(bits) {
+            Some(memory_type) => Ok(memory_type),
+            None => Err(InvalidMtrrMemoryType(bits)),
+        }
+    }
+
+    /// Gets the underlying bits.
+    pub const fn bits(self) -> u8 {
+        self as u8
+    }
+}
+
+bitflags! {
+    /// Capability flags of the IA32_MTRRCAP register.
+    #[repr(transparent)]
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    pub struct MtrrCapFlags: u64 {
+        /// Fixed range MTRRs are supported.
+        const FIXED_RANGE = 1 << 8;
+        /// The write combining memory type is supported.
+        const WRITE_COMBINING = 1 << 10;
+        /// The system management range register (SMRR) interface is supported.
+        const SMRR = 1 << 11;
+    }
+}
+
+bitflags! {
+    /// Flags of the IA32_MTRR_DEF_TYPE register.
+    #[repr(transparent)]
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    pub struct MtrrDefTypeFlags: u64 {
+        /// Enables the fixed range MTRRs. Has no effect unless
+        /// [`MTRR_ENABLE`](MtrrDefTypeFlags::MTRR_ENABLE) is also set.
+        const FIXED_RANGE_ENABLE = 1 << 10;
+        /// Enables the MTRRs. If unset, all of physical memory is uncacheable.
+        const MTRR_ENABLE = 1 << 11;
+    }
+}
+
+/// A variable range MTRR, consisting of an IA32_MTRR_PHYSBASEn and
+/// IA32_MTRR_PHYSMASKn register pair.
+///
+/// A physical address `addr` is part of the range if
+/// `addr & mask == base & mask`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MtrrVariableRange {
+    /// The physical base address of the range.
+    pub base: PhysAddr,
+    /// The address mask of the range. Only bits 12 up to the physical address
+    /// width of the processor are used.
+    pub mask: u64,
+    /// The memory type of the range.
+    pub memory_type: MtrrMemoryType,
+    /// Whether the range is enabled.
+    pub valid: bool,
+}
+
+impl MtrrVariableRange {
+    const ADDRESS_MASK: u64 = 0x000f_ffff_ffff_f000;
+    const VALID: u64 = 1 << 11;
+
+    /// Creates a valid range covering `size` bytes starting at `base`.
+    ///
+    /// `phys_addr_bits` is the physical address width of the processor
+    /// (MAXPHYADDR), which can be determined using CPUID.80000008H:EAX\[7:0\].
+    ///
+    /// The size must be a power of two of at least 4 KiB and `base` must be
+    /// aligned to it.
+    pub fn new(
+        base: PhysAddr,
+        size: u64,
+        memory_type: MtrrMemoryType,
+        phys_addr_bits: u8,
+    ) -> Result<Self, InvalidMtrrRange> {
+        if !size.is_power_of_two() || size < 4096 {
+            return Err(InvalidMtrrRange::InvalidSize(size));
+        }
+        if !base.is_aligned(size) {
+            return Err(InvalidMtrrRange::UnalignedBase(base));
+        }
+
+        let phys_mask = 1u64
+            .checked_shl(u32::from(phys_addr_bits))
+            .map_or(u64::MAX, |bit| bit - 1);
+        let mask = !(size - 1) & phys_mask & Self::ADDRESS_MASK;
+        Ok(MtrrVariableRange {
+            base,
+            mask,
+            memory_type,
+            valid: true,
+        })
+    }
+
+    /// Decodes the raw values of the IA32_MTRR_PHYSBASEn and
+    /// IA32_MTRR_PHYSMASKn registers, returning an error if the memory type is
+    /// invalid.
+    pub fn from_raw(base: u64, mask: u64) -> Result<Self, InvalidMtrrMemoryType> {
+        Ok(MtrrVariableRange {
+            base: PhysAddr::new(base & Self::ADDRESS_MASK),
+            mask: mask & Self::ADDRESS_MASK,
+            memory_type: MtrrMemoryType::try_from_bits(base as u8)?,
+            valid: mask & Self::VALID != 0,
+        })
+    }
+
+    /// Encodes the range into the raw values of the IA32_MTRR_PHYSBASEn and
+    /// IA32_MTRR_PHYSMASKn registers.
+    pub fn to_raw(self) -> (u64, u64) {
+        let base = self.base.as_u64() | u64::from(self.memory_type.bits());
+        let valid = if self.valid { Self::VALID } else { 0 };
+        (base, (self.mask & Self::ADDRESS_MASK) | valid)
+    }
+}
+
+/// An MTRR contains a reserved memory type.
+///
+/// Contains the raw bits of the invalid memory type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidMtrrMemoryType(pub u8);
+
+impl fmt::Display for InvalidMtrrMemoryType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x} is not a valid MTRR memory type", self.0)
+    }
+}
+
+/// A variable range MTRR could not be created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidMtrrRange {
+    /// The size is not a power of two of at least 4 KiB.
+    InvalidSize(u64),
+    /// The base address is not aligned to the size.
+    UnalignedBase(PhysAddr),
+    /// The index is not smaller than the number of variable ranges.
+    InvalidIndex(u8),
+}
+
+impl fmt::Display for InvalidMtrrRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSize(size) => {
+                write!(f, "MTRR size must be a power of two >= 4096, got {size:#x}")
+            }
+            Self::UnalignedBase(base) => {
+                write!(
+                    f,
+                    "MTRR base {:#x} is not aligned to its size",
+                    base.as_u64()
+                )
+            }
+            Self::InvalidIndex(index) => write!(f, "no variable range MTRR with index {index}"),
+        }
+    }
+}
+
+#[cfg(all(feature = "instructions", target_arch = "x86_64"))]
+mod x86_64 {
+    use super::*;
+    use crate::instructions::cpuid::{cpuid, max_leaf};
+
+    impl MtrrCap {
+        /// Reads the number of variable ranges (VCNT) and the capability flags.
+        #[inline]
+        pub fn read() -> (u8, MtrrCapFlags) {
+            let raw = Self::read_raw();
+            (raw as u8, MtrrCapFlags::from_bits_truncate(raw))
+        }
+
+        /// Reads the raw IA32_MTRRCAP register.
+        #[inline]
+        pub fn read_raw() -> u64 {
+            unsafe { Self::MSR.read() }
+        }
+    }
+
+    impl MtrrDefType {
+        /// Reads the default memory type and the enable flags.
+        ///
+        /// Returns an error if the register contains a reserved memory type.
+        #[inline]
+        pub fn read() -> Result<(MtrrMemoryType, MtrrDefTypeFlags), InvalidMtrrMemoryType> {
+            let raw = Self::read_raw();
+            let memory_type = MtrrMemoryType::try_from_bits(raw as u8)?;
+            Ok((memory_type, MtrrDefTypeFlags::from_bits_truncate(raw)))
+        }
+
+        /// Reads the raw IA32_MTRR_DEF_TYPE register.
+        #[inline]
+        pub fn read_raw() -> u64 {
+            unsafe { Self::MSR.read() }
+        }
+
+        /// Writes the default memory type and the enable flags.
+        ///
+        /// Preserves the value of reserved fields.
+        ///
+        /// ## Safety
+        ///
+        /// Changing memory types can break memory safety, e.g. by making memory
+        /// mapped devices cacheable. Caches must be flushed and the MTRRs should
+        /// only be changed as described in Intel 3a, Section 12.11.7.2 "MemTypeSet() Function".
+        #[inline]
+        pub unsafe fn write(memory_type: MtrrMemoryType, flags: MtrrDefTypeFlags) {
+            let old_value = Self::read_raw();
+            let reserved = old_value & !(0xff | MtrrDefTypeFlags::all().bits());
+            let new_value = reserved | flags.bits() | u64::from(memory_type.bits());
+            unsafe {
+                Self::write_raw(new_value);
+            }
+        }
+
+        /// Writes the raw IA32_MTRR_DEF_TYPE register.
+        ///
+        /// Does not preserve any bits, including reserved fields.
+        ///
+        /// ## Safety
+        ///
+        /// See [`MtrrDefType::write`].
+        #[inline]
+        pub unsafe fn write_raw(value: u64) {
+            let mut msr = Self::MSR;
+            unsafe {
+                msr.write(value);
+            }
+        }
+    }
+
+    impl MtrrVariable {
+        /// Reads the variable range with the given index.
+        ///
+        /// Returns an error if IA32_MTRR_PHYSBASEn contains a reserved memory type.
+        ///
+        /// ## Panics
+        ///
+        /// Panics if `index` is not smaller than the number of variable ranges
+        /// reported by [`MtrrCap::read`].
+        #[inline]
+        pub fn read(index: u8) -> Result<MtrrVariableRange, InvalidMtrrMemoryType> {
+            Self::check_index(index).unwrap();
+            let base = unsafe { Self::base_msr(index).read() };
+            let mask = unsafe { Self::mask_msr(index).read() };
+            MtrrVariableRange::from_raw(base, mask)
+        }
+
+        /// Writes the variable range with the given index.
+        ///
+        /// ## Panics
+        ///
+        /// Panics if `index` is not smaller than the number of variable ranges
+        /// reported by [`MtrrCap::read`].
+        ///
+        /// ## Safety
+        ///
+        /// The mask must not contain bits above the physical address width of the
+        /// processor. See also [`MtrrDefType::write`].
+        #[inline]
+        pub unsafe fn write(index: u8, range: MtrrVariableRange) {
+            Self::check_index(index).unwrap();
+            let (base, mask) = range.to_raw();
+            unsafe {
+                Self::base_msr(index).write(base);
+                Self::mask_msr(index).write(mask);
+            }
+        }
+
+        /// Configures the variable range with the given index to cover `size` bytes
+        /// starting at `base` with the given memory type.
+        ///
+        /// Returns an error if the size is not a power of two of at least 4 KiB, if
+        /// `base` is not aligned to it, or if `index` is not smaller than the number of
+        /// variable ranges reported by [`MtrrCap::read`].
+        ///
+        /// ## Safety
+        ///
+        /// See [`MtrrDefType::write`].
+        #[inline]
+        pub unsafe fn set_variable_range(
+            index: u8,
+            base: PhysAddr,
+            size: u64,
+            memory_type: MtrrMemoryType,
+        ) -> Result<(), InvalidMtrrRange> {
+            Self::check_index(index)?;
+            let range = MtrrVariableRange::new(base, size, memory_type, phys_addr_bits())?;
+            unsafe {
+                Self::write(index, range);
+            }
+            Ok(())
+        }
+
+        fn check_index(index: u8) -> Result<(), InvalidMtrrRange> {
+            let (count, _) = MtrrCap::read();
+            if index < count {
+                Ok(())
+            } else {
+                Err(InvalidMtrrRange::InvalidIndex(index))
+            }
+        }
+    }
+
+    /// Returns the physical address width of the processor (MAXPHYADDR), or 36 if
+    /// the processor doesn't report it.
+    fn phys_addr_bits() -> u8 {
+        if max_leaf(0x8000_0000) >= 0x8000_0008 {
+            cpuid(0x8000_0008, 0).eax as u8
+        } else {
+            36
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variable_range_mask() {
+        let range = MtrrVariableRange::new(
+            PhysAddr::new(0xc000_0000),
+            0x20_0000,
+            MtrrMemoryType::WriteCombining,
+            36,
+        )
+        .unwrap();
+        assert_eq!(range.mask, 0xf_ffe0_0000);
+        assert_eq!(range.to_raw(), (0xc000_0001, 0xf_ffe0_0800));
+        assert_eq!(
+            MtrrVariableRange::from_raw(0xc000_0001, 0xf_ffe0_0800),
+            Ok(range)
+        );
+        // 0x02 and 0x03 are reserved memory types.
+        assert_eq!(
+            MtrrVariableRange::from_raw(0xc000_0002, 0xf_ffe0_0800),
+            Err(InvalidMtrrMemoryType(0x02))
+        );
+    }
+
+    #[test]
+    fn variable_range_invalid() {
+        let base = PhysAddr::new(0xc010_0000);
+        assert_eq!(
+            MtrrVariableRange::new(base, 0x20_0000, MtrrMemoryType::WriteBack, 36),
+            Err(InvalidMtrrRange::UnalignedBase(base))
+        );
+        assert_eq!(
+            MtrrVariableRange::new(base, 0x30_0000, MtrrMemoryType::WriteBack, 36),
+            Err(InvalidMtrrRange::InvalidSize(0x30_0000))
+        );
+        assert_eq!(
+            MtrrVariableRange::new(base, 0x800, MtrrMemoryType::WriteBack, 36),
+            Err(InvalidMtrrRange::InvalidSize(0x800))
+        );
+    }
+}
@@ -60,6 +60,40 @@ impl Default for MxCsr {
     }
 }
 
+/// The rounding mode used by SSE instructions, stored in bits 13 and 14 of MXCSR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum RoundingMode {
+    /// Round to nearest (even). This is the default.
+    Nearest = 0,
+    /// Round down, toward negative infinity.
+    Down = 1,
+    /// Round up, toward positive infinity.
+    Up = 2,
+    /// Round toward zero (truncate).
+    TowardZero = 3,
+}
+
+impl MxCsr {
+    /// Returns the rounding mode.
+    #[inline]
+    pub const fn rounding_mode(self) -> RoundingMode {
+        match (self.bits() >> 13) & 0b11 {
+            0 => RoundingMode::Nearest,
+            1 => RoundingMode::Down,
+            2 => RoundingMode::Up,
+            _ => RoundingMode::TowardZero,
+        }
+    }
+
+    /// Sets the rounding mode without changing any other flags.
+    #[inline]
+    pub fn set_rounding_mode(&mut self, mode: RoundingMode) {
+        self.remove(MxCsr::ROUNDING_CONTROL_ZERO);
+        self.insert(MxCsr::from_bits_truncate(u32::from(mode as u8) << 13));
+    }
+}
+
 #[cfg(all(feature = "instructions", target_arch = "x86_64"))]
 mod x86_64 {
     use super::*;
@@ -94,6 +128,12 @@ mod x86_64 {
         self::write(mxcsr);
     }
 
+    /// Sets the rounding mode in MXCSR without changing any other flags.
+    #[inline]
+    pub fn set_rounding_mode(mode: RoundingMode) {
+        self::update(|mxcsr| mxcsr.set_rounding_mode(mode));
+    }
+
     #[cfg(test)]
     mod test {
         use crate::registers::mxcsr::*;
@@ -116,5 +156,52 @@ mod x86_64 {
             write(mxcsr);
             assert_eq!(mxcsr, read());
         }
+
+        #[test]
+        fn mxcsr_set_rounding_mode() {
+            let mxcsr = read();
+            set_rounding_mode(RoundingMode::TowardZero);
+            assert_eq!(read().rounding_mode(), RoundingMode::TowardZero);
+            assert_eq!(read() - MxCsr::ROUNDING_CONTROL_ZERO, mxcsr);
+            write(mxcsr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bit_positions() {
+        assert_eq!(MxCsr::INVALID_OPERATION.bits(), 1 << 0);
+        assert_eq!(MxCsr::PRECISION.bits(), 1 << 5);
+        assert_eq!(MxCsr::DENORMALS_ARE_ZEROS.bits(), 1 << 6);
+        assert_eq!(MxCsr::INVALID_OPERATION_MASK.bits(), 1 << 7);
+        assert_eq!(MxCsr::PRECISION_MASK.bits(), 1 << 12);
+        assert_eq!(MxCsr::ROUNDING_CONTROL_ZERO.bits(), 0b11 << 13);
+        assert_eq!(MxCsr::FLUSH_TO_ZERO.bits(), 1 << 15);
+        assert_eq!(MxCsr::default().bits(), 0x1f80);
+    }
+
+    #[test]
+    fn rounding_mode() {
+        let mut mxcsr = MxCsr::default() | MxCsr::FLUSH_TO_ZERO;
+        assert_eq!(mxcsr.rounding_mode(), RoundingMode::Nearest);
+        for &mode in &[
+            RoundingMode::Down,
+            RoundingMode::Up,
+            RoundingMode::TowardZero,
+            RoundingMode::Nearest,
+        ] {
+            mxcsr.set_rounding_mode(mode);
+            assert_eq!(mxcsr.rounding_mode(), mode);
+            assert_eq!(
+                mxcsr - MxCsr::ROUNDING_CONTROL_ZERO,
+                MxCsr::default() | MxCsr::FLUSH_TO_ZERO
+            );
+        }
+        mxcsr.set_rounding_mode(RoundingMode::Up);
+        assert_eq!(mxcsr.bits(), 0x1f80 | 0x8000 | (2 << 13));
     }
 }
@@ -0,0 +1,178 @@
+//! Access to the protection key rights register (PKRU).
+
+use bit_field::BitField;
+
+/// The protection key rights register for user-mode pages.
+///
+/// Contains an access-disable and a write-disable bit for each of the 16
+/// protection keys, see [`PkruValue`].
+///
+/// Reading or writing this register requires
+/// [`Cr4Flags::PROTECTION_KEY_USER`](crate::registers::control::Cr4Flags::PROTECTION_KEY_USER)
+/// to be set, otherwise an invalid opcode exception is raised.
+#[derive(Debug)]
+pub struct Pkru;
+
+/// A value of the [`Pkru`] register.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct PkruValue {
+    bits: u32,
+}
+
+impl PkruValue {
+    /// Creates a value with all accesses allowed for all protection keys.
+    #[inline]
+    pub const fn new() -> Self {
+        Self { bits: 0 }
+    }
+
+    /// Converts from the underlying bit representation.
+    #[inline]
+    pub const fn from_bits(bits: u32) -> Self {
+        Self { bits }
+    }
+
+    /// Returns the underlying bit representation.
+    #[inline]
+    pub const fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Returns whether all data accesses to pages with the given protection key are disabled.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `key` is not smaller than 16.
+    #[inline]
+    pub fn access_disable(&self, key: u8) -> bool {
+        self.bits.get_bit(Self::access_disable_bit(key))
+    }
+
+    /// Disables or enables all data accesses to pages with the given protection key.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `key` is not smaller than 16.
+    #[inline]
+    pub fn set_access_disable(&mut self, key: u8, disable: bool) {
+        self.bits.set_bit(Self::access_disable_bit(key), disable);
+    }
+
+    /// Returns whether writes to pages with the given protection key are disabled.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `key` is not smaller than 16.
+    #[inline]
+    pub fn write_disable(&self, key: u8) -> bool {
+        self.bits.get_bit(Self::access_disable_bit(key) + 1)
+    }
+
+    /// Disables or enables writes to pages with the given protection key.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `key` is not smaller than 16.
+    #[inline]
+    pub fn set_write_disable(&mut self, key: u8, disable: bool) {
+        self.bits
+            .set_bit(Self::access_disable_bit(key) + 1, disable);
+    }
+
+    fn access_disable_bit(key: u8) -> usize {
+        assert!(key < 16, "protection key must be smaller than 16");
+        usize::from(key) * 2
+    }
+}
+
+#[cfg(all(feature = "instructions", target_arch = "x86_64"))]
+mod x86_64 {
+    use super::*;
+    use core::arch::asm;
+
+    impl Pkru {
+        /// Reads the current PKRU value using the `rdpkru` instruction.
+        #[inline]
+        pub fn read() -> PkruValue {
+            let bits: u32;
+            unsafe {
+                asm!(
+                    "rdpkru",
+                    in("ecx") 0,
+                    out("eax") bits,
+                    out("edx") _,
+                    options(nomem, nostack, preserves_flags),
+                );
+            }
+            PkruValue::from_bits(bits)
+        }
+
+        /// Writes a new PKRU value using the `wrpkru` instruction.
+        ///
+        /// `wrpkru` requires ECX and EDX to be 0, which is ensured by this
+        /// function. It is not a serializing instruction, but the processor
+        /// does not execute subsequent memory accesses speculatively before
+        /// the new value is in effect.
+        ///
+        /// ## Safety
+        ///
+        /// Disabling access to pages that are in use can break memory safety.
+        #[inline]
+        pub unsafe fn write(value: PkruValue) {
+            unsafe {
+                asm!(
+                    "wrpkru",
+                    in("eax") value.bits(),
+                    in("ecx") 0,
+                    in("edx") 0,
+                    options(nostack, preserves_flags),
+                );
+            }
+        }
+
+        /// Updates the PKRU value.
+        ///
+        /// ## Safety
+        ///
+        /// See [`Pkru::write`].
+        #[inline]
+        pub unsafe fn update<F>(f: F)
+        where
+            F: FnOnce(&mut PkruValue),
+        {
+            let mut value = Self::read();
+            f(&mut value);
+            unsafe {
+                Self::write(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_key_bits() {
+        let mut value = PkruValue::new();
+        value.set_access_disable(3, true);
+        assert_eq!(value.bits(), 0x0000_0040);
+        value.set_write_disable(3, true);
+        value.set_write_disable(15, true);
+        assert_eq!(value.bits(), 0x8000_00c0);
+        assert!(value.access_disable(3));
+        assert!(value.write_disable(3));
+        assert!(!value.access_disable(15));
+
+        value.set_access_disable(3, false);
+        assert_eq!(value.bits(), 0x8000_0080);
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_key() {
+        PkruValue::new().set_access_disable(16, true);
+    }
+}
@@ -68,6 +68,7 @@ bitflags! {
 mod x86_64 {
     use super::*;
     use core::arch::asm;
+    use core::marker::PhantomData;
 
     /// Returns the current value of the RFLAGS register.
     ///
@@ -145,14 +146,98 @@ mod x86_64 {
         }
     }
 
+    /// Saves the current value of the RFLAGS register using `pushfq`.
+    ///
+    /// The returned guard restores the saved value using `popfq` when it is
+    /// dropped or [popped](SavedRFlags::pop). Note that in ring 3, `popfq` can
+    /// only restore the `INTERRUPT_FLAG` if the I/O privilege level is 3.
+    #[inline]
+    pub fn push_flags() -> SavedRFlags {
+        SavedRFlags {
+            flags: read_raw(),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// A saved value of the RFLAGS register that is restored when dropped.
+    ///
+    /// Created by [`push_flags`]. The guard is not `Send`, because the flags
+    /// must be restored on the same CPU.
+    #[derive(Debug)]
+    #[must_use = "the saved flags are restored when this guard is dropped"]
+    pub struct SavedRFlags {
+        flags: u64,
+        _not_send: PhantomData<*const ()>,
+    }
+
+    impl SavedRFlags {
+        /// Returns the saved flags.
+        #[inline]
+        pub fn flags(&self) -> RFlags {
+            RFlags::from_bits_truncate(self.flags)
+        }
+
+        /// Restores the saved flags using `popfq`.
+        ///
+        /// This is equivalent to dropping the guard.
+        #[inline]
+        pub fn pop(self) {}
+    }
+
+    impl Drop for SavedRFlags {
+        #[inline]
+        fn drop(&mut self) {
+            // SAFETY: The saved value was read from the RFLAGS register, so it
+            // satisfies all requirements of the Rust code running at that time.
+            unsafe { write_raw(self.flags) }
+        }
+    }
+
+    /// Runs a closure with disabled interrupts and restores the previous
+    /// state of the `INTERRUPT_FLAG` afterwards.
+    ///
+    /// Unlike [`interrupts::without_interrupts`](crate::instructions::interrupts::without_interrupts),
+    /// which only re-enables interrupts if they were enabled before, this
+    /// restores the exact previous state of the `INTERRUPT_FLAG`, even if the
+    /// closure enables interrupts. All other flags are left as the closure set
+    /// them. Use [`push_flags`] to restore the complete RFLAGS register.
+    #[inline]
+    pub fn without_interrupts<F, R>(f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        use crate::instructions::interrupts;
+
+        let was_enabled = read().contains(RFlags::INTERRUPT_FLAG);
+        interrupts::disable();
+        let ret = f();
+        if was_enabled {
+            interrupts::enable();
+        } else {
+            interrupts::disable();
+        }
+        ret
+    }
+
     #[cfg(test)]
     mod test {
-        use crate::registers::rflags::read;
+        use crate::registers::rflags::{push_flags, read, write, RFlags};
 
         #[test]
         fn rflags_read() {
             let rflags = read();
             println!("{:#?}", rflags);
         }
+
+        #[test]
+        fn push_pop_flags() {
+            // The ID flag can be modified in user mode and has no side effects.
+            let saved = push_flags();
+            let original = saved.flags();
+            unsafe { write(original ^ RFlags::ID) };
+            assert_eq!(read() & RFlags::ID, (original ^ RFlags::ID) & RFlags::ID);
+            saved.pop();
+            assert_eq!(read() & RFlags::ID, original & RFlags::ID);
+        }
     }
 }
@@ -36,6 +36,26 @@ pub trait Segment {
 /// While most segments are unused in 64-bit mode, the FS and GS segments are
 /// still partially used. Only the 64-bit segment base address is used, and this
 /// address can be set via the GDT, or by using the `FSGSBASE` instructions.
+///
+/// # Example
+///
+/// Use the `FSGSBASE` instructions if they are enabled and fall back to the
+/// MSR otherwise:
+///
+/// ```no_run
+/// # #[cfg(all(feature = "instructions", target_arch = "x86_64"))]
+/// # fn example() {
+/// use x86_64::registers::control::{Cr4, Cr4Flags};
+/// use x86_64::registers::model_specific::FsBase;
+/// use x86_64::registers::segmentation::{Segment64, FS};
+///
+/// let fs_base = if Cr4::read().contains(Cr4Flags::FSGSBASE) {
+///     FS::read_base()
+/// } else {
+///     FsBase::read()
+/// };
+/// # }
+/// ```
 pub trait Segment64: Segment {
     /// MSR containing the segment base. This MSR can be used to set the base
     /// when [`CR4.FSGSBASE`][Cr4Flags::FSGSBASE] is **not** set.
@@ -68,6 +88,16 @@ pub trait Segment64: Segment {
 #[repr(transparent)]
 pub struct SegmentSelector(pub u16);
 
+/// The descriptor table referenced by a [`SegmentSelector`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum SegmentTable {
+    /// The global descriptor table.
+    Gdt = 0,
+    /// The local descriptor table.
+    Ldt = 1,
+}
+
 impl SegmentSelector {
     /// Creates a new SegmentSelector
     ///
@@ -79,6 +109,24 @@ impl SegmentSelector {
         SegmentSelector((index << 3) | (rpl as u16))
     }
 
+    /// Creates a new SegmentSelector referencing the given descriptor table.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` does not fit in 13 bits, i.e. is 8192 or larger.
+    #[inline]
+    pub const fn new_with_table(
+        index: u16,
+        table: SegmentTable,
+        rpl: PrivilegeLevel,
+    ) -> SegmentSelector {
+        assert!(
+            index < 1 << 13,
+            "segment selector index must be smaller than 8192"
+        );
+        SegmentSelector((index << 3) | ((table as u16) << 2) | (rpl as u16))
+    }
+
     /// Can be used as a selector into a non-existent segment and assigned to segment registers,
     /// e.g. data segment register in ring 0
     pub const NULL: Self = Self::new(0, PrivilegeLevel::Ring0);
@@ -95,11 +143,27 @@ impl SegmentSelector {
         PrivilegeLevel::from_u16(self.0.get_bits(0..2))
     }
 
+    /// Returns the descriptor table referenced by this selector.
+    #[inline]
+    pub fn ti(self) -> SegmentTable {
+        if self.0.get_bit(2) {
+            SegmentTable::Ldt
+        } else {
+            SegmentTable::Gdt
+        }
+    }
+
     /// Set the privilege level for this Segment selector.
     #[inline]
     pub fn set_rpl(&mut self, rpl: PrivilegeLevel) {
         self.0.set_bits(0..2, rpl as u16);
     }
+
+    /// Returns a copy of this selector with the given requested privilege level.
+    #[inline]
+    pub const fn with_rpl(self, rpl: PrivilegeLevel) -> SegmentSelector {
+        SegmentSelector((self.0 & !0b11) | (rpl as u16))
+    }
 }
 
 impl fmt::Debug for SegmentSelector {
@@ -111,6 +175,18 @@ impl fmt::Debug for SegmentSelector {
     }
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for SegmentSelector {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "SegmentSelector {{ index: {=u16}, rpl: {=u8} }}",
+            self.index(),
+            self.rpl() as u8
+        );
+    }
+}
+
 /// Code Segment
 ///
 /// While most fields in the Code-Segment [`Descriptor`] are unused in 64-bit
@@ -140,6 +216,11 @@ pub struct CS;
 /// `sysenter`/`sysexit` instructions (even on 64-bit transitions). This is to
 /// maintain symmetry with 32-bit transitions where setting SS actually will
 /// actually have an effect.
+///
+/// Loading SS with [`Segment::set_reg`] does not change the stack pointer, so
+/// the current stack stays in use. The selector's DPL and RPL must match the
+/// current privilege level, otherwise a general protection fault is raised.
+/// In ring 0, a null selector may be loaded.
 #[derive(Debug)]
 pub struct SS;
 
@@ -168,3 +249,37 @@ pub struct FS;
 /// base often points to a per-cpu kernel data structure.
 #[derive(Debug)]
 pub struct GS;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segment_selector_fields() {
+        let selector = SegmentSelector::new(4, PrivilegeLevel::Ring3);
+        assert_eq!(selector.0, 0x23);
+        assert_eq!(selector.index(), 4);
+        assert_eq!(selector.ti(), SegmentTable::Gdt);
+        assert_eq!(selector.rpl(), PrivilegeLevel::Ring3);
+
+        let selector =
+            SegmentSelector::new_with_table(8191, SegmentTable::Ldt, PrivilegeLevel::Ring1);
+        assert_eq!(selector.0, 0xfffd);
+        assert_eq!(selector.index(), 8191);
+        assert_eq!(selector.ti(), SegmentTable::Ldt);
+        assert_eq!(selector.rpl(), PrivilegeLevel::Ring1);
+
+        let user = SegmentSelector::new(3, PrivilegeLevel::Ring0).with_rpl(PrivilegeLevel::Ring3);
+        assert_eq!(user, SegmentSelector(0x1b));
+        assert_eq!(
+            selector.with_rpl(PrivilegeLevel::Ring0),
+            SegmentSelector::new_with_table(8191, SegmentTable::Ldt, PrivilegeLevel::Ring0)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "segment selector index must be smaller than 8192")]
+    fn segment_selector_index_too_large() {
+        SegmentSelector::new_with_table(8192, SegmentTable::Gdt, PrivilegeLevel::Ring0);
+    }
+}
@@ -168,3 +168,22 @@ mod x86_64 {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xcr0_bit_positions() {
+        assert_eq!(XCr0Flags::X87.bits(), 1 << 0);
+        assert_eq!(XCr0Flags::SSE.bits(), 1 << 1);
+        assert_eq!(XCr0Flags::AVX.bits(), 1 << 2);
+        assert_eq!(XCr0Flags::BNDREG.bits(), 1 << 3);
+        assert_eq!(XCr0Flags::BNDCSR.bits(), 1 << 4);
+        assert_eq!(XCr0Flags::OPMASK.bits(), 1 << 5);
+        assert_eq!(XCr0Flags::ZMM_HI256.bits(), 1 << 6);
+        assert_eq!(XCr0Flags::HI16_ZMM.bits(), 1 << 7);
+        assert_eq!(XCr0Flags::MPK.bits(), 1 << 9);
+        assert_eq!(XCr0Flags::LWP.bits(), 1 << 62);
+    }
+}
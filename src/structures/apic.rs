@@ -0,0 +1,595 @@
+//! Access to the local APIC in xAPIC and x2APIC mode.
+//!
+//! In xAPIC mode, the registers of the local APIC are memory mapped at the physical address
+//! stored in [`ApicBase`](crate::registers::model_specific::ApicBase) (usually `0xfee0_0000`),
+//! which has to be mapped as uncacheable memory. In x2APIC mode, which is enabled through
+//! [`ApicBaseFlags::X2APIC_ENABLE`](crate::registers::model_specific::ApicBaseFlags::X2APIC_ENABLE),
+//! the registers are accessed as model specific registers starting at `0x800` instead. Both modes
+//! are abstracted by the [`ApicAccess`] trait, on top of which [`LocalApic`] provides typed
+//! accessors.
+
+use crate::registers::model_specific::Msr;
+use crate::VirtAddr;
+use bit_field::BitField;
+
+/// A register of the local APIC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ApicRegister {
+    /// Local APIC ID register.
+    Id,
+    /// Local APIC version register.
+    Version,
+    /// Task priority register (TPR).
+    TaskPriority,
+    /// End of interrupt register (EOI).
+    EndOfInterrupt,
+    /// Spurious interrupt vector register (SVR).
+    SpuriousInterruptVector,
+    /// Error status register (ESR).
+    ErrorStatus,
+    /// Low half of the interrupt command register (ICR).
+    ///
+    /// Use [`ApicAccess::write_icr`] to send interrupts.
+    InterruptCommand,
+    /// LVT timer register.
+    LvtTimer,
+    /// LVT LINT0 register.
+    LvtLint0,
+    /// LVT LINT1 register.
+    LvtLint1,
+    /// LVT error register.
+    LvtError,
+    /// Initial count register of the timer.
+    TimerInitialCount,
+    /// Current count register of the timer.
+    TimerCurrentCount,
+    /// Divide configuration register of the timer.
+    TimerDivideConfiguration,
+}
+
+impl ApicRegister {
+    /// Returns the offset of the register from the xAPIC base address.
+    #[inline]
+    pub const fn offset(self) -> usize {
+        match self {
+            Self::Id => 0x20,
+            Self::Version => 0x30,
+            Self::TaskPriority => 0x80,
+            Self::EndOfInterrupt => 0xb0,
+            Self::SpuriousInterruptVector => 0xf0,
+            Self::ErrorStatus => 0x280,
+            Self::InterruptCommand => 0x300,
+            Self::LvtTimer => 0x320,
+            Self::LvtLint0 => 0x350,
+            Self::LvtLint1 => 0x360,
+            Self::LvtError => 0x370,
+            Self::TimerInitialCount => 0x380,
+            Self::TimerCurrentCount => 0x390,
+            Self::TimerDivideConfiguration => 0x3e0,
+        }
+    }
+
+    /// Returns the model specific register used to access the register in x2APIC mode.
+    #[inline]
+    pub const fn msr(self) -> Msr {
+        Msr::new(0x800 + (self.offset() >> 4) as u32)
+    }
+}
+
+/// Access to the registers of a local APIC.
+///
+/// ## Safety
+///
+/// Implementations must access the registers of the local APIC of the current processor.
+pub unsafe trait ApicAccess {
+    /// Reads the given 32-bit register.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that the register is readable and that reading it has no unsafe
+    /// side effects.
+    unsafe fn read(&mut self, register: ApicRegister) -> u32;
+
+    /// Writes the given 32-bit register.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that the register is writable and that writing `value` to it has
+    /// no unsafe side effects.
+    unsafe fn write(&mut self, register: ApicRegister, value: u32);
+
+    /// Writes the full 64-bit interrupt command register, which sends an interrupt.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that sending the interrupt has no unsafe side effects.
+    unsafe fn write_icr(&mut self, value: u64);
+
+    /// Extracts the APIC ID from the value of the ID register.
+    fn decode_id(raw: u32) -> u32;
+
+    /// Encodes the destination field in bits 32 to 63 of the interrupt command register.
+    fn encode_destination(apic_id: u32) -> u32;
+}
+
+/// Memory mapped access to the local APIC in xAPIC mode.
+#[derive(Debug)]
+pub struct XApic {
+    base: VirtAddr,
+}
+
+impl XApic {
+    /// Creates an accessor for the registers mapped at `base`.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that the 4KiB page of the local APIC is mapped as uncacheable
+    /// memory at `base`, and that the local APIC is in xAPIC mode.
+    #[inline]
+    pub const unsafe fn new(base: VirtAddr) -> Self {
+        Self { base }
+    }
+
+    #[inline]
+    fn register(&self, offset: usize) -> *mut u32 {
+        (self.base + offset as u64).as_mut_ptr()
+    }
+}
+
+unsafe impl ApicAccess for XApic {
+    #[inline]
+    unsafe fn read(&mut self, register: ApicRegister) -> u32 {
+        unsafe { self.register(register.offset()).read_volatile() }
+    }
+
+    #[inline]
+    unsafe fn write(&mut self, register: ApicRegister, value: u32) {
+        unsafe { self.register(register.offset()).write_volatile(value) }
+    }
+
+    /// Writes the high half of the ICR before the low half, which sends the interrupt, and
+    /// waits until the interrupt has been delivered.
+    #[inline]
+    unsafe fn write_icr(&mut self, value: u64) {
+        let low = ApicRegister::InterruptCommand.offset();
+        unsafe {
+            self.register(low + 0x10)
+                .write_volatile((value >> 32) as u32);
+            self.register(low).write_volatile(value as u32);
+            while self.register(low).read_volatile().get_bit(12) {
+                core::hint::spin_loop();
+            }
+        }
+    }
+
+    #[inline]
+    fn decode_id(raw: u32) -> u32 {
+        raw >> 24
+    }
+
+    #[inline]
+    fn encode_destination(apic_id: u32) -> u32 {
+        apic_id << 24
+    }
+}
+
+/// MSR based access to the local APIC in x2APIC mode.
+#[derive(Debug)]
+pub struct X2Apic(());
+
+impl X2Apic {
+    /// Creates an accessor for the x2APIC registers.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that the local APIC is in x2APIC mode.
+    #[inline]
+    pub const unsafe fn new() -> Self {
+        Self(())
+    }
+}
+
+unsafe impl ApicAccess for X2Apic {
+    #[inline]
+    unsafe fn read(&mut self, register: ApicRegister) -> u32 {
+        unsafe { register.msr().read() as u32 }
+    }
+
+    #[inline]
+    unsafe fn write(&mut self, register: ApicRegister, value: u32) {
+        unsafe { register.msr().write(u64::from(value)) }
+    }
+
+    #[inline]
+    unsafe fn write_icr(&mut self, value: u64) {
+        unsafe { ApicRegister::InterruptCommand.msr().write(value) }
+    }
+
+    #[inline]
+    fn decode_id(raw: u32) -> u32 {
+        raw
+    }
+
+    #[inline]
+    fn encode_destination(apic_id: u32) -> u32 {
+        apic_id
+    }
+}
+
+/// The delivery mode of an interrupt sent by the local APIC or the I/O APIC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum DeliveryMode {
+    /// Deliver the interrupt to the vector.
+    Fixed = 0b000,
+    /// Deliver the interrupt to the processor with the lowest priority.
+    LowestPriority = 0b001,
+    /// Deliver a system management interrupt. The vector must be 0.
+    Smi = 0b010,
+    /// Deliver a non-maskable interrupt. The vector is ignored.
+    Nmi = 0b100,
+    /// Deliver an INIT request. The vector must be 0.
+    Init = 0b101,
+    /// Deliver a start-up IPI. The vector is the page number of the start-up code.
+    StartUp = 0b110,
+    /// Deliver the interrupt like an external interrupt from a PIC.
+    ExtInt = 0b111,
+}
+
+impl DeliveryMode {
+    /// Converts from bits, returning `None` for the reserved value `0b011`.
+    #[inline]
+    pub const fn from_bits(bits: u8) -> Option<Self> {
+        match bits {
+            0b000 => Some(Self::Fixed),
+            0b001 => Some(Self::LowestPriority),
+            0b010 => Some(Self::Smi),
+            0b100 => Some(Self::Nmi),
+            0b101 => Some(Self::Init),
+            0b110 => Some(Self::StartUp),
+            0b111 => Some(Self::ExtInt),
+            _ => None,
+        }
+    }
+}
+
+/// Encodes the interrupt command register for an IPI to a single processor.
+///
+/// `destination` is the already encoded value of the high half (see
+/// [`ApicAccess::encode_destination`]). All IPIs are edge triggered with the level assert bit
+/// set, as required for INIT IPIs and ignored by current processors for all others.
+const fn encode_icr(destination: u32, vector: u8, delivery_mode: DeliveryMode) -> u64 {
+    let level_assert = 1 << 14;
+    (destination as u64) << 32 | level_assert | (delivery_mode as u64) << 8 | vector as u64
+}
+
+/// A local APIC.
+///
+/// ## Example
+///
+/// Starting an application processor:
+///
+/// ```no_run
+/// use x86_64::structures::apic::{LocalApic, XApic};
+/// use x86_64::VirtAddr;
+///
+/// let mut apic = LocalApic::new(unsafe { XApic::new(VirtAddr::new(0xfee0_0000)) });
+/// let ap_id = 1;
+/// unsafe {
+///     apic.send_init_ipi(ap_id);
+///     // ... wait 10 ms ...
+///     // Start executing the code at physical address 0x8000.
+///     apic.send_startup_ipi(ap_id, 0x08);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LocalApic<A: ApicAccess> {
+    access: A,
+}
+
+impl<A: ApicAccess> LocalApic<A> {
+    /// Creates a local APIC using the given register access.
+    #[inline]
+    pub const fn new(access: A) -> Self {
+        Self { access }
+    }
+
+    /// Returns the register access.
+    #[inline]
+    pub fn access(&mut self) -> &mut A {
+        &mut self.access
+    }
+
+    /// Returns the APIC ID of the current processor.
+    #[inline]
+    pub fn id(&mut self) -> u32 {
+        A::decode_id(unsafe { self.access.read(ApicRegister::Id) })
+    }
+
+    /// Returns the version register, which contains the version in bits 0 to 7 and the number
+    /// of LVT entries minus one in bits 16 to 23.
+    #[inline]
+    pub fn version(&mut self) -> u32 {
+        unsafe { self.access.read(ApicRegister::Version) }
+    }
+
+    /// Returns the task priority. Interrupts with a priority class (bits 4 to 7 of the vector)
+    /// lower than or equal to the priority class of the TPR are not delivered.
+    #[inline]
+    pub fn task_priority(&mut self) -> u8 {
+        unsafe { self.access.read(ApicRegister::TaskPriority) as u8 }
+    }
+
+    /// Sets the task priority.
+    #[inline]
+    pub fn set_task_priority(&mut self, priority: u8) {
+        unsafe {
+            self.access
+                .write(ApicRegister::TaskPriority, u32::from(priority))
+        }
+    }
+
+    /// Signals the end of the interrupt that is currently being handled.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that an interrupt delivered by the local APIC is being handled.
+    #[inline]
+    pub unsafe fn send_eoi(&mut self) {
+        unsafe { self.access.write(ApicRegister::EndOfInterrupt, 0) }
+    }
+
+    /// Returns the spurious interrupt vector register.
+    #[inline]
+    pub fn spurious_interrupt_vector(&mut self) -> u32 {
+        unsafe { self.access.read(ApicRegister::SpuriousInterruptVector) }
+    }
+
+    /// Software-enables the local APIC and sets the vector for spurious interrupts.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that a handler is installed for `spurious_vector` and all
+    /// unmasked local vector table entries.
+    #[inline]
+    pub unsafe fn enable(&mut self, spurious_vector: u8) {
+        let mut svr = self.spurious_interrupt_vector();
+        svr.set_bits(0..8, u32::from(spurious_vector));
+        svr.set_bit(8, true);
+        unsafe {
+            self.access
+                .write(ApicRegister::SpuriousInterruptVector, svr)
+        }
+    }
+
+    /// Reads an entry of the local vector table.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `register` is not one of the LVT registers.
+    #[inline]
+    pub fn lvt(&mut self, register: ApicRegister) -> LvtEntry {
+        assert_lvt(register);
+        LvtEntry(unsafe { self.access.read(register) })
+    }
+
+    /// Writes an entry of the local vector table.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `register` is not one of the LVT registers.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that a handler is installed for the vector of the entry if it is
+    /// not masked.
+    #[inline]
+    pub unsafe fn set_lvt(&mut self, register: ApicRegister, entry: LvtEntry) {
+        assert_lvt(register);
+        unsafe { self.access.write(register, entry.0) }
+    }
+
+    /// Reads and clears the error status register.
+    #[inline]
+    pub fn error_status(&mut self) -> u32 {
+        unsafe {
+            // The register is updated by writing to it.
+            self.access.write(ApicRegister::ErrorStatus, 0);
+            self.access.read(ApicRegister::ErrorStatus)
+        }
+    }
+
+    /// Sends an IPI with the given vector and delivery mode to the processor `apic_id`.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that the IPI has no unsafe side effects on the target.
+    #[inline]
+    pub unsafe fn send_ipi(&mut self, apic_id: u32, vector: u8, delivery_mode: DeliveryMode) {
+        let icr = encode_icr(A::encode_destination(apic_id), vector, delivery_mode);
+        unsafe { self.access.write_icr(icr) }
+    }
+
+    /// Sends an INIT IPI to the processor `apic_id`, which resets it into the wait-for-SIPI
+    /// state.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that resetting the target processor is sound.
+    #[inline]
+    pub unsafe fn send_init_ipi(&mut self, apic_id: u32) {
+        unsafe { self.send_ipi(apic_id, 0, DeliveryMode::Init) }
+    }
+
+    /// Sends a start-up IPI to the processor `apic_id`, which starts executing in real mode at
+    /// physical address `vector * 0x1000`.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that valid start-up code is located at the address.
+    #[inline]
+    pub unsafe fn send_startup_ipi(&mut self, apic_id: u32, vector: u8) {
+        unsafe { self.send_ipi(apic_id, vector, DeliveryMode::StartUp) }
+    }
+}
+
+fn assert_lvt(register: ApicRegister) {
+    assert!(
+        matches!(
+            register,
+            ApicRegister::LvtTimer
+                | ApicRegister::LvtLint0
+                | ApicRegister::LvtLint1
+                | ApicRegister::LvtError
+        ),
+        "not a local vector table register"
+    );
+}
+
+/// An entry of the local vector table (LVT).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct LvtEntry(u32);
+
+/// The operating mode of the local APIC timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum TimerMode {
+    /// Count down once from the initial count.
+    OneShot = 0b00,
+    /// Reload the initial count after reaching 0.
+    Periodic = 0b01,
+    /// Fire when the time stamp counter reaches the value in
+    /// [`TscDeadline`](crate::registers::model_specific::TscDeadline).
+    TscDeadline = 0b10,
+}
+
+impl LvtEntry {
+    /// Creates an unmasked, edge-triggered entry with fixed delivery for the given vector.
+    #[inline]
+    pub const fn new(vector: u8) -> Self {
+        Self(vector as u32)
+    }
+
+    /// Creates an entry from its raw value.
+    #[inline]
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// Returns the raw value of the entry.
+    #[inline]
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Returns the vector.
+    #[inline]
+    pub const fn vector(self) -> u8 {
+        self.0 as u8
+    }
+
+    /// Returns whether the interrupt is masked.
+    #[inline]
+    pub const fn is_masked(self) -> bool {
+        self.0 & (1 << 16) != 0
+    }
+
+    /// Sets or clears the mask bit.
+    #[inline]
+    pub const fn with_masked(self, masked: bool) -> Self {
+        if masked {
+            Self(self.0 | 1 << 16)
+        } else {
+            Self(self.0 & !(1 << 16))
+        }
+    }
+
+    /// Sets the delivery mode. This is ignored for the timer and error entries.
+    #[inline]
+    pub const fn with_delivery_mode(self, mode: DeliveryMode) -> Self {
+        Self(self.0 & !(0b111 << 8) | (mode as u32) << 8)
+    }
+
+    /// Sets the timer mode. This is only used by the timer entry.
+    #[inline]
+    pub const fn with_timer_mode(self, mode: TimerMode) -> Self {
+        Self(self.0 & !(0b11 << 17) | (mode as u32) << 17)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn register_mapping() {
+        assert_eq!(ApicRegister::Id.msr().number(), 0x802);
+        assert_eq!(ApicRegister::EndOfInterrupt.offset(), 0xb0);
+        assert_eq!(ApicRegister::EndOfInterrupt.msr().number(), 0x80b);
+        assert_eq!(ApicRegister::SpuriousInterruptVector.msr().number(), 0x80f);
+        assert_eq!(ApicRegister::InterruptCommand.msr().number(), 0x830);
+        assert_eq!(ApicRegister::LvtTimer.msr().number(), 0x832);
+        assert_eq!(ApicRegister::LvtError.msr().number(), 0x837);
+        assert_eq!(ApicRegister::TimerDivideConfiguration.msr().number(), 0x83e);
+    }
+
+    #[test]
+    fn icr_encoding() {
+        assert_eq!(
+            encode_icr(XApic::encode_destination(3), 0, DeliveryMode::Init),
+            0x0300_0000_0000_4500
+        );
+        assert_eq!(
+            encode_icr(
+                X2Apic::encode_destination(0x1234),
+                0x08,
+                DeliveryMode::StartUp
+            ),
+            0x0000_1234_0000_4608
+        );
+    }
+
+    #[test]
+    fn lvt_entry() {
+        let entry = LvtEntry::new(0x30)
+            .with_timer_mode(TimerMode::Periodic)
+            .with_masked(true);
+        assert_eq!(entry.bits(), 0x0003_0030);
+        assert_eq!(entry.vector(), 0x30);
+        assert!(entry.is_masked());
+        assert!(!entry.with_masked(false).is_masked());
+        assert_eq!(
+            LvtEntry::new(0)
+                .with_delivery_mode(DeliveryMode::Nmi)
+                .bits(),
+            0x400
+        );
+    }
+
+    #[test]
+    fn xapic_mmio() {
+        #[repr(align(4096))]
+        struct Page([u32; 1024]);
+
+        let mut page = Page([0; 1024]);
+        page.0[0x20 / 4] = 5 << 24;
+        let base = VirtAddr::from_ptr(page.0.as_mut_ptr());
+        let mut apic = LocalApic::new(unsafe { XApic::new(base) });
+
+        assert_eq!(apic.id(), 5);
+        unsafe {
+            apic.enable(0xff);
+            apic.set_lvt(
+                ApicRegister::LvtLint0,
+                LvtEntry::new(0x21).with_masked(true),
+            );
+            apic.send_startup_ipi(2, 0x08);
+        }
+        assert_eq!(page.0[0xf0 / 4], 0x1ff);
+        assert_eq!(page.0[0x350 / 4], 0x1_0021);
+        assert_eq!(page.0[0x300 / 4], 0x4608);
+        assert_eq!(page.0[0x310 / 4], 2 << 24);
+    }
+}
@@ -1,8 +1,8 @@
 //! Types for the Global Descriptor Table and segment selectors.
 
 pub use crate::registers::segmentation::SegmentSelector;
-use crate::structures::tss::TaskStateSegment;
-use crate::PrivilegeLevel;
+use crate::structures::tss::{TaskStateSegment, TaskStateSegmentWithIoBitmap};
+use crate::{PrivilegeLevel, VirtAddr};
 use bit_field::BitField;
 use bitflags::bitflags;
 use core::fmt;
@@ -149,6 +149,15 @@ impl<const MAX: usize> GlobalDescriptorTable<MAX> {
     /// [`CS::set_reg`] and [`load_tss`](crate::instructions::tables::load_tss)
     /// are both unsafe for this reason.
     ///
+    /// The entries are copied into the new table, so the slice does not need to
+    /// outlive it. This can be used to take over an existing table, e.g. one
+    /// set up by the bootloader, by creating the slice from the base and limit
+    /// returned by [`sgdt`](crate::instructions::tables::sgdt). System
+    /// descriptors are copied as the two entries they occupy. The new table is
+    /// aligned by Rust, so the slice has no alignment requirements beyond those
+    /// of `u64`, but the new table must live as long as it is loaded (see
+    /// [`load`](GlobalDescriptorTable::load)).
+    ///
     /// Panics if:
     /// * the provided slice has more than `MAX` entries
     /// * the provided slice is empty
@@ -186,6 +195,32 @@ impl<const MAX: usize> GlobalDescriptorTable<MAX> {
         &self.table[..self.len]
     }
 
+    /// Returns the number of used [`Entry`]s, including the null entry.
+    ///
+    /// System descriptors count as the two [`Entry`]s they occupy.
+    // A GDT always contains the null entry, so it is never empty.
+    #[allow(clippy::len_without_is_empty)]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the raw value of the [`Entry`] at the given index, or `None` if
+    /// the index is not in use.
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<u64> {
+        self.entries().get(index).map(Entry::raw)
+    }
+
+    /// Returns an iterator over the raw values of the used [`Entry`]s,
+    /// starting with the null entry.
+    ///
+    /// System descriptors yield two values, one for each [`Entry`] they occupy.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        self.entries().iter().map(Entry::raw)
+    }
+
     /// Appends the given segment descriptor to the GDT, returning the segment selector.
     ///
     /// Note that depending on the type of the [`Descriptor`] this may append
@@ -236,6 +271,37 @@ impl<const MAX: usize> GlobalDescriptorTable<MAX> {
     /// this means its up to the user to ensure that there will be no modifications
     /// after loading and that the GDT will live for as long as it's loaded.
     ///
+    /// The table must stay at the same memory location until a different GDT is
+    /// loaded. Segment registers hold selectors into the table, so any selector
+    /// loaded from it (including the TSS selector loaded by `ltr`) must keep
+    /// referring to a valid descriptor during that time as well.
+    ///
+    /// ## Example
+    ///
+    /// Loading a GDT that is allocated per CPU:
+    ///
+    /// ```no_run
+    /// use x86_64::instructions::segmentation::{Segment, CS, SS};
+    /// use x86_64::structures::gdt::{Descriptor, GlobalDescriptorTable};
+    ///
+    /// struct PerCpu {
+    ///     gdt: GlobalDescriptorTable,
+    /// }
+    ///
+    /// let mut cpu = Box::new(PerCpu {
+    ///     gdt: GlobalDescriptorTable::new(),
+    /// });
+    /// let code = cpu.gdt.append(Descriptor::kernel_code_segment());
+    /// let data = cpu.gdt.append(Descriptor::kernel_data_segment());
+    /// // SAFETY: the box keeps the GDT at a fixed address and `cpu` is never
+    /// // dropped or modified while the GDT is loaded.
+    /// unsafe {
+    ///     cpu.gdt.load_unsafe();
+    ///     CS::set_reg(code);
+    ///     SS::set_reg(data);
+    /// }
+    /// # core::mem::forget(cpu);
+    /// ```
     #[cfg(all(feature = "instructions", target_arch = "x86_64"))]
     #[inline]
     pub unsafe fn load_unsafe(&self) {
@@ -439,17 +505,31 @@ impl Descriptor {
     /// being used.
     #[inline]
     pub unsafe fn tss_segment_unchecked(tss: *const TaskStateSegment) -> Descriptor {
-        use self::DescriptorFlags as Flags;
-        use core::mem::size_of;
+        Self::tss_segment_raw(tss as u64, core::mem::size_of::<TaskStateSegment>())
+    }
+
+    /// Creates a TSS system descriptor for the given TSS with an I/O permission
+    /// bitmap.
+    ///
+    /// The segment limit of the descriptor covers the bitmap and its
+    /// terminating byte. See [`Descriptor::tss_segment`] for more information.
+    #[inline]
+    pub fn tss_segment_with_io_bitmap<const N: usize>(
+        tss: &'static TaskStateSegmentWithIoBitmap<N>,
+    ) -> Descriptor {
+        let ptr = tss as *const TaskStateSegmentWithIoBitmap<N>;
+        Self::tss_segment_raw(ptr as u64, TaskStateSegmentWithIoBitmap::<N>::SIZE)
+    }
 
-        let ptr = tss as u64;
+    fn tss_segment_raw(ptr: u64, size: usize) -> Descriptor {
+        use self::DescriptorFlags as Flags;
 
         let mut low = Flags::PRESENT.bits();
         // base
         low.set_bits(16..40, ptr.get_bits(0..24));
         low.set_bits(56..64, ptr.get_bits(24..32));
         // limit (the `-1` in needed since the bound is inclusive)
-        low.set_bits(0..16, (size_of::<TaskStateSegment>() - 1) as u64);
+        low.set_bits(0..16, (size - 1) as u64);
         // type (0b1001 = available 64-bit tss)
         low.set_bits(40..44, 0b1001);
 
@@ -458,6 +538,39 @@ impl Descriptor {
 
         Descriptor::SystemSegment(low, high)
     }
+
+    /// Creates a 64-bit call gate descriptor.
+    ///
+    /// A far call through the call gate transfers control to `offset` in the
+    /// code segment referenced by `selector`. `dpl` is the lowest privilege
+    /// level that can use the gate.
+    #[inline]
+    pub fn call_gate(
+        selector: SegmentSelector,
+        offset: VirtAddr,
+        dpl: PrivilegeLevel,
+    ) -> Descriptor {
+        use self::DescriptorFlags as Flags;
+
+        let offset = offset.as_u64();
+
+        let mut low = Flags::PRESENT.bits();
+        // offset
+        low.set_bits(0..16, offset.get_bits(0..16));
+        low.set_bits(48..64, offset.get_bits(16..32));
+        // segment selector
+        low.set_bits(16..32, u64::from(selector.0));
+        // type (0b1100 = 64-bit call gate)
+        low.set_bits(40..44, 0b1100);
+        // dpl
+        low.set_bits(45..47, dpl as u64);
+
+        // The type field of the upper half (bits 8..13) must be zero.
+        let mut high = 0;
+        high.set_bits(0..32, offset.get_bits(32..64));
+
+        Descriptor::SystemSegment(low, high)
+    }
 }
 
 #[cfg(test)]
@@ -535,6 +648,77 @@ mod tests {
         assert_eq!(gdt.entries().len(), 3);
     }
 
+    #[test]
+    pub fn from_entries_round_trip() {
+        let gdt = make_full_gdt();
+        let raw: Vec<u64> = gdt.iter().collect();
+        let copy = GlobalDescriptorTable::<8>::from_raw_entries(&raw);
+        assert_eq!(copy.entries(), gdt.entries());
+        assert_eq!(copy.limit(), gdt.limit());
+
+        // Appending continues after the copied entries.
+        let mut partial = GlobalDescriptorTable::<8>::from_raw_entries(&raw[..3]);
+        let selector = partial.append(Descriptor::tss_segment(&TSS));
+        assert_eq!(selector.index(), 3);
+        assert_eq!(partial.len(), 5);
+    }
+
+    #[test]
+    pub fn iter_entries() {
+        let mut gdt = GlobalDescriptorTable::new();
+        gdt.append(Descriptor::kernel_code_segment());
+        gdt.append(Descriptor::kernel_data_segment());
+        let tss = Descriptor::tss_segment(&TSS);
+        gdt.append(tss);
+
+        let (tss_low, tss_high) = match tss {
+            Descriptor::SystemSegment(low, high) => (low, high),
+            Descriptor::UserSegment(_) => unreachable!(),
+        };
+        let expected = [
+            0,
+            Flags::KERNEL_CODE64.bits(),
+            Flags::KERNEL_DATA.bits(),
+            tss_low,
+            tss_high,
+        ];
+        assert_eq!(gdt.len(), 5);
+        assert!(gdt.iter().eq(expected.iter().copied()));
+        assert_eq!(gdt.get(3), Some(tss_low));
+        assert_eq!(gdt.get(5), None);
+    }
+
+    #[test]
+    pub fn tss_with_io_bitmap_limit() {
+        static TSS_WITH_BITMAP: TaskStateSegmentWithIoBitmap<16> =
+            TaskStateSegmentWithIoBitmap::new();
+        match Descriptor::tss_segment_with_io_bitmap(&TSS_WITH_BITMAP) {
+            Descriptor::SystemSegment(low, _) => assert_eq!(low.get_bits(0..16), 0x68 + 16),
+            Descriptor::UserSegment(_) => panic!("TSS must be a system segment"),
+        }
+    }
+
+    #[test]
+    pub fn call_gate() {
+        let selector = SegmentSelector::new(1, PrivilegeLevel::Ring0);
+        let offset = VirtAddr::new(0xffff_8000_1234_5678);
+        let gate = Descriptor::call_gate(selector, offset, PrivilegeLevel::Ring3);
+        let (low, high) = match gate {
+            Descriptor::SystemSegment(low, high) => (low, high),
+            Descriptor::UserSegment(_) => panic!("call gate must be a system segment"),
+        };
+
+        let reassembled =
+            low.get_bits(0..16) | (low.get_bits(48..64) << 16) | (high.get_bits(0..32) << 32);
+        assert_eq!(reassembled, offset.as_u64());
+        assert_eq!(low.get_bits(16..32), 0x8);
+        assert_eq!(low.get_bits(40..44), 0b1100);
+        assert!(!low.get_bit(44));
+        assert!(low.get_bit(47));
+        assert_eq!(high.get_bits(40..45), 0);
+        assert_eq!(gate.dpl(), PrivilegeLevel::Ring3);
+    }
+
     #[test]
     pub fn descriptor_dpl() {
         assert_eq!(
@@ -0,0 +1,354 @@
+//! Access to the memory mapped registers of the high precision event timer (HPET).
+//!
+//! The HPET consists of a main counter that increments at a fixed frequency and a number of
+//! timers that raise an interrupt when the main counter reaches their comparator value. The
+//! physical address of the registers is reported by the ACPI HPET table.
+//!
+//! The main counter and the timers are either 64 or 32 bits wide, which is reported by
+//! [`Hpet::is_64bit`] and [`HpetTimerFlags::SIZE_CAPABLE`]. A 32-bit main counter wraps around
+//! after about 5 minutes at the usual frequency of 14.318 MHz, and the upper half of its value
+//! reads as 0. A 64-bit timer can be forced into 32-bit mode with
+//! [`HpetTimerFlags::MODE_32BIT`], in which case only the lower half of the comparator is
+//! compared against the lower half of the main counter.
+
+use crate::VirtAddr;
+use bitflags::bitflags;
+
+const CAPABILITIES: u64 = 0x000;
+const CONFIGURATION: u64 = 0x010;
+const MAIN_COUNTER: u64 = 0x0f0;
+
+/// Bit 0 of the general configuration register: start the main counter and allow interrupts.
+const ENABLE: u64 = 1 << 0;
+/// Bit 13 of the capabilities register: the main counter is 64 bits wide.
+const COUNT_SIZE_CAPABLE: u64 = 1 << 13;
+
+/// Returns the offset of the configuration and capability register of the given timer.
+const fn timer_config_offset(timer: u8) -> u64 {
+    0x100 + 0x20 * timer as u64
+}
+
+/// Returns the offset of the comparator register of the given timer.
+const fn timer_comparator_offset(timer: u8) -> u64 {
+    0x108 + 0x20 * timer as u64
+}
+
+/// Converts the period of the main counter in femtoseconds to its frequency in Hz, rounded to
+/// the nearest integer.
+///
+/// ## Panics
+///
+/// Panics if `period` is 0.
+#[inline]
+pub const fn femtoseconds_to_hz(period: u32) -> u64 {
+    assert!(period != 0, "HPET period must not be 0");
+    const FEMTOSECONDS_PER_SECOND: u64 = 1_000_000_000_000_000;
+    (FEMTOSECONDS_PER_SECOND + period as u64 / 2) / period as u64
+}
+
+bitflags! {
+    /// The configuration and capability register of an HPET timer.
+    #[repr(transparent)]
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    pub struct HpetTimerFlags: u64 {
+        /// The interrupt is level triggered instead of edge triggered.
+        const LEVEL_TRIGGERED = 1 << 1;
+        /// Raise an interrupt when the main counter reaches the comparator.
+        const INTERRUPT_ENABLE = 1 << 2;
+        /// Periodic mode: the comparator is incremented by the last written period on every
+        /// interrupt.
+        const PERIODIC = 1 << 3;
+        /// The timer supports periodic mode (read-only).
+        const PERIODIC_CAPABLE = 1 << 4;
+        /// The timer is 64 bits wide (read-only).
+        const SIZE_CAPABLE = 1 << 5;
+        /// In periodic mode, the next write to the comparator sets its value instead of the
+        /// period. Cleared automatically after the write.
+        const SET_VALUE = 1 << 6;
+        /// Force a 64-bit timer into 32-bit mode.
+        const MODE_32BIT = 1 << 8;
+        /// Deliver the interrupt as a front side bus (MSI) message instead of through the I/O
+        /// APIC.
+        const FSB_ENABLE = 1 << 14;
+        /// The timer supports FSB interrupt delivery (read-only).
+        const FSB_CAPABLE = 1 << 15;
+    }
+}
+
+/// The high precision event timer.
+#[derive(Debug)]
+pub struct Hpet {
+    base: VirtAddr,
+}
+
+impl Hpet {
+    /// Creates an HPET whose registers are mapped at `base`.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that the 1KiB register block of the HPET is mapped as
+    /// uncacheable memory at `base`, and that no other instance accesses the same HPET.
+    #[inline]
+    pub const unsafe fn new(base: VirtAddr) -> Self {
+        Self { base }
+    }
+
+    #[inline]
+    fn read(&self, offset: u64) -> u64 {
+        unsafe { (self.base + offset).as_ptr::<u64>().read_volatile() }
+    }
+
+    #[inline]
+    unsafe fn write(&mut self, offset: u64, value: u64) {
+        unsafe {
+            (self.base + offset)
+                .as_mut_ptr::<u64>()
+                .write_volatile(value)
+        }
+    }
+
+    /// Returns the general capabilities and ID register.
+    #[inline]
+    pub fn capabilities(&self) -> u64 {
+        self.read(CAPABILITIES)
+    }
+
+    /// Returns the period of the main counter in femtoseconds.
+    #[inline]
+    pub fn period_femtoseconds(&self) -> u32 {
+        (self.capabilities() >> 32) as u32
+    }
+
+    /// Returns the frequency of the main counter in Hz.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the reported [period](Self::period_femtoseconds) is 0, which is not a valid
+    /// value for an HPET.
+    #[inline]
+    pub fn frequency(&self) -> u64 {
+        femtoseconds_to_hz(self.period_femtoseconds())
+    }
+
+    /// Returns the number of timers.
+    #[inline]
+    pub fn timer_count(&self) -> u8 {
+        ((self.capabilities() >> 8) & 0x1f) as u8 + 1
+    }
+
+    /// Returns whether the main counter is 64 bits wide.
+    #[inline]
+    pub fn is_64bit(&self) -> bool {
+        self.capabilities() & COUNT_SIZE_CAPABLE != 0
+    }
+
+    /// Returns whether the main counter is running.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.read(CONFIGURATION) & ENABLE != 0
+    }
+
+    /// Starts the main counter and enables the interrupts of the timers.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that handlers are installed for all timers with
+    /// [`HpetTimerFlags::INTERRUPT_ENABLE`] set.
+    #[inline]
+    pub unsafe fn enable(&mut self) {
+        let config = self.read(CONFIGURATION);
+        unsafe { self.write(CONFIGURATION, config | ENABLE) };
+    }
+
+    /// Stops the main counter and disables the interrupts of all timers.
+    #[inline]
+    pub fn disable(&mut self) {
+        let config = self.read(CONFIGURATION);
+        unsafe { self.write(CONFIGURATION, config & !ENABLE) };
+    }
+
+    /// Returns the value of the main counter.
+    #[inline]
+    pub fn main_counter(&self) -> u64 {
+        self.read(MAIN_COUNTER)
+    }
+
+    /// Sets the value of the main counter. The counter should be stopped while doing this.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that no code relies on the main counter being monotonic.
+    #[inline]
+    pub unsafe fn set_main_counter(&mut self, value: u64) {
+        unsafe { self.write(MAIN_COUNTER, value) };
+    }
+
+    /// Returns the configuration and capabilities of the given timer.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `timer` is not smaller than [`timer_count`](Self::timer_count).
+    #[inline]
+    pub fn timer_config(&self, timer: u8) -> HpetTimerFlags {
+        self.assert_timer(timer);
+        HpetTimerFlags::from_bits_truncate(self.read(timer_config_offset(timer)))
+    }
+
+    /// Returns the I/O APIC inputs the interrupt of the given timer can be routed to, as a
+    /// bitmask.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `timer` is not smaller than [`timer_count`](Self::timer_count).
+    #[inline]
+    pub fn timer_routing_capabilities(&self, timer: u8) -> u32 {
+        self.assert_timer(timer);
+        (self.read(timer_config_offset(timer)) >> 32) as u32
+    }
+
+    /// Writes the configuration of the given timer and routes its interrupt to the I/O APIC
+    /// input `route`. Read-only flags are ignored.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `timer` is not smaller than [`timer_count`](Self::timer_count) or `route` is
+    /// not one of the [supported inputs](Self::timer_routing_capabilities).
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that a handler is installed for the interrupt if
+    /// [`HpetTimerFlags::INTERRUPT_ENABLE`] is set.
+    #[inline]
+    pub unsafe fn set_timer_config(&mut self, timer: u8, flags: HpetTimerFlags, route: u8) {
+        assert!(
+            route < 32 && self.timer_routing_capabilities(timer) & (1 << route) != 0,
+            "interrupt route not supported by the timer"
+        );
+        let value = flags.bits() | u64::from(route) << 9;
+        unsafe { self.write(timer_config_offset(timer), value) };
+    }
+
+    /// Returns the comparator value of the given timer.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `timer` is not smaller than [`timer_count`](Self::timer_count).
+    #[inline]
+    pub fn comparator(&self, timer: u8) -> u64 {
+        self.assert_timer(timer);
+        self.read(timer_comparator_offset(timer))
+    }
+
+    /// Sets the comparator of the given timer, which fires once the main counter reaches
+    /// `value`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `timer` is not smaller than [`timer_count`](Self::timer_count).
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that a handler is installed for the interrupt of the timer.
+    #[inline]
+    pub unsafe fn set_comparator(&mut self, timer: u8, value: u64) {
+        self.assert_timer(timer);
+        unsafe { self.write(timer_comparator_offset(timer), value) };
+    }
+
+    /// Configures the given timer to fire every `period` ticks of the main counter, starting
+    /// `period` ticks from now. Other configuration flags are preserved.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `timer` is not smaller than [`timer_count`](Self::timer_count) or does not
+    /// support periodic mode.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that a handler is installed for the interrupt of the timer.
+    #[inline]
+    pub unsafe fn set_periodic(&mut self, timer: u8, period: u64) {
+        assert!(
+            self.timer_config(timer)
+                .contains(HpetTimerFlags::PERIODIC_CAPABLE),
+            "timer does not support periodic mode"
+        );
+        let config = self.read(timer_config_offset(timer));
+        let flags =
+            HpetTimerFlags::PERIODIC | HpetTimerFlags::SET_VALUE | HpetTimerFlags::INTERRUPT_ENABLE;
+        let start = self.main_counter().wrapping_add(period);
+        unsafe {
+            self.write(timer_config_offset(timer), config | flags.bits());
+            // With `SET_VALUE` set, the first write sets the comparator, the second the period.
+            self.write(timer_comparator_offset(timer), start);
+            self.write(timer_comparator_offset(timer), period);
+        }
+    }
+
+    fn assert_timer(&self, timer: u8) {
+        assert!(timer < self.timer_count(), "HPET timer does not exist");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn register_offsets() {
+        assert_eq!(timer_config_offset(0), 0x100);
+        assert_eq!(timer_comparator_offset(0), 0x108);
+        assert_eq!(timer_config_offset(2), 0x140);
+        assert_eq!(timer_comparator_offset(31), 0x4e8);
+    }
+
+    #[test]
+    fn period_conversion() {
+        // 14.31818 MHz
+        assert_eq!(femtoseconds_to_hz(69_841_279), 14_318_180);
+        assert_eq!(femtoseconds_to_hz(100_000_000), 10_000_000);
+        assert_eq!(femtoseconds_to_hz(1), 1_000_000_000_000_000);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_period() {
+        femtoseconds_to_hz(0);
+    }
+
+    #[test]
+    fn hpet_mmio() {
+        #[repr(align(1024))]
+        struct Registers([u64; 128]);
+
+        let mut registers = Registers([0; 128]);
+        // 3 timers, 64-bit main counter, period 69841279 fs
+        registers.0[0] = (69_841_279 << 32) | COUNT_SIZE_CAPABLE | (2 << 8);
+        registers.0[0x100 / 8] = (0b1100 << 32) | HpetTimerFlags::PERIODIC_CAPABLE.bits();
+        registers.0[0xf0 / 8] = 1000;
+        let mut hpet = unsafe { Hpet::new(VirtAddr::from_ptr(registers.0.as_mut_ptr())) };
+
+        assert_eq!(hpet.timer_count(), 3);
+        assert!(hpet.is_64bit());
+        assert_eq!(hpet.frequency(), 14_318_180);
+        assert_eq!(hpet.timer_routing_capabilities(0), 0b1100);
+        assert!(!hpet.is_enabled());
+
+        unsafe {
+            hpet.set_periodic(0, 500);
+            hpet.set_comparator(2, 0x1234);
+            hpet.enable();
+        }
+        assert!(hpet.is_enabled());
+        assert_eq!(
+            hpet.timer_config(0),
+            HpetTimerFlags::PERIODIC_CAPABLE
+                | HpetTimerFlags::PERIODIC
+                | HpetTimerFlags::SET_VALUE
+                | HpetTimerFlags::INTERRUPT_ENABLE
+        );
+        // The mock does not implement the period write, so the second write remains.
+        assert_eq!(hpet.comparator(0), 500);
+        assert_eq!(hpet.comparator(2), 0x1234);
+    }
+}
@@ -447,40 +447,56 @@ pub struct InterruptDescriptorTable {
 }
 
 impl InterruptDescriptorTable {
+    /// An IDT filled with non-present entries.
+    ///
+    /// Unlike [`new`](Self::new), which is only a `const fn` on Rust 1.61 and later, this can be
+    /// used to place the table directly in a `static` on all supported Rust versions and fill
+    /// it at runtime (e.g. behind a lock or before it is loaded):
+    ///
+    /// ```
+    /// use x86_64::structures::idt::InterruptDescriptorTable;
+    ///
+    /// static mut IDT: InterruptDescriptorTable = InterruptDescriptorTable::DEFAULT;
+    /// ```
+    pub const DEFAULT: Self = InterruptDescriptorTable {
+        divide_error: Entry::missing(),
+        debug: Entry::missing(),
+        non_maskable_interrupt: Entry::missing(),
+        breakpoint: Entry::missing(),
+        overflow: Entry::missing(),
+        bound_range_exceeded: Entry::missing(),
+        invalid_opcode: Entry::missing(),
+        device_not_available: Entry::missing(),
+        double_fault: Entry::missing(),
+        coprocessor_segment_overrun: Entry::missing(),
+        invalid_tss: Entry::missing(),
+        segment_not_present: Entry::missing(),
+        stack_segment_fault: Entry::missing(),
+        general_protection_fault: Entry::missing(),
+        page_fault: Entry::missing(),
+        reserved_1: Entry::missing(),
+        x87_floating_point: Entry::missing(),
+        alignment_check: Entry::missing(),
+        machine_check: Entry::missing(),
+        simd_floating_point: Entry::missing(),
+        virtualization: Entry::missing(),
+        cp_protection_exception: Entry::missing(),
+        reserved_2: [Entry::missing(); 6],
+        hv_injection_exception: Entry::missing(),
+        vmm_communication_exception: Entry::missing(),
+        security_exception: Entry::missing(),
+        reserved_3: Entry::missing(),
+        interrupts: [Entry::missing(); 256 - 32],
+    };
+
     /// Creates a new IDT filled with non-present entries.
+    ///
+    /// On Rust 1.61 and later, this is a `const fn`. See [`DEFAULT`](Self::DEFAULT) for use in
+    /// `static`s on older versions.
     #[inline]
     #[rustversion::attr(since(1.61), const)]
     pub fn new() -> InterruptDescriptorTable {
-        InterruptDescriptorTable {
-            divide_error: Entry::missing(),
-            debug: Entry::missing(),
-            non_maskable_interrupt: Entry::missing(),
-            breakpoint: Entry::missing(),
-            overflow: Entry::missing(),
-            bound_range_exceeded: Entry::missing(),
-            invalid_opcode: Entry::missing(),
-            device_not_available: Entry::missing(),
-            double_fault: Entry::missing(),
-            coprocessor_segment_overrun: Entry::missing(),
-            invalid_tss: Entry::missing(),
-            segment_not_present: Entry::missing(),
-            stack_segment_fault: Entry::missing(),
-            general_protection_fault: Entry::missing(),
-            page_fault: Entry::missing(),
-            reserved_1: Entry::missing(),
-            x87_floating_point: Entry::missing(),
-            alignment_check: Entry::missing(),
-            machine_check: Entry::missing(),
-            simd_floating_point: Entry::missing(),
-            virtualization: Entry::missing(),
-            cp_protection_exception: Entry::missing(),
-            reserved_2: [Entry::missing(); 6],
-            hv_injection_exception: Entry::missing(),
-            vmm_communication_exception: Entry::missing(),
-            security_exception: Entry::missing(),
-            reserved_3: Entry::missing(),
-            interrupts: [Entry::missing(); 256 - 32],
-        }
+        Self::DEFAULT
     }
 
     /// Resets all entries of this IDT in place.
@@ -506,6 +522,27 @@ impl InterruptDescriptorTable {
     /// - `self` always stays at the same memory location. It is recommended to wrap it in
     ///   a `Box`.
     ///
+    /// The CPU reads the table through its address on every interrupt, so these requirements
+    /// hold until a different IDT is loaded, not just until this function returns. Modifying
+    /// the table while it is loaded is only sound if no interrupt can observe a partially
+    /// written entry (e.g. because interrupts are disabled).
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use x86_64::structures::idt::InterruptDescriptorTable;
+    ///
+    /// struct Cpu {
+    ///     idt: Box<InterruptDescriptorTable>,
+    /// }
+    ///
+    /// let cpu = Cpu {
+    ///     idt: Box::new(InterruptDescriptorTable::new()),
+    /// };
+    /// // SAFETY: the box keeps the table at a fixed address and `cpu` is not
+    /// // dropped before a different IDT is loaded.
+    /// unsafe { cpu.idt.load_unsafe() };
+    /// ```
     #[cfg(all(feature = "instructions", target_arch = "x86_64"))]
     #[inline]
     pub unsafe fn load_unsafe(&self) {
@@ -564,6 +601,45 @@ impl InterruptDescriptorTable {
         let (lower_idx, upper_idx) = self.condition_slice_bounds(bounds);
         &mut self.interrupts[(lower_idx - 32)..(upper_idx - 32)]
     }
+
+    /// Returns the entry for the given vector with the handler function type erased.
+    ///
+    /// Unlike indexing, this also works for exceptions that push an error code or
+    /// must not return, as well as for reserved vectors.
+    #[inline]
+    pub fn get(&self, vector: u8) -> &Entry<()> {
+        &self.as_erased()[usize::from(vector)]
+    }
+
+    /// Returns a mutable reference to the entry for the given vector with the handler
+    /// function type erased.
+    ///
+    /// As the handler function type is erased, handlers can only be set through the unsafe
+    /// `Entry::set_handler_addr` method.
+    ///
+    /// Panics if the vector is reserved.
+    #[inline]
+    pub fn get_mut(&mut self, vector: u8) -> &mut Entry<()> {
+        if let 15 | 22..=27 | 31 = vector {
+            panic!("entry {} is reserved", vector);
+        }
+        // SAFETY: See `as_erased`.
+        let entries = unsafe { &mut *(self as *mut Self as *mut [Entry<()>; 256]) };
+        &mut entries[usize::from(vector)]
+    }
+
+    /// Returns an iterator over all 256 entries and their vectors with the handler function
+    /// type erased.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &Entry<()>)> {
+        (0..=255).zip(self.as_erased().iter())
+    }
+
+    fn as_erased(&self) -> &[Entry<()>; 256] {
+        // SAFETY: The IDT is a `repr(C)` struct consisting of exactly 256 entries and the layout
+        // of `Entry<F>` does not depend on `F`.
+        unsafe { &*(self as *const Self as *const [Entry<()>; 256]) }
+    }
 }
 
 impl Default for InterruptDescriptorTable {
@@ -957,7 +1033,9 @@ impl EntryOptions {
         self
     }
 
-    fn present(&self) -> bool {
+    /// Returns whether the present bit is set.
+    #[inline]
+    pub fn present(&self) -> bool {
         self.bits.get_bit(15)
     }
 
@@ -977,7 +1055,9 @@ impl EntryOptions {
         self
     }
 
-    fn privilege_level(&self) -> PrivilegeLevel {
+    /// Returns the required privilege level (DPL) for invoking the handler.
+    #[inline]
+    pub fn privilege_level(&self) -> PrivilegeLevel {
         PrivilegeLevel::from_u16(self.bits.get_bits(13..15))
     }
 
@@ -996,13 +1076,24 @@ impl EntryOptions {
     /// valid and not used by other interrupts. Otherwise, memory safety violations are possible.
     #[inline]
     pub unsafe fn set_stack_index(&mut self, index: u16) -> &mut Self {
+        assert!(
+            index < 7,
+            "IST index must be in the range 0..7, got {}",
+            index
+        );
         // The hardware IST index starts at 1, but our software IST index
         // starts at 0. Therefore we need to add 1 here.
         self.bits.set_bits(0..3, index + 1);
         self
     }
 
-    fn stack_index(&self) -> Option<u16> {
+    /// Returns the Interrupt Stack Table (IST) index assigned to this handler, or `None` if
+    /// the existing stack is used.
+    ///
+    /// Like [`set_stack_index`](EntryOptions::set_stack_index), this index starts at 0, i.e.
+    /// it is one less than the value stored in the entry.
+    #[inline]
+    pub fn stack_index(&self) -> Option<u16> {
         self.bits.get_bits(0..3).checked_sub(1)
     }
 }
@@ -1050,6 +1141,25 @@ impl InterruptStackFrame {
     ///
     /// Also, it is not fully clear yet whether modifications of the interrupt stack frame are
     /// officially supported by LLVM's x86 interrupt calling convention.
+    ///
+    /// The modified values are loaded by `iretq` when the handler returns, so changing the
+    /// instruction or stack pointer redirects execution of the interrupted code.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use x86_64::structures::idt::InterruptStackFrame;
+    ///
+    /// /// Called by an invalid opcode handler to resume after a `ud2` instruction.
+    /// fn skip_ud2(stack_frame: &mut InterruptStackFrame) {
+    ///     // `ud2` is two bytes long.
+    ///     unsafe {
+    ///         stack_frame
+    ///             .as_mut()
+    ///             .update(|frame| frame.instruction_pointer += 2u64);
+    ///     }
+    /// }
+    /// ```
     #[inline]
     pub unsafe fn as_mut(&mut self) -> Volatile<&mut InterruptStackFrameValue> {
         Volatile::new(&mut self.0)
@@ -1163,6 +1273,12 @@ impl fmt::Debug for InterruptStackFrameValue {
 bitflags! {
     /// Describes an page fault error code.
     ///
+    /// Bits 0 to 4 are defined on all processors. The remaining flags are only
+    /// reported if the corresponding feature is supported and enabled:
+    /// `PROTECTION_KEY` requires protection keys (`CR4.PKE` or `CR4.PKS`),
+    /// `SHADOW_STACK` requires CET shadow stacks, `SGX` is Intel-only and `RMP`
+    /// is AMD-only (SEV-SNP).
+    ///
     /// This structure is defined by the following manual sections:
     ///   * AMD Volume 2: 8.4.2
     ///   * Intel Volume 3A: 4.7
@@ -1192,6 +1308,10 @@ bitflags! {
         const INSTRUCTION_FETCH = 1 << 4;
 
         /// If this flag is set, it indicates that the page fault was caused by a protection key.
+        ///
+        /// In this case the page is present and the access was permitted by the paging
+        /// structures, but denied by the rights in [`Pkru`](crate::registers::pkru::Pkru) (or
+        /// `IA32_PKRS` for supervisor pages).
         const PROTECTION_KEY = 1 << 5;
 
         /// If this flag is set, it indicates that the page fault was caused by a shadow stack
@@ -1720,6 +1840,88 @@ mod test {
         }
     }
 
+    #[test]
+    fn iter_entries() {
+        let mut idt = InterruptDescriptorTable::new();
+        idt.get_mut(8).pointer_low = 0x5678;
+        idt.get_mut(8).pointer_middle = 0x1234;
+        idt.get_mut(0x80).pointer_low = 0x4321;
+        idt.get_mut(0x80).pointer_middle = 0x8765;
+        assert_eq!(idt.iter().count(), 256);
+        for (vector, entry) in idt.iter() {
+            assert_eq!(entry, idt.get(vector));
+        }
+        assert_eq!(idt.double_fault.handler_addr().as_u64(), 0x1234_5678);
+        assert_eq!(idt.get(8).handler_addr().as_u64(), 0x1234_5678);
+        assert_eq!(idt[0x80].handler_addr().as_u64(), 0x8765_4321);
+    }
+
+    #[test]
+    #[should_panic]
+    fn get_mut_reserved() {
+        InterruptDescriptorTable::new().get_mut(22);
+    }
+
+    #[test]
+    fn entry_options_getters() {
+        let mut options = EntryOptions::minimal();
+        assert_eq!(options.stack_index(), None);
+        assert!(!options.present());
+        assert_eq!(options.privilege_level(), PrivilegeLevel::Ring0);
+
+        unsafe { options.set_stack_index(1) };
+        options
+            .set_present(true)
+            .set_privilege_level(PrivilegeLevel::Ring3);
+        assert_eq!(options.bits.get_bits(0..3), 2);
+        assert_eq!(options.stack_index(), Some(1));
+        assert!(options.present());
+        assert_eq!(options.privilege_level(), PrivilegeLevel::Ring3);
+    }
+
+    #[test]
+    #[should_panic(expected = "IST index must be in the range 0..7")]
+    fn invalid_stack_index() {
+        unsafe { EntryOptions::minimal().set_stack_index(7) };
+    }
+
+    #[test]
+    fn exception_field_offsets() {
+        let idt = InterruptDescriptorTable::new();
+        let base = &idt as *const _ as usize;
+        let offset = |entry: *const u8| entry as usize - base;
+        assert_eq!(
+            offset(&idt.cp_protection_exception as *const _ as _),
+            21 * 16
+        );
+        assert_eq!(
+            offset(&idt.hv_injection_exception as *const _ as _),
+            28 * 16
+        );
+        assert_eq!(
+            offset(&idt.vmm_communication_exception as *const _ as _),
+            29 * 16
+        );
+        assert_eq!(offset(&idt.security_exception as *const _ as _), 30 * 16);
+    }
+
+    #[test]
+    fn idt_in_static() {
+        static IDT: InterruptDescriptorTable = InterruptDescriptorTable::DEFAULT;
+        for index in 0..=255 {
+            assert!(!entry_present(&IDT, index));
+        }
+    }
+
+    #[rustversion::since(1.61)]
+    #[test]
+    fn idt_new_in_static() {
+        static IDT: InterruptDescriptorTable = InterruptDescriptorTable::new();
+        for index in 0..=255 {
+            assert!(!entry_present(&IDT, index));
+        }
+    }
+
     #[test]
     fn idt_fmt_debug() {
         dbg!(InterruptDescriptorTable::new());
@@ -1739,6 +1941,50 @@ mod test {
         })
     }
 
+    #[cfg(all(
+        feature = "instructions",
+        feature = "abi_x86_interrupt",
+        target_arch = "x86_64"
+    ))]
+    // there seems to be a bug in LLVM that causes rustc to crash on windows when compiling this test:
+    // https://github.com/rust-osdev/x86_64/pull/285#issuecomment-962642984
+    #[cfg(not(windows))]
+    #[test]
+    fn handler_addr() {
+        extern "x86-interrupt" fn breakpoint_handler(_stack_frame: InterruptStackFrame) {}
+
+        let mut idt = InterruptDescriptorTable::new();
+        assert_eq!(idt.breakpoint.handler_addr(), VirtAddr::zero());
+
+        idt.breakpoint.set_handler_fn(breakpoint_handler);
+        assert_eq!(
+            idt.breakpoint.handler_addr(),
+            VirtAddr::from_ptr(breakpoint_handler as *const ())
+        );
+        assert!(idt.breakpoint.options.bits.get_bit(15));
+
+        let addr = VirtAddr::new(0xffff_8000_1234_5670);
+        unsafe { idt[32].set_handler_addr(addr) }.set_privilege_level(PrivilegeLevel::Ring3);
+        assert_eq!(idt[32].handler_addr(), addr);
+    }
+
+    #[test]
+    fn page_fault_error_code_protection_key() {
+        // A user-mode write denied by a protection key.
+        let code = PageFaultErrorCode::from_bits_truncate(0b10_0111);
+        assert!(code.contains(PageFaultErrorCode::PROTECTION_KEY));
+        assert_eq!(
+            code,
+            PageFaultErrorCode::PROTECTION_VIOLATION
+                | PageFaultErrorCode::CAUSED_BY_WRITE
+                | PageFaultErrorCode::USER_MODE
+                | PageFaultErrorCode::PROTECTION_KEY
+        );
+        assert_eq!(PageFaultErrorCode::SHADOW_STACK.bits(), 1 << 6);
+        assert_eq!(PageFaultErrorCode::SGX.bits(), 1 << 15);
+        assert_eq!(PageFaultErrorCode::RMP.bits(), 1 << 31);
+    }
+
     #[test]
     fn isr_frame_manipulation() {
         let mut frame = InterruptStackFrame(InterruptStackFrameValue {
@@ -1754,5 +2000,7 @@ mod test {
         unsafe {
             frame.as_mut().update(|f| f.instruction_pointer += 2u64);
         }
+        assert_eq!(frame.instruction_pointer, VirtAddr::new(0x1002));
+        assert_eq!(frame.stack_pointer, VirtAddr::new(0x2000));
     }
 }
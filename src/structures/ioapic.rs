@@ -0,0 +1,305 @@
+//! Access to the redirection table of an I/O APIC.
+//!
+//! The I/O APIC routes hardware interrupts to the local APICs. Its registers are not mapped
+//! directly: the memory mapped window only consists of an index register (`IOREGSEL`) at offset
+//! `0x00` and a data register (`IOWIN`) at offset `0x10`. A register is accessed by first writing
+//! its index to `IOREGSEL` and then reading or writing `IOWIN`. As this sequence is not atomic,
+//! accesses to the same I/O APIC must be serialized, which is ensured by the `&mut self` receivers
+//! of [`IoApic`].
+
+use crate::structures::apic::DeliveryMode;
+use crate::VirtAddr;
+
+const IOREGSEL: u64 = 0x00;
+const IOWIN: u64 = 0x10;
+
+const REGISTER_ID: u32 = 0x00;
+const REGISTER_VERSION: u32 = 0x01;
+const REGISTER_REDIRECTION_TABLE: u32 = 0x10;
+
+/// An I/O APIC.
+#[derive(Debug)]
+pub struct IoApic {
+    base: VirtAddr,
+}
+
+impl IoApic {
+    /// Creates an I/O APIC whose registers are mapped at `base`.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that the registers of the I/O APIC (as reported by the ACPI MADT)
+    /// are mapped as uncacheable memory at `base`, and that no other instance accesses the same
+    /// I/O APIC.
+    #[inline]
+    pub const unsafe fn new(base: VirtAddr) -> Self {
+        Self { base }
+    }
+
+    #[inline]
+    fn read_register(&mut self, index: u32) -> u32 {
+        unsafe {
+            (self.base + IOREGSEL)
+                .as_mut_ptr::<u32>()
+                .write_volatile(index);
+            (self.base + IOWIN).as_ptr::<u32>().read_volatile()
+        }
+    }
+
+    #[inline]
+    unsafe fn write_register(&mut self, index: u32, value: u32) {
+        unsafe {
+            (self.base + IOREGSEL)
+                .as_mut_ptr::<u32>()
+                .write_volatile(index);
+            (self.base + IOWIN)
+                .as_mut_ptr::<u32>()
+                .write_volatile(value);
+        }
+    }
+
+    /// Returns the ID of the I/O APIC.
+    #[inline]
+    pub fn id(&mut self) -> u8 {
+        ((self.read_register(REGISTER_ID) >> 24) & 0xf) as u8
+    }
+
+    /// Returns the version of the I/O APIC.
+    #[inline]
+    pub fn version(&mut self) -> u8 {
+        self.read_register(REGISTER_VERSION) as u8
+    }
+
+    /// Returns the number of entries in the redirection table, i.e. the number of interrupt
+    /// inputs of the I/O APIC.
+    #[inline]
+    pub fn max_redirection_entries(&mut self) -> u16 {
+        u16::from((self.read_register(REGISTER_VERSION) >> 16) as u8) + 1
+    }
+
+    /// Reads the redirection entry of the interrupt input `irq`.
+    ///
+    /// `irq` must be smaller than [`max_redirection_entries`](Self::max_redirection_entries).
+    #[inline]
+    pub fn read_redirection_entry(&mut self, irq: u8) -> RedirectionEntry {
+        let index = REGISTER_REDIRECTION_TABLE + 2 * u32::from(irq);
+        let low = self.read_register(index);
+        let high = self.read_register(index + 1);
+        RedirectionEntry::from_bits(u64::from(high) << 32 | u64::from(low))
+    }
+
+    /// Writes the redirection entry of the interrupt input `irq`.
+    ///
+    /// The entry is masked while the destination is written, so that no interrupt is delivered
+    /// with a partially written entry.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that `irq` is smaller than
+    /// [`max_redirection_entries`](Self::max_redirection_entries) and, if the entry is not
+    /// masked, that a handler is installed for its vector on the destination.
+    #[inline]
+    pub unsafe fn write_redirection_entry(&mut self, irq: u8, entry: RedirectionEntry) {
+        let index = REGISTER_REDIRECTION_TABLE + 2 * u32::from(irq);
+        let bits = entry.bits();
+        unsafe {
+            self.write_register(index, (bits as u32) | RedirectionEntry::MASKED);
+            self.write_register(index + 1, (bits >> 32) as u32);
+            self.write_register(index, bits as u32);
+        }
+    }
+}
+
+/// How the destination of a [`RedirectionEntry`] is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DestinationMode {
+    /// The destination is an APIC ID.
+    Physical,
+    /// The destination is a set of processors selected by their logical destination registers.
+    Logical,
+}
+
+/// The polarity of an interrupt input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PinPolarity {
+    /// The interrupt is active while the input is high. This is the default for ISA IRQs.
+    ActiveHigh,
+    /// The interrupt is active while the input is low. This is the default for PCI IRQs.
+    ActiveLow,
+}
+
+/// The trigger mode of an interrupt input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TriggerMode {
+    /// The interrupt is triggered by an edge. This is the default for ISA IRQs.
+    Edge,
+    /// The interrupt is triggered by a level and remains pending until the end of interrupt is
+    /// signaled. This is the default for PCI IRQs.
+    Level,
+}
+
+/// An entry of the redirection table of an [`IoApic`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct RedirectionEntry(u64);
+
+impl RedirectionEntry {
+    const MASKED: u32 = 1 << 16;
+
+    /// Creates an unmasked, edge triggered, active high entry with fixed delivery of `vector`
+    /// to the processor with the APIC ID `destination`.
+    #[inline]
+    pub const fn new(vector: u8, destination: u8) -> Self {
+        Self((destination as u64) << 56 | vector as u64)
+    }
+
+    /// Creates an entry from its raw value.
+    #[inline]
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    /// Returns the raw value of the entry.
+    #[inline]
+    pub const fn bits(self) -> u64 {
+        self.0
+    }
+
+    /// Returns the vector.
+    #[inline]
+    pub const fn vector(self) -> u8 {
+        self.0 as u8
+    }
+
+    /// Returns the delivery mode, or `None` if it is reserved.
+    #[inline]
+    pub const fn delivery_mode(self) -> Option<DeliveryMode> {
+        DeliveryMode::from_bits((self.0 >> 8) as u8 & 0b111)
+    }
+
+    /// Sets the delivery mode.
+    #[inline]
+    pub const fn with_delivery_mode(self, mode: DeliveryMode) -> Self {
+        Self(self.0 & !(0b111 << 8) | (mode as u64) << 8)
+    }
+
+    /// Returns the destination mode.
+    #[inline]
+    pub const fn destination_mode(self) -> DestinationMode {
+        if self.bit(11) {
+            DestinationMode::Logical
+        } else {
+            DestinationMode::Physical
+        }
+    }
+
+    /// Sets the destination mode.
+    #[inline]
+    pub const fn with_destination_mode(self, mode: DestinationMode) -> Self {
+        self.with_bit(11, matches!(mode, DestinationMode::Logical))
+    }
+
+    /// Returns whether the interrupt is waiting to be delivered (read-only).
+    #[inline]
+    pub const fn is_pending(self) -> bool {
+        self.bit(12)
+    }
+
+    /// Returns the polarity of the interrupt input.
+    #[inline]
+    pub const fn polarity(self) -> PinPolarity {
+        if self.bit(13) {
+            PinPolarity::ActiveLow
+        } else {
+            PinPolarity::ActiveHigh
+        }
+    }
+
+    /// Sets the polarity of the interrupt input.
+    #[inline]
+    pub const fn with_polarity(self, polarity: PinPolarity) -> Self {
+        self.with_bit(13, matches!(polarity, PinPolarity::ActiveLow))
+    }
+
+    /// Returns the trigger mode.
+    #[inline]
+    pub const fn trigger_mode(self) -> TriggerMode {
+        if self.bit(15) {
+            TriggerMode::Level
+        } else {
+            TriggerMode::Edge
+        }
+    }
+
+    /// Sets the trigger mode.
+    #[inline]
+    pub const fn with_trigger_mode(self, mode: TriggerMode) -> Self {
+        self.with_bit(15, matches!(mode, TriggerMode::Level))
+    }
+
+    /// Returns whether the interrupt is masked.
+    #[inline]
+    pub const fn is_masked(self) -> bool {
+        self.bit(16)
+    }
+
+    /// Sets or clears the mask bit.
+    #[inline]
+    pub const fn with_masked(self, masked: bool) -> Self {
+        self.with_bit(16, masked)
+    }
+
+    /// Returns the destination.
+    #[inline]
+    pub const fn destination(self) -> u8 {
+        (self.0 >> 56) as u8
+    }
+
+    /// Sets the destination.
+    #[inline]
+    pub const fn with_destination(self, destination: u8) -> Self {
+        Self(self.0 & !(0xff << 56) | (destination as u64) << 56)
+    }
+
+    const fn bit(self, bit: u32) -> bool {
+        self.0 & (1 << bit) != 0
+    }
+
+    const fn with_bit(self, bit: u32, value: bool) -> Self {
+        if value {
+            Self(self.0 | 1 << bit)
+        } else {
+            Self(self.0 & !(1 << bit))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redirection_entry_encoding() {
+        let entry = RedirectionEntry::new(0x31, 2)
+            .with_trigger_mode(TriggerMode::Level)
+            .with_polarity(PinPolarity::ActiveLow)
+            .with_masked(true);
+        assert_eq!(entry.bits(), 0x0200_0000_0001_a031);
+        assert_eq!(entry.vector(), 0x31);
+        assert_eq!(entry.destination(), 2);
+        assert_eq!(entry.delivery_mode(), Some(DeliveryMode::Fixed));
+        assert_eq!(entry.destination_mode(), DestinationMode::Physical);
+        assert_eq!(entry.trigger_mode(), TriggerMode::Level);
+        assert_eq!(entry.polarity(), PinPolarity::ActiveLow);
+        assert!(entry.is_masked());
+        assert!(!entry.is_pending());
+
+        let entry = entry
+            .with_masked(false)
+            .with_delivery_mode(DeliveryMode::LowestPriority)
+            .with_destination_mode(DestinationMode::Logical)
+            .with_destination(0xf0);
+        assert_eq!(entry.bits(), 0xf000_0000_0000_a931);
+        assert_eq!(RedirectionEntry::from_bits(0x300).delivery_mode(), None);
+    }
+}
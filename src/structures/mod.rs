@@ -2,11 +2,21 @@
 
 use crate::VirtAddr;
 
+#[cfg(all(feature = "instructions", target_arch = "x86_64"))]
+pub mod apic;
 pub mod gdt;
+#[cfg(all(feature = "instructions", target_arch = "x86_64"))]
+pub mod hpet;
 
 pub mod idt;
+#[cfg(all(feature = "instructions", target_arch = "x86_64"))]
+pub mod ioapic;
 
 pub mod paging;
+#[cfg(all(feature = "instructions", target_arch = "x86_64"))]
+pub mod pic;
+#[cfg(all(feature = "instructions", target_arch = "x86_64"))]
+pub mod pit;
 pub mod port;
 pub mod tss;
 
@@ -1,9 +1,12 @@
 //! Abstractions for default-sized and huge physical memory frames.
 
 use super::page::AddressNotAligned;
+use super::range;
 use crate::structures::paging::page::{PageSize, Size4KiB};
 use crate::PhysAddr;
 use core::fmt;
+#[cfg(feature = "step_trait")]
+use core::iter::Step;
 use core::marker::PhantomData;
 use core::ops::{Add, AddAssign, Sub, SubAssign};
 
@@ -132,8 +135,75 @@ impl<S: PageSize> Sub<PhysFrame<S>> for PhysFrame<S> {
     }
 }
 
+#[cfg(feature = "step_trait")]
+impl<S: PageSize> Step for PhysFrame<S> {
+    fn steps_between(start: &Self, end: &Self) -> (usize, Option<usize>) {
+        use core::convert::TryFrom;
+
+        if let Some(steps) = end
+            .start_address()
+            .as_u64()
+            .checked_sub(start.start_address().as_u64())
+        {
+            let steps = steps / S::SIZE;
+            let steps = usize::try_from(steps).ok();
+            (steps.unwrap_or(usize::MAX), steps)
+        } else {
+            (0, None)
+        }
+    }
+
+    fn forward_checked(start: Self, count: usize) -> Option<Self> {
+        use core::convert::TryFrom;
+
+        let count = u64::try_from(count).ok()?.checked_mul(S::SIZE)?;
+        let start_address = start.start_address().as_u64().checked_add(count)?;
+        let start_address = PhysAddr::try_new(start_address).ok()?;
+        Some(Self {
+            start_address,
+            size: PhantomData,
+        })
+    }
+
+    fn backward_checked(start: Self, count: usize) -> Option<Self> {
+        use core::convert::TryFrom;
+
+        let count = u64::try_from(count).ok()?.checked_mul(S::SIZE)?;
+        let start_address = start.start_address().as_u64().checked_sub(count)?;
+        Some(Self {
+            start_address: PhysAddr::new(start_address),
+            size: PhantomData,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<S: PageSize> serde::Serialize for PhysFrame<S> {
+    fn serialize<T: serde::Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        serde::Serialize::serialize(&self.start_address, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, S: PageSize> serde::Deserialize<'de> for PhysFrame<S> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let addr = <PhysAddr as serde::Deserialize>::deserialize(deserializer)?;
+        PhysFrame::from_start_address(addr).map_err(|AddressNotAligned| {
+            serde::de::Error::invalid_value(
+                serde::de::Unexpected::Unsigned(addr.as_u64()),
+                &"a frame aligned address",
+            )
+        })
+    }
+}
+
 /// An range of physical memory frames, exclusive the upper bound.
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
 #[repr(C)]
 pub struct PhysFrameRange<S: PageSize = Size4KiB> {
     /// The start of the range, inclusive.
@@ -164,6 +234,33 @@ impl<S: PageSize> PhysFrameRange<S> {
     pub fn size(&self) -> u64 {
         S::SIZE * self.len()
     }
+
+    /// Returns the frames contained in both `self` and `other`, or `None` if
+    /// the ranges have no frame in common.
+    #[inline]
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        range::intersection((self.start, self.end), (other.start, other.end), false)
+            .map(|(start, end)| Self { start, end })
+    }
+
+    /// Returns the range covering the frames of both `self` and `other`, or
+    /// `None` if the ranges neither overlap nor are adjacent.
+    ///
+    /// Empty ranges are ignored: the union with an empty range is the other
+    /// range.
+    #[inline]
+    pub fn union(&self, other: &Self) -> Option<Self> {
+        range::union((self.start, self.end), (other.start, other.end), false)
+            .map(|(start, end)| Self { start, end })
+    }
+
+    /// Returns whether all frames of `other` are contained in `self`.
+    ///
+    /// An empty range is contained in every range.
+    #[inline]
+    pub fn contains_range(&self, other: &Self) -> bool {
+        range::contains_range((self.start, self.end), (other.start, other.end), false)
+    }
 }
 
 impl<S: PageSize> Iterator for PhysFrameRange<S> {
@@ -192,6 +289,11 @@ impl<S: PageSize> fmt::Debug for PhysFrameRange<S> {
 
 /// An range of physical memory frames, inclusive the upper bound.
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
 #[repr(C)]
 pub struct PhysFrameRangeInclusive<S: PageSize = Size4KiB> {
     /// The start of the range, inclusive.
@@ -222,6 +324,33 @@ impl<S: PageSize> PhysFrameRangeInclusive<S> {
     pub fn size(&self) -> u64 {
         S::SIZE * self.len()
     }
+
+    /// Returns the frames contained in both `self` and `other`, or `None` if
+    /// the ranges have no frame in common.
+    #[inline]
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        range::intersection((self.start, self.end), (other.start, other.end), true)
+            .map(|(start, end)| Self { start, end })
+    }
+
+    /// Returns the range covering the frames of both `self` and `other`, or
+    /// `None` if the ranges neither overlap nor are adjacent.
+    ///
+    /// Empty ranges are ignored: the union with an empty range is the other
+    /// range.
+    #[inline]
+    pub fn union(&self, other: &Self) -> Option<Self> {
+        range::union((self.start, self.end), (other.start, other.end), true)
+            .map(|(start, end)| Self { start, end })
+    }
+
+    /// Returns whether all frames of `other` are contained in `self`.
+    ///
+    /// An empty range is contained in every range.
+    #[inline]
+    pub fn contains_range(&self, other: &Self) -> bool {
+        range::contains_range((self.start, self.end), (other.start, other.end), true)
+    }
 }
 
 impl<S: PageSize> Iterator for PhysFrameRangeInclusive<S> {
@@ -251,6 +380,51 @@ impl<S: PageSize> fmt::Debug for PhysFrameRangeInclusive<S> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::structures::paging::page::{Size1GiB, Size2MiB};
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_frame() {
+        use serde_test::{assert_de_tokens_error, assert_tokens, Token};
+
+        let start = PhysFrame::<Size4KiB>::containing_address(PhysAddr::new(0x1000));
+        assert_tokens(&start, &[Token::U64(0x1000)]);
+        assert_de_tokens_error::<PhysFrame>(
+            &[Token::U64(0x1234)],
+            "invalid value: integer `4660`, expected a frame aligned address",
+        );
+
+        let range = PhysFrame::range(start, start + 2);
+        assert_tokens(
+            &range,
+            &[
+                Token::Struct {
+                    name: "PhysFrameRange",
+                    len: 2,
+                },
+                Token::Str("start"),
+                Token::U64(0x1000),
+                Token::Str("end"),
+                Token::U64(0x3000),
+                Token::StructEnd,
+            ],
+        );
+        let range = PhysFrame::range_inclusive(start, start + 2);
+        assert_tokens(
+            &range,
+            &[
+                Token::Struct {
+                    name: "PhysFrameRangeInclusive",
+                    len: 2,
+                },
+                Token::Str("start"),
+                Token::U64(0x1000),
+                Token::Str("end"),
+                Token::U64(0x3000),
+                Token::StructEnd,
+            ],
+        );
+    }
     #[test]
     pub fn test_frame_range_len() {
         let start_addr = PhysAddr::new(0xdead_beaf);
@@ -263,4 +437,135 @@ mod tests {
         let range_inclusive = PhysFrameRangeInclusive { start, end };
         assert_eq!(range_inclusive.len(), 51);
     }
+
+    #[rustversion::since(1.61)]
+    #[test]
+    fn test_frame_const_constructors() {
+        const FRAME: PhysFrame = PhysFrame::containing_address(PhysAddr::new(0x1234));
+        const HUGE_FRAME: Result<PhysFrame<Size2MiB>, AddressNotAligned> =
+            PhysFrame::from_start_address(PhysAddr::new(0x20_0000));
+        const UNALIGNED: Result<PhysFrame<Size2MiB>, AddressNotAligned> =
+            PhysFrame::from_start_address(PhysAddr::new(0x1000));
+
+        assert_eq!(FRAME.start_address(), PhysAddr::new(0x1000));
+        assert_eq!(
+            HUGE_FRAME.unwrap().start_address(),
+            PhysAddr::new(0x20_0000)
+        );
+        assert!(UNALIGNED.is_err());
+    }
+
+    #[test]
+    fn test_frame_from_start_address() {
+        let frame = PhysFrame::<Size4KiB>::from_start_address(PhysAddr::new(0x5000)).unwrap();
+        assert_eq!(frame.start_address(), PhysAddr::new(0x5000));
+        assert!(matches!(
+            PhysFrame::<Size4KiB>::from_start_address(PhysAddr::new(0x5008)),
+            Err(AddressNotAligned)
+        ));
+        assert!(PhysFrame::<Size1GiB>::from_start_address(PhysAddr::new(0x20_0000)).is_err());
+    }
+
+    #[test]
+    pub fn test_frame_range_set_ops() {
+        let frame = |n: u64| PhysFrame::<Size4KiB>::containing_address(PhysAddr::new(n * 0x1000));
+
+        let range = |start, end| PhysFrameRange {
+            start: frame(start),
+            end: frame(end),
+        };
+        assert_eq!(range(0, 4).union(&range(4, 6)), Some(range(0, 6)));
+        assert_eq!(range(0, 4).intersection(&range(4, 6)), None);
+        assert!(range(0, 6).contains_range(&range(2, 6)));
+
+        let range = |start, end| PhysFrameRangeInclusive {
+            start: frame(start),
+            end: frame(end),
+        };
+        assert_eq!(range(0, 3).union(&range(4, 6)), Some(range(0, 6)));
+        assert_eq!(range(0, 4).intersection(&range(4, 6)), Some(range(4, 4)));
+        assert!(!range(0, 5).contains_range(&range(2, 6)));
+    }
+
+    #[test]
+    #[cfg(feature = "step_trait")]
+    fn frame_step_forward() {
+        let test_cases = [
+            (0, 0, Some(0)),
+            (0, 1, Some(0x1000)),
+            (0x1000, 1, Some(0x2000)),
+            (0x000f_ffff_ffff_e000, 1, Some(0x000f_ffff_ffff_f000)),
+            (0x000f_ffff_ffff_f000, 1, None),
+            #[cfg(target_pointer_width = "64")]
+            (0, 0xff_ffff_ffff, Some(0x000f_ffff_ffff_f000)),
+            #[cfg(target_pointer_width = "64")]
+            (0, 0x100_0000_0000, None),
+            // Make sure that we handle `steps * PAGE_SIZE > u32::MAX`
+            // correctly on 32-bit targets.
+            (0, 0x10_0000, Some(0x1_0000_0000)),
+        ];
+        for (start, count, result) in test_cases {
+            let start = PhysFrame::<Size4KiB>::from_start_address(PhysAddr::new(start)).unwrap();
+            let result = result.map(|result| {
+                PhysFrame::<Size4KiB>::from_start_address(PhysAddr::new(result)).unwrap()
+            });
+            assert_eq!(Step::forward_checked(start, count), result);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "step_trait")]
+    fn frame_step_backwards() {
+        let test_cases = [
+            (0, 0, Some(0)),
+            (0, 1, None),
+            (0x1000, 1, Some(0)),
+            (0x2000, 1, Some(0x1000)),
+            #[cfg(target_pointer_width = "64")]
+            (0x000f_ffff_ffff_f000, 0xff_ffff_ffff, Some(0)),
+            // Make sure that we handle `steps * PAGE_SIZE > u32::MAX`
+            // correctly on 32-bit targets.
+            (0x1_0000_0000, 0x10_0000, Some(0)),
+        ];
+        for (start, count, result) in test_cases {
+            let start = PhysFrame::<Size4KiB>::from_start_address(PhysAddr::new(start)).unwrap();
+            let result = result.map(|result| {
+                PhysFrame::<Size4KiB>::from_start_address(PhysAddr::new(result)).unwrap()
+            });
+            assert_eq!(Step::backward_checked(start, count), result);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "step_trait")]
+    fn frame_steps_between() {
+        let test_cases = [
+            (0, 0, 0, Some(0)),
+            (0, 0x1000, 1, Some(1)),
+            (0x1000, 0, 0, None),
+            (0x1000, 0x1000, 0, Some(0)),
+            // Make sure that we handle `steps * PAGE_SIZE > u32::MAX` correctly on 32-bit
+            // targets.
+            (0, 0x1_0000_0000, 0x10_0000, Some(0x10_0000)),
+            #[cfg(target_pointer_width = "64")]
+            (0, 0x1000_0000_0000, 0x1_0000_0000, Some(0x1_0000_0000)),
+            #[cfg(not(target_pointer_width = "64"))]
+            (0, 0x1000_0000_0000, usize::MAX, None),
+        ];
+        for (start, end, lower, upper) in test_cases {
+            let start = PhysFrame::<Size4KiB>::from_start_address(PhysAddr::new(start)).unwrap();
+            let end = PhysFrame::from_start_address(PhysAddr::new(end)).unwrap();
+            assert_eq!(Step::steps_between(&start, &end), (lower, upper));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "step_trait")]
+    fn frame_std_range() {
+        let start = PhysFrame::<Size4KiB>::containing_address(PhysAddr::new(0x1000));
+        let end = start + 8;
+        assert!((start..end).eq(PhysFrame::range(start, end)));
+        assert!((start..=end).eq(PhysFrame::range_inclusive(start, end)));
+        assert_eq!((start..end).step_by(2).count(), 4);
+    }
 }
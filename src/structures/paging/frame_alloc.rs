@@ -1,6 +1,6 @@
 //! Traits for abstracting away frame allocation and deallocation.
 
-use crate::structures::paging::{PageSize, PhysFrame};
+use crate::structures::paging::{frame::PhysFrameRange, PageSize, PhysFrame};
 
 /// A trait for types that can allocate a frame of memory.
 ///
@@ -12,6 +12,18 @@ use crate::structures::paging::{PageSize, PhysFrame};
 pub unsafe trait FrameAllocator<S: PageSize> {
     /// Allocate a frame of the appropriate size and return it if possible.
     fn allocate_frame(&mut self) -> Option<PhysFrame<S>>;
+
+    /// Allocate `count` physically contiguous frames and return them if possible.
+    ///
+    /// The returned range is only guaranteed to be aligned to the frame size `S`, not to the
+    /// total size of the range. The default implementation always returns `None`, since
+    /// frames returned by [`allocate_frame`](Self::allocate_frame) are not necessarily
+    /// contiguous.
+    #[inline]
+    fn allocate_frames(&mut self, count: usize) -> Option<PhysFrameRange<S>> {
+        let _ = count;
+        None
+    }
 }
 
 /// A trait for types that can deallocate a frame of memory.
@@ -22,4 +34,19 @@ pub trait FrameDeallocator<S: PageSize> {
     ///
     /// The caller must ensure that the passed frame is unused.
     unsafe fn deallocate_frame(&mut self, frame: PhysFrame<S>);
+
+    /// Deallocate the given range of unused frames.
+    ///
+    /// The default implementation deallocates every frame individually using
+    /// [`deallocate_frame`](Self::deallocate_frame).
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that all frames in the passed range are unused.
+    #[inline]
+    unsafe fn deallocate_frames(&mut self, frames: PhysFrameRange<S>) {
+        for frame in frames {
+            unsafe { self.deallocate_frame(frame) };
+        }
+    }
 }
@@ -14,7 +14,7 @@ use crate::structures::paging::{
 #[derive(Debug)]
 pub struct MappedPageTable<'a, P: PageTableFrameMapping> {
     page_table_walker: PageTableWalker<P>,
-    level_4_table: &'a mut PageTable,
+    root_table: &'a mut PageTable,
 }
 
 impl<'a, P: PageTableFrameMapping> MappedPageTable<'a, P> {
@@ -30,19 +30,80 @@ impl<'a, P: PageTableFrameMapping> MappedPageTable<'a, P> {
     #[inline]
     pub unsafe fn new(level_4_table: &'a mut PageTable, page_table_frame_mapping: P) -> Self {
         Self {
-            level_4_table,
-            page_table_walker: unsafe { PageTableWalker::new(page_table_frame_mapping) },
+            root_table: level_4_table,
+            page_table_walker: unsafe { PageTableWalker::new(page_table_frame_mapping, false) },
+        }
+    }
+
+    /// Creates a new `MappedPageTable` for a 5-level page table hierarchy that uses the passed
+    /// `PageTableFrameMapping` for converting virtual to physical addresses.
+    ///
+    /// Because [`VirtAddr`] only represents addresses that are sign extended from bit 47, only
+    /// the first and the last entry of the level 5 table are used.
+    ///
+    /// ## Safety
+    ///
+    /// This function is unsafe because the caller must guarantee that the passed `page_table_frame_mapping`
+    /// `PageTableFrameMapping` is correct. Also, the passed `level_5_table` must point to the level 5 page table
+    /// of a valid page table hierarchy. Otherwise this function might break memory safety, e.g.
+    /// by writing to an illegal memory location.
+    #[inline]
+    pub unsafe fn new_5_level(
+        level_5_table: &'a mut PageTable,
+        page_table_frame_mapping: P,
+    ) -> Self {
+        Self {
+            root_table: level_5_table,
+            page_table_walker: unsafe { PageTableWalker::new(page_table_frame_mapping, true) },
         }
     }
 
     /// Returns an immutable reference to the wrapped level 4 `PageTable` instance.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if this `MappedPageTable` was created with [`new_5_level`](Self::new_5_level). Use
+    /// [`level_5_table`](Self::level_5_table) in that case.
     pub fn level_4_table(&self) -> &PageTable {
-        self.level_4_table
+        assert!(
+            !self.page_table_walker.level_5,
+            "5-level page table has no root level 4 table, use `level_5_table`"
+        );
+        self.root_table
     }
 
     /// Returns a mutable reference to the wrapped level 4 `PageTable` instance.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if this `MappedPageTable` was created with [`new_5_level`](Self::new_5_level). Use
+    /// [`level_5_table_mut`](Self::level_5_table_mut) in that case.
     pub fn level_4_table_mut(&mut self) -> &mut PageTable {
-        self.level_4_table
+        assert!(
+            !self.page_table_walker.level_5,
+            "5-level page table has no root level 4 table, use `level_5_table_mut`"
+        );
+        self.root_table
+    }
+
+    /// Returns an immutable reference to the wrapped level 5 `PageTable` instance, or `None` if
+    /// this `MappedPageTable` was not created with [`new_5_level`](Self::new_5_level).
+    pub fn level_5_table(&self) -> Option<&PageTable> {
+        if self.page_table_walker.level_5 {
+            Some(self.root_table)
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the wrapped level 5 `PageTable` instance, or `None` if
+    /// this `MappedPageTable` was not created with [`new_5_level`](Self::new_5_level).
+    pub fn level_5_table_mut(&mut self) -> Option<&mut PageTable> {
+        if self.page_table_walker.level_5 {
+            Some(self.root_table)
+        } else {
+            None
+        }
     }
 
     /// Returns the `PageTableFrameMapping` used for converting virtual to physical addresses.
@@ -63,7 +124,12 @@ impl<'a, P: PageTableFrameMapping> MappedPageTable<'a, P> {
     where
         A: FrameAllocator<Size4KiB> + ?Sized,
     {
-        let p4 = &mut self.level_4_table;
+        let p4 = self.page_table_walker.create_level_4_table(
+            self.root_table,
+            page.start_address(),
+            parent_table_flags,
+            allocator,
+        )?;
         let p3 = self.page_table_walker.create_next_table(
             &mut p4[page.p4_index()],
             parent_table_flags,
@@ -91,7 +157,12 @@ impl<'a, P: PageTableFrameMapping> MappedPageTable<'a, P> {
     where
         A: FrameAllocator<Size4KiB> + ?Sized,
     {
-        let p4 = &mut self.level_4_table;
+        let p4 = self.page_table_walker.create_level_4_table(
+            self.root_table,
+            page.start_address(),
+            parent_table_flags,
+            allocator,
+        )?;
         let p3 = self.page_table_walker.create_next_table(
             &mut p4[page.p4_index()],
             parent_table_flags,
@@ -124,7 +195,12 @@ impl<'a, P: PageTableFrameMapping> MappedPageTable<'a, P> {
     where
         A: FrameAllocator<Size4KiB> + ?Sized,
     {
-        let p4 = &mut self.level_4_table;
+        let p4 = self.page_table_walker.create_level_4_table(
+            self.root_table,
+            page.start_address(),
+            parent_table_flags,
+            allocator,
+        )?;
         let p3 = self.page_table_walker.create_next_table(
             &mut p4[page.p4_index()],
             parent_table_flags,
@@ -170,7 +246,9 @@ impl<P: PageTableFrameMapping> Mapper<Size1GiB> for MappedPageTable<'_, P> {
         &mut self,
         page: Page<Size1GiB>,
     ) -> Result<(PhysFrame<Size1GiB>, MapperFlush<Size1GiB>), UnmapError> {
-        let p4 = &mut self.level_4_table;
+        let p4 = self
+            .page_table_walker
+            .level_4_table_mut(self.root_table, page.start_address())?;
         let p3 = self
             .page_table_walker
             .next_table_mut(&mut p4[page.p4_index()])?;
@@ -197,7 +275,9 @@ impl<P: PageTableFrameMapping> Mapper<Size1GiB> for MappedPageTable<'_, P> {
         page: Page<Size1GiB>,
         flags: PageTableFlags,
     ) -> Result<MapperFlush<Size1GiB>, FlagUpdateError> {
-        let p4 = &mut self.level_4_table;
+        let p4 = self
+            .page_table_walker
+            .level_4_table_mut(self.root_table, page.start_address())?;
         let p3 = self
             .page_table_walker
             .next_table_mut(&mut p4[page.p4_index()])?;
@@ -215,7 +295,9 @@ impl<P: PageTableFrameMapping> Mapper<Size1GiB> for MappedPageTable<'_, P> {
         page: Page<Size1GiB>,
         flags: PageTableFlags,
     ) -> Result<MapperFlushAll, FlagUpdateError> {
-        let p4 = &mut self.level_4_table;
+        let p4 = self
+            .page_table_walker
+            .level_4_table_mut(self.root_table, page.start_address())?;
         let p4_entry = &mut p4[page.p4_index()];
 
         if p4_entry.is_unused() {
@@ -244,7 +326,9 @@ impl<P: PageTableFrameMapping> Mapper<Size1GiB> for MappedPageTable<'_, P> {
     }
 
     fn translate_page(&self, page: Page<Size1GiB>) -> Result<PhysFrame<Size1GiB>, TranslateError> {
-        let p4 = &self.level_4_table;
+        let p4 = self
+            .page_table_walker
+            .level_4_table(self.root_table, page.start_address())?;
         let p3 = self.page_table_walker.next_table(&p4[page.p4_index()])?;
 
         let p3_entry = &p3[page.p3_index()];
@@ -278,7 +362,9 @@ impl<P: PageTableFrameMapping> Mapper<Size2MiB> for MappedPageTable<'_, P> {
         &mut self,
         page: Page<Size2MiB>,
     ) -> Result<(PhysFrame<Size2MiB>, MapperFlush<Size2MiB>), UnmapError> {
-        let p4 = &mut self.level_4_table;
+        let p4 = self
+            .page_table_walker
+            .level_4_table_mut(self.root_table, page.start_address())?;
         let p3 = self
             .page_table_walker
             .next_table_mut(&mut p4[page.p4_index()])?;
@@ -308,7 +394,9 @@ impl<P: PageTableFrameMapping> Mapper<Size2MiB> for MappedPageTable<'_, P> {
         page: Page<Size2MiB>,
         flags: PageTableFlags,
     ) -> Result<MapperFlush<Size2MiB>, FlagUpdateError> {
-        let p4 = &mut self.level_4_table;
+        let p4 = self
+            .page_table_walker
+            .level_4_table_mut(self.root_table, page.start_address())?;
         let p3 = self
             .page_table_walker
             .next_table_mut(&mut p4[page.p4_index()])?;
@@ -330,7 +418,9 @@ impl<P: PageTableFrameMapping> Mapper<Size2MiB> for MappedPageTable<'_, P> {
         page: Page<Size2MiB>,
         flags: PageTableFlags,
     ) -> Result<MapperFlushAll, FlagUpdateError> {
-        let p4 = &mut self.level_4_table;
+        let p4 = self
+            .page_table_walker
+            .level_4_table_mut(self.root_table, page.start_address())?;
         let p4_entry = &mut p4[page.p4_index()];
 
         if p4_entry.is_unused() {
@@ -347,7 +437,9 @@ impl<P: PageTableFrameMapping> Mapper<Size2MiB> for MappedPageTable<'_, P> {
         page: Page<Size2MiB>,
         flags: PageTableFlags,
     ) -> Result<MapperFlushAll, FlagUpdateError> {
-        let p4 = &mut self.level_4_table;
+        let p4 = self
+            .page_table_walker
+            .level_4_table_mut(self.root_table, page.start_address())?;
         let p3 = self
             .page_table_walker
             .next_table_mut(&mut p4[page.p4_index()])?;
@@ -371,7 +463,9 @@ impl<P: PageTableFrameMapping> Mapper<Size2MiB> for MappedPageTable<'_, P> {
     }
 
     fn translate_page(&self, page: Page<Size2MiB>) -> Result<PhysFrame<Size2MiB>, TranslateError> {
-        let p4 = &self.level_4_table;
+        let p4 = self
+            .page_table_walker
+            .level_4_table(self.root_table, page.start_address())?;
         let p3 = self.page_table_walker.next_table(&p4[page.p4_index()])?;
         let p2 = self.page_table_walker.next_table(&p3[page.p3_index()])?;
 
@@ -406,7 +500,9 @@ impl<P: PageTableFrameMapping> Mapper<Size4KiB> for MappedPageTable<'_, P> {
         &mut self,
         page: Page<Size4KiB>,
     ) -> Result<(PhysFrame<Size4KiB>, MapperFlush<Size4KiB>), UnmapError> {
-        let p4 = &mut self.level_4_table;
+        let p4 = self
+            .page_table_walker
+            .level_4_table_mut(self.root_table, page.start_address())?;
         let p3 = self
             .page_table_walker
             .next_table_mut(&mut p4[page.p4_index()])?;
@@ -434,7 +530,9 @@ impl<P: PageTableFrameMapping> Mapper<Size4KiB> for MappedPageTable<'_, P> {
         page: Page<Size4KiB>,
         flags: PageTableFlags,
     ) -> Result<MapperFlush<Size4KiB>, FlagUpdateError> {
-        let p4 = &mut self.level_4_table;
+        let p4 = self
+            .page_table_walker
+            .level_4_table_mut(self.root_table, page.start_address())?;
         let p3 = self
             .page_table_walker
             .next_table_mut(&mut p4[page.p4_index()])?;
@@ -454,12 +552,65 @@ impl<P: PageTableFrameMapping> Mapper<Size4KiB> for MappedPageTable<'_, P> {
         Ok(MapperFlush::new(page))
     }
 
+    unsafe fn update_flags_range(
+        &mut self,
+        pages: PageRangeInclusive<Size4KiB>,
+        flags: PageTableFlags,
+    ) -> Result<MapperFlushRange<Size4KiB>, FlagUpdateRangeError<Size4KiB>> {
+        let mut last_updated = None;
+        let mut update = || -> Result<(), FlagUpdateError> {
+            let mut remaining = pages.peekable();
+            while let Some(first) = remaining.next() {
+                // All pages within the same 2MiB region share a level 1 table, so only walk the
+                // page table hierarchy once per region.
+                let region = Page::<Size2MiB>::containing_address(first.start_address());
+                let p4 = self
+                    .page_table_walker
+                    .level_4_table_mut(self.root_table, first.start_address())?;
+                let p3 = self
+                    .page_table_walker
+                    .next_table_mut(&mut p4[first.p4_index()])?;
+                let p2 = self
+                    .page_table_walker
+                    .next_table_mut(&mut p3[first.p3_index()])?;
+                let p1 = self
+                    .page_table_walker
+                    .next_table_mut(&mut p2[first.p2_index()])?;
+
+                let mut page = first;
+                loop {
+                    let entry = &mut p1[page.p1_index()];
+                    if entry.is_unused() {
+                        return Err(FlagUpdateError::PageNotMapped);
+                    }
+                    entry.set_flags(flags);
+                    last_updated = Some(page);
+
+                    match remaining.next_if(|next| {
+                        Page::<Size2MiB>::containing_address(next.start_address()) == region
+                    }) {
+                        Some(next) => page = next,
+                        None => break,
+                    }
+                }
+            }
+            Ok(())
+        };
+
+        if let Err(error) = update() {
+            return Err(FlagUpdateRangeError::new(error, pages.start, last_updated));
+        }
+        Ok(MapperFlushRange::new(pages))
+    }
+
     unsafe fn set_flags_p4_entry(
         &mut self,
         page: Page<Size4KiB>,
         flags: PageTableFlags,
     ) -> Result<MapperFlushAll, FlagUpdateError> {
-        let p4 = &mut self.level_4_table;
+        let p4 = self
+            .page_table_walker
+            .level_4_table_mut(self.root_table, page.start_address())?;
         let p4_entry = &mut p4[page.p4_index()];
 
         if p4_entry.is_unused() {
@@ -476,7 +627,9 @@ impl<P: PageTableFrameMapping> Mapper<Size4KiB> for MappedPageTable<'_, P> {
         page: Page<Size4KiB>,
         flags: PageTableFlags,
     ) -> Result<MapperFlushAll, FlagUpdateError> {
-        let p4 = &mut self.level_4_table;
+        let p4 = self
+            .page_table_walker
+            .level_4_table_mut(self.root_table, page.start_address())?;
         let p3 = self
             .page_table_walker
             .next_table_mut(&mut p4[page.p4_index()])?;
@@ -496,7 +649,9 @@ impl<P: PageTableFrameMapping> Mapper<Size4KiB> for MappedPageTable<'_, P> {
         page: Page<Size4KiB>,
         flags: PageTableFlags,
     ) -> Result<MapperFlushAll, FlagUpdateError> {
-        let p4 = &mut self.level_4_table;
+        let p4 = self
+            .page_table_walker
+            .level_4_table_mut(self.root_table, page.start_address())?;
         let p3 = self
             .page_table_walker
             .next_table_mut(&mut p4[page.p4_index()])?;
@@ -515,7 +670,9 @@ impl<P: PageTableFrameMapping> Mapper<Size4KiB> for MappedPageTable<'_, P> {
     }
 
     fn translate_page(&self, page: Page<Size4KiB>) -> Result<PhysFrame<Size4KiB>, TranslateError> {
-        let p4 = &self.level_4_table;
+        let p4 = self
+            .page_table_walker
+            .level_4_table(self.root_table, page.start_address())?;
         let p3 = self.page_table_walker.next_table(&p4[page.p4_index()])?;
         let p2 = self.page_table_walker.next_table(&p3[page.p3_index()])?;
         let p1 = self.page_table_walker.next_table(&p2[page.p2_index()])?;
@@ -534,7 +691,13 @@ impl<P: PageTableFrameMapping> Mapper<Size4KiB> for MappedPageTable<'_, P> {
 impl<P: PageTableFrameMapping> Translate for MappedPageTable<'_, P> {
     #[allow(clippy::inconsistent_digit_grouping)]
     fn translate(&self, addr: VirtAddr) -> TranslateResult {
-        let p4 = &self.level_4_table;
+        let p4 = match self.page_table_walker.level_4_table(self.root_table, addr) {
+            Ok(page_table) => page_table,
+            Err(PageTableWalkError::NotMapped) => return TranslateResult::NotMapped,
+            Err(PageTableWalkError::MappedToHugePage) => {
+                panic!("level 5 entry has huge page bit set")
+            }
+        };
         let p3 = match self.page_table_walker.next_table(&p4[addr.p4_index()]) {
             Ok(page_table) => page_table,
             Err(PageTableWalkError::NotMapped) => return TranslateResult::NotMapped,
@@ -677,14 +840,48 @@ impl<P: PageTableFrameMapping> CleanUp for MappedPageTable<'_, P> {
             page_table.iter().all(PageTableEntry::is_unused)
         }
 
-        unsafe {
-            clean_up(
-                self.level_4_table,
-                &self.page_table_walker,
-                PageTableLevel::Four,
-                range,
-                frame_deallocator,
-            );
+        if !self.page_table_walker.level_5 {
+            unsafe {
+                clean_up(
+                    self.root_table,
+                    &self.page_table_walker,
+                    PageTableLevel::Four,
+                    range,
+                    frame_deallocator,
+                );
+            }
+            return;
+        }
+
+        // With 5-level paging, the lower half of the address space is mapped by the first
+        // entry of the level 5 table and the higher half by the last entry.
+        let halves = [
+            (0, VirtAddr::new(0), VirtAddr::new(0x7fff_ffff_f000)),
+            (
+                511,
+                VirtAddr::new(0xffff_8000_0000_0000),
+                VirtAddr::new(0xffff_ffff_ffff_f000),
+            ),
+        ];
+        for (index, start, end) in halves {
+            let start = range.start.max(Page::containing_address(start));
+            let end = range.end.min(Page::containing_address(end));
+            let entry = &mut self.root_table[index];
+            if let Ok(page_table) = self.page_table_walker.next_table_mut(entry) {
+                unsafe {
+                    if clean_up(
+                        page_table,
+                        &self.page_table_walker,
+                        PageTableLevel::Four,
+                        Page::range_inclusive(start, end),
+                        frame_deallocator,
+                    ) {
+                        let frame = entry.frame().unwrap();
+                        entry.set_unused();
+                        frame_deallocator.deallocate_frame(frame);
+                    }
+                }
+            }
         }
     }
 }
@@ -692,13 +889,73 @@ impl<P: PageTableFrameMapping> CleanUp for MappedPageTable<'_, P> {
 #[derive(Debug)]
 struct PageTableWalker<P: PageTableFrameMapping> {
     page_table_frame_mapping: P,
+    level_5: bool,
 }
 
 impl<P: PageTableFrameMapping> PageTableWalker<P> {
     #[inline]
-    pub unsafe fn new(page_table_frame_mapping: P) -> Self {
+    pub unsafe fn new(page_table_frame_mapping: P, level_5: bool) -> Self {
         Self {
             page_table_frame_mapping,
+            level_5,
+        }
+    }
+
+    /// Internal helper function to get a reference to the level 4 table for the given address.
+    ///
+    /// With 5-level paging, the level 4 table is looked up in the passed level 5 table.
+    /// Otherwise, the passed table is the level 4 table and is returned directly.
+    #[inline]
+    fn level_4_table<'b>(
+        &self,
+        root_table: &'b PageTable,
+        addr: VirtAddr,
+    ) -> Result<&'b PageTable, PageTableWalkError> {
+        if self.level_5 {
+            self.next_table(&root_table[addr.p5_index()])
+        } else {
+            Ok(root_table)
+        }
+    }
+
+    /// Internal helper function to get a mutable reference to the level 4 table for the given
+    /// address.
+    ///
+    /// With 5-level paging, the level 4 table is looked up in the passed level 5 table.
+    /// Otherwise, the passed table is the level 4 table and is returned directly.
+    #[inline]
+    fn level_4_table_mut<'b>(
+        &self,
+        root_table: &'b mut PageTable,
+        addr: VirtAddr,
+    ) -> Result<&'b mut PageTable, PageTableWalkError> {
+        if self.level_5 {
+            self.next_table_mut(&mut root_table[addr.p5_index()])
+        } else {
+            Ok(root_table)
+        }
+    }
+
+    /// Internal helper function to get a mutable reference to the level 4 table for the given
+    /// address, creating it if needed.
+    ///
+    /// With 5-level paging, the level 4 table is looked up in the passed level 5 table.
+    /// Otherwise, the passed table is the level 4 table and is returned directly.
+    #[inline]
+    fn create_level_4_table<'b, A>(
+        &self,
+        root_table: &'b mut PageTable,
+        addr: VirtAddr,
+        insert_flags: PageTableFlags,
+        allocator: &mut A,
+    ) -> Result<&'b mut PageTable, PageTableCreateError>
+    where
+        A: FrameAllocator<Size4KiB> + ?Sized,
+    {
+        if self.level_5 {
+            self.create_next_table(&mut root_table[addr.p5_index()], insert_flags, allocator)
+        } else {
+            Ok(root_table)
         }
     }
 
@@ -889,3 +1146,458 @@ pub unsafe trait PageTableFrameMapping {
     /// Translate the given physical frame to a virtual page table pointer.
     fn frame_to_pointer(&self, frame: PhysFrame) -> *mut PageTable;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Maps physical frame `n` to the `n`th table of a slice of page tables.
+    struct TableArray(*mut PageTable);
+
+    unsafe impl PageTableFrameMapping for TableArray {
+        fn frame_to_pointer(&self, frame: PhysFrame) -> *mut PageTable {
+            let index = frame.start_address().as_u64() / Size4KiB::SIZE;
+            unsafe { self.0.add(index as usize) }
+        }
+    }
+
+    /// Hands out the frames of the tables after the root table.
+    struct TableAllocator {
+        next: u64,
+        end: u64,
+    }
+
+    unsafe impl FrameAllocator<Size4KiB> for TableAllocator {
+        fn allocate_frame(&mut self) -> Option<PhysFrame> {
+            if self.next == self.end {
+                return None;
+            }
+            let frame = PhysFrame::containing_address(PhysAddr::new(self.next * Size4KiB::SIZE));
+            self.next += 1;
+            Some(frame)
+        }
+
+        fn allocate_frames(&mut self, count: usize) -> Option<PhysFrameRange> {
+            let count = count as u64;
+            if self.end - self.next < count {
+                return None;
+            }
+            let start = PhysFrame::containing_address(PhysAddr::new(self.next * Size4KiB::SIZE));
+            self.next += count;
+            Some(PhysFrame::range(start, start + count))
+        }
+    }
+
+    impl FrameDeallocator<Size4KiB> for TableAllocator {
+        unsafe fn deallocate_frame(&mut self, _frame: PhysFrame) {
+            self.next -= 1;
+        }
+    }
+
+    #[test]
+    fn map_region_auto_huge_pages() {
+        let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+        // 3MiB starting at an odd 4KiB page.
+        let start = VirtAddr::new(0x1f_f000);
+        let range = start..start + 0x30_0000u64;
+
+        let mut tables = vec![PageTable::new(); 6];
+        let ptr = tables.as_mut_ptr();
+        let mut allocator = TableAllocator { next: 1, end: 6 };
+        let mut mapper = unsafe { MappedPageTable::new(&mut *ptr, TableArray(ptr)) };
+        let phys_base = PhysAddr::new(0x3f_f000);
+        let (counts, batch) = unsafe {
+            map_region_auto(&mut mapper, range.clone(), phys_base, flags, &mut allocator)
+        }
+        .unwrap();
+        batch.ignore();
+        // One 4KiB page up to the 2MiB boundary, one 2MiB page and 255 4KiB pages for the tail.
+        assert_eq!(
+            counts,
+            MappedPageCounts {
+                size_1gib: 0,
+                size_2mib: 1,
+                size_4kib: 256,
+            }
+        );
+        // A level 3, level 2 and two level 1 tables.
+        assert_eq!(allocator.next, 5);
+        assert_eq!(mapper.translate_addr(start), Some(phys_base));
+        assert_eq!(
+            mapper.translate_addr(VirtAddr::new(0x2f_1234)),
+            Some(PhysAddr::new(0x4f_1234))
+        );
+        assert!(matches!(
+            mapper.translate(VirtAddr::new(0x20_0000)),
+            TranslateResult::Mapped {
+                frame: MappedFrame::Size2MiB(_),
+                ..
+            }
+        ));
+        assert_eq!(
+            mapper.translate_addr(VirtAddr::new(0x4f_e000)),
+            Some(PhysAddr::new(0x6f_e000))
+        );
+        assert_eq!(mapper.translate_addr(range.end), None);
+
+        // The physical base is not 2MiB aligned where the virtual address is.
+        let mut tables = vec![PageTable::new(); 6];
+        let ptr = tables.as_mut_ptr();
+        let mut allocator = TableAllocator { next: 1, end: 6 };
+        let mut mapper = unsafe { MappedPageTable::new(&mut *ptr, TableArray(ptr)) };
+        let (counts, batch) = unsafe {
+            map_region_auto(
+                &mut mapper,
+                range,
+                PhysAddr::new(0x40_0000),
+                flags,
+                &mut allocator,
+            )
+        }
+        .unwrap();
+        assert!(batch.is_flush_all());
+        batch.ignore();
+        assert_eq!(
+            counts,
+            MappedPageCounts {
+                size_1gib: 0,
+                size_2mib: 0,
+                size_4kib: 768,
+            }
+        );
+    }
+
+    #[test]
+    fn translate_page_sizes() {
+        let mut tables = vec![PageTable::new(); 5];
+        let ptr = tables.as_mut_ptr();
+        let mut allocator = TableAllocator { next: 1, end: 5 };
+        let mut mapper = unsafe { MappedPageTable::new(&mut *ptr, TableArray(ptr)) };
+        let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+
+        let page_4kib = Page::<Size4KiB>::containing_address(VirtAddr::new(0x1000));
+        let frame_4kib = PhysFrame::containing_address(PhysAddr::new(0x1234_6000));
+        let page_2mib = Page::<Size2MiB>::containing_address(VirtAddr::new(0x20_0000));
+        let frame_2mib = PhysFrame::containing_address(PhysAddr::new(0x8000_0000));
+        let page_1gib = Page::<Size1GiB>::containing_address(VirtAddr::new(0x40_0000_0000));
+        let frame_1gib = PhysFrame::containing_address(PhysAddr::new(0x1_0000_0000));
+        unsafe {
+            mapper
+                .map_to(page_4kib, frame_4kib, flags, &mut allocator)
+                .unwrap()
+                .ignore();
+            mapper
+                .map_to(page_2mib, frame_2mib, flags, &mut allocator)
+                .unwrap()
+                .ignore();
+            mapper
+                .map_to(page_1gib, frame_1gib, flags, &mut allocator)
+                .unwrap()
+                .ignore();
+        }
+
+        let expected = [
+            (page_4kib.start_address(), MappedFrame::Size4KiB(frame_4kib)),
+            (page_2mib.start_address(), MappedFrame::Size2MiB(frame_2mib)),
+            (page_1gib.start_address(), MappedFrame::Size1GiB(frame_1gib)),
+        ];
+        for (addr, expected_frame) in expected {
+            let offset = expected_frame.size() - 8;
+            match mapper.translate(addr + offset) {
+                TranslateResult::Mapped {
+                    frame,
+                    offset: actual_offset,
+                    flags: actual_flags,
+                } => {
+                    assert_eq!(frame, expected_frame);
+                    assert_eq!(actual_offset, offset);
+                    assert_eq!(actual_flags - PageTableFlags::HUGE_PAGE, flags);
+                }
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+        assert_eq!(MappedFrame::Size2MiB(frame_2mib).size(), Size2MiB::SIZE);
+        assert_eq!(
+            MappedFrame::Size1GiB(frame_1gib).start_address(),
+            PhysAddr::new(0x1_0000_0000)
+        );
+    }
+
+    #[test]
+    fn map_5_level() {
+        let mut tables = vec![PageTable::new(); 6];
+        let ptr = tables.as_mut_ptr();
+        let mut allocator = TableAllocator { next: 1, end: 6 };
+        let mut mapper = unsafe { MappedPageTable::new_5_level(&mut *ptr, TableArray(ptr)) };
+
+        let page = Page::<Size4KiB>::containing_address(VirtAddr::new(0xffff_8123_4567_8000));
+        let frame = PhysFrame::containing_address(PhysAddr::new(0x1234_5000));
+        unsafe {
+            mapper
+                .map_to(page, frame, PageTableFlags::PRESENT, &mut allocator)
+                .unwrap()
+                .ignore();
+        }
+        // One table for each of the levels 4 to 1.
+        assert_eq!(allocator.next, 5);
+        let level_5_table = mapper.level_5_table().unwrap();
+        assert!(level_5_table[0].is_unused());
+        assert!(!level_5_table[511].is_unused());
+
+        assert_eq!(mapper.translate_page(page).unwrap(), frame);
+        assert_eq!(
+            mapper.translate_addr(page.start_address() + 0x123),
+            Some(frame.start_address() + 0x123)
+        );
+        assert!(matches!(
+            mapper.translate(VirtAddr::new(0x1000)),
+            TranslateResult::NotMapped
+        ));
+
+        mapper.unmap(page).unwrap().1.ignore();
+        assert!(matches!(
+            mapper.translate(page.start_address()),
+            TranslateResult::NotMapped
+        ));
+
+        unsafe {
+            mapper.clean_up(&mut allocator);
+        }
+        assert_eq!(allocator.next, 1);
+        assert!(mapper.level_5_table().unwrap().is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn level_4_table_of_5_level_mapper() {
+        let mut table = PageTable::new();
+        let ptr: *mut PageTable = &mut table;
+        let mapper = unsafe { MappedPageTable::new_5_level(&mut *ptr, TableArray(ptr)) };
+        let _ = mapper.level_4_table();
+    }
+
+    #[test]
+    fn clean_up_addr_range() {
+        let mut tables = vec![PageTable::new(); 5];
+        let ptr = tables.as_mut_ptr();
+        let mut allocator = TableAllocator { next: 1, end: 5 };
+        let mut mapper = unsafe { MappedPageTable::new(&mut *ptr, TableArray(ptr)) };
+
+        // Two regions sharing the level 3 and level 2 tables, but with separate level 1 tables.
+        let first = Page::<Size4KiB>::containing_address(VirtAddr::new(0x20_0000));
+        let first = Page::range_inclusive(first, first + 7);
+        let second = Page::<Size4KiB>::containing_address(VirtAddr::new(0x40_0000));
+        let second = Page::range_inclusive(second, second + 7);
+        let frame = PhysFrame::containing_address(PhysAddr::new(0x10_0000));
+        for page in first.chain(second) {
+            unsafe { mapper.map_to(page, frame, PageTableFlags::PRESENT, &mut allocator) }
+                .unwrap()
+                .ignore();
+        }
+        assert_eq!(allocator.next, 5);
+
+        for page in first {
+            mapper.unmap(page).unwrap().1.ignore();
+        }
+        unsafe { mapper.clean_up_addr_range(first, &mut allocator) };
+        // Only the level 1 table of the first region was freed.
+        assert_eq!(allocator.next, 4);
+        assert_eq!(mapper.translate_page(second.start).unwrap(), frame);
+
+        for page in second {
+            mapper.unmap(page).unwrap().1.ignore();
+        }
+        unsafe { mapper.clean_up_addr_range(second, &mut allocator) };
+        assert_eq!(allocator.next, 1);
+        assert!(mapper.level_4_table().is_empty());
+    }
+
+    #[test]
+    fn map_range_to_contiguous() {
+        let mut tables = vec![PageTable::new(); 8];
+        let ptr = tables.as_mut_ptr();
+        let mut allocator = TableAllocator { next: 1, end: 8 };
+        let mut mapper = unsafe { MappedPageTable::new(&mut *ptr, TableArray(ptr)) };
+
+        let start = Page::<Size4KiB>::containing_address(VirtAddr::new(0x4000_0000));
+        let pages = Page::range_inclusive(start, start + 3);
+        let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+        let (frames, flush) =
+            unsafe { mapper.map_range_to_contiguous(pages, flags, &mut allocator) }.unwrap();
+        flush.ignore();
+
+        let first = PhysFrame::containing_address(PhysAddr::new(0x1000));
+        assert_eq!(frames, PhysFrame::range(first, first + 4));
+        for (i, page) in pages.enumerate() {
+            assert_eq!(mapper.translate_page(page).unwrap(), first + i as u64);
+        }
+
+        let pages = Page::range_inclusive(start + 4, start + 7);
+        assert!(matches!(
+            unsafe { mapper.map_range_to_contiguous(pages, flags, &mut allocator) },
+            Err(MapToError::FrameAllocationFailed)
+        ));
+    }
+
+    #[test]
+    fn map_to_with_pkey() {
+        let mut tables = vec![PageTable::new(); 4];
+        let ptr = tables.as_mut_ptr();
+        let mut allocator = TableAllocator { next: 1, end: 4 };
+        let mut mapper = unsafe { MappedPageTable::new(&mut *ptr, TableArray(ptr)) };
+
+        let page = Page::<Size4KiB>::containing_address(VirtAddr::new(0x4000_0000));
+        let frame = PhysFrame::containing_address(PhysAddr::new(0x10_0000));
+        // The key bits of the flags are replaced by the key.
+        let flags = PageTableFlags::PRESENT
+            | PageTableFlags::USER_ACCESSIBLE
+            | PageTableFlags::NO_EXECUTE
+            | PageTableFlags::BIT_60;
+        unsafe { mapper.map_to_with_pkey(page, frame, flags, 5, &mut allocator) }
+            .unwrap()
+            .ignore();
+
+        match mapper.translate(page.start_address()) {
+            TranslateResult::Mapped {
+                frame: MappedFrame::Size4KiB(mapped),
+                flags: mapped_flags,
+                ..
+            } => {
+                assert_eq!(mapped, frame);
+                assert_eq!(
+                    mapped_flags,
+                    (flags - PageTableFlags::BIT_60)
+                        | PageTableFlags::BIT_59
+                        | PageTableFlags::BIT_61
+                );
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        let entry = &tables[3][page.p1_index()];
+        assert_eq!(entry.protection_key(), 5);
+        assert_eq!(entry.addr(), frame.start_address());
+    }
+
+    #[test]
+    #[should_panic]
+    fn map_to_with_pkey_out_of_range() {
+        let mut tables = vec![PageTable::new(); 4];
+        let ptr = tables.as_mut_ptr();
+        let mut allocator = TableAllocator { next: 1, end: 4 };
+        let mut mapper = unsafe { MappedPageTable::new(&mut *ptr, TableArray(ptr)) };
+        let page = Page::<Size4KiB>::containing_address(VirtAddr::new(0x4000_0000));
+        let frame = PhysFrame::containing_address(PhysAddr::new(0x10_0000));
+        let _ = unsafe {
+            mapper.map_to_with_pkey(page, frame, PageTableFlags::PRESENT, 16, &mut allocator)
+        };
+    }
+
+    #[test]
+    fn map_user_page_parent_flags() {
+        let mut tables = vec![PageTable::new(); 4];
+        let ptr = tables.as_mut_ptr();
+        let mut allocator = TableAllocator { next: 1, end: 4 };
+        let mut mapper = unsafe { MappedPageTable::new(&mut *ptr, TableArray(ptr)) };
+
+        // A kernel page creates the parent tables without `USER_ACCESSIBLE`.
+        let kernel_page = Page::<Size4KiB>::containing_address(VirtAddr::new(0x20_0000));
+        let frame = PhysFrame::containing_address(PhysAddr::new(0x10_0000));
+        let flags = PageTableFlags::PRESENT;
+        unsafe { mapper.map_to(kernel_page, frame, flags, &mut allocator) }
+            .unwrap()
+            .ignore();
+        assert!(!mapper.level_4_table()[kernel_page.p4_index()]
+            .flags()
+            .contains(PageTableFlags::USER_ACCESSIBLE));
+
+        let user_page = kernel_page + 1;
+        let flags =
+            PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE;
+        unsafe { mapper.map_to(user_page, frame + 2, flags, &mut allocator) }
+            .unwrap()
+            .ignore();
+        assert_eq!(allocator.next, 4);
+
+        let mapping = mapper.page_table_frame_mapping();
+        let path = unsafe {
+            mapper
+                .level_4_table()
+                .walk(user_page.start_address(), |f| mapping.frame_to_pointer(f))
+        };
+        for level in [
+            PageTableLevel::Four,
+            PageTableLevel::Three,
+            PageTableLevel::Two,
+            PageTableLevel::One,
+        ] {
+            let entry_flags = path.entry(level).unwrap().flags();
+            assert!(
+                entry_flags.contains(flags),
+                "{:?}: {:?}",
+                level,
+                entry_flags
+            );
+        }
+        match path.result {
+            TranslateResult::Mapped {
+                flags: effective, ..
+            } => assert_eq!(effective, flags),
+            _ => panic!("user page is not mapped"),
+        }
+    }
+
+    #[test]
+    fn update_flags_range() {
+        let mut tables = vec![PageTable::new(); 6];
+        let ptr = tables.as_mut_ptr();
+        let mut allocator = TableAllocator { next: 1, end: 6 };
+        let mut mapper = unsafe { MappedPageTable::new(&mut *ptr, TableArray(ptr)) };
+
+        // The range crosses the boundary between two level 1 tables.
+        let start = Page::<Size4KiB>::containing_address(VirtAddr::new(0x1f_0000));
+        let pages = Page::range_inclusive(start, start + 99);
+        for (i, page) in pages.enumerate() {
+            // Use even frames only, since `flags()` interprets address bit 12 as `PAT_HUGE_PAGE`.
+            let addr = PhysAddr::new(0x10_0000 + i as u64 * 0x2000);
+            let frame = PhysFrame::containing_address(addr);
+            let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+            unsafe {
+                mapper
+                    .map_to(page, frame, flags, &mut allocator)
+                    .unwrap()
+                    .ignore();
+            }
+        }
+        // One table each for levels 3 and 2, two level 1 tables.
+        assert_eq!(allocator.next, 5);
+
+        let flags = PageTableFlags::PRESENT | PageTableFlags::NO_EXECUTE;
+        let flush = unsafe { mapper.update_flags_range(pages, flags) }.unwrap();
+        assert_eq!(flush.pages(), pages);
+        flush.ignore();
+
+        for page in pages {
+            match mapper.translate(page.start_address()) {
+                TranslateResult::Mapped {
+                    flags: page_flags, ..
+                } => assert_eq!(page_flags, flags),
+                _ => panic!("page {:?} is not mapped", page),
+            }
+        }
+
+        // The pages before the unmapped page are still updated and returned for flushing.
+        let unmapped = Page::range_inclusive(start, start + 100);
+        let err = unsafe { mapper.update_flags_range(unmapped, flags) }.unwrap_err();
+        assert!(matches!(err.error, FlagUpdateError::PageNotMapped));
+        let updated = err.updated.unwrap();
+        assert_eq!(updated.pages(), pages);
+        updated.ignore();
+
+        let err = unsafe {
+            mapper.update_flags_range(Page::range_inclusive(start + 100, start + 101), flags)
+        }
+        .unwrap_err();
+        assert!(matches!(err.error, FlagUpdateError::PageNotMapped));
+        assert!(err.updated.is_none());
+    }
+}
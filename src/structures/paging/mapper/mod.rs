@@ -6,13 +6,21 @@ pub use self::offset_page_table::OffsetPageTable;
 #[cfg(all(feature = "instructions", target_arch = "x86_64"))]
 pub use self::recursive_page_table::{InvalidPageTable, RecursivePageTable};
 
+#[cfg(all(feature = "instructions", target_arch = "x86_64"))]
+use crate::instructions::tlb::FLUSH_ALL_THRESHOLD;
 use crate::structures::paging::{
+    frame::PhysFrameRange,
     frame_alloc::{FrameAllocator, FrameDeallocator},
     page::PageRangeInclusive,
-    page_table::PageTableFlags,
+    page_table::{PageTableEntry, PageTableFlags},
     Page, PageSize, PhysFrame, Size1GiB, Size2MiB, Size4KiB,
 };
 use crate::{PhysAddr, VirtAddr};
+use core::convert::TryFrom;
+
+/// Same as `tlb::FLUSH_ALL_THRESHOLD`, which is only available with the `instructions` feature.
+#[cfg(not(all(feature = "instructions", target_arch = "x86_64")))]
+const FLUSH_ALL_THRESHOLD: usize = 32;
 
 mod mapped_page_table;
 mod offset_page_table;
@@ -76,7 +84,10 @@ pub enum TranslateResult {
 }
 
 /// Represents a physical frame mapped in a page table.
-#[derive(Debug)]
+///
+/// The variant tells the size of the page through which the address is mapped, e.g. for
+/// memory dumpers that need to know the granularity of a mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MappedFrame {
     /// The virtual address is mapped to a 4KiB frame.
     Size4KiB(PhysFrame<Size4KiB>),
@@ -111,7 +122,8 @@ pub trait Mapper<S: PageSize> {
     /// Creates a new mapping in the page table.
     ///
     /// This function might need additional physical frames to create new page tables. These
-    /// frames are allocated from the `allocator` argument. At most three frames are required.
+    /// frames are allocated from the `allocator` argument. At most three frames are required
+    /// (four with 5-level paging).
     ///
     /// Parent page table entries are automatically updated with `PRESENT | WRITABLE | USER_ACCESSIBLE`
     /// if present in the `PageTableFlags`. Depending on the used mapper implementation
@@ -200,10 +212,15 @@ pub trait Mapper<S: PageSize> {
     /// Creates a new mapping in the page table.
     ///
     /// This function might need additional physical frames to create new page tables. These
-    /// frames are allocated from the `allocator` argument. At most three frames are required.
+    /// frames are allocated from the `allocator` argument. At most three frames are required
+    /// (four with 5-level paging).
     ///
     /// The flags of the parent table(s) can be explicitly specified. Those flags are used for
-    /// newly created table entries, and for existing entries the flags are added.
+    /// newly created table entries, and for existing entries the flags are added (OR-combined).
+    /// Flags are never removed from existing parent entries, so mapping a page never reduces the
+    /// permissions of other pages sharing the same parent tables. For example, passing
+    /// `USER_ACCESSIBLE` makes every level of the hierarchy user accessible, which is required
+    /// for the page to be accessible from user mode.
     ///
     /// Depending on the used mapper implementation, the `PRESENT` and `WRITABLE` flags might
     /// be set for parent tables, even if they are not specified in `parent_table_flags`.
@@ -301,6 +318,41 @@ pub trait Mapper<S: PageSize> {
         flags: PageTableFlags,
     ) -> Result<MapperFlush<S>, FlagUpdateError>;
 
+    /// Updates the flags of all pages in the given range.
+    ///
+    /// This is equivalent to calling [`update_flags`](Mapper::update_flags) for every page,
+    /// but returns a single [`MapperFlushRange`] for the whole range.
+    ///
+    /// If an error occurs, the flags of all pages before the failing page have already been
+    /// updated. The returned [`FlagUpdateRangeError`] contains a [`MapperFlushRange`] for these
+    /// pages, which must still be flushed.
+    ///
+    /// ## Safety
+    ///
+    /// This method is unsafe because changing the flags of a mapping
+    /// might result in undefined behavior. For example, setting the
+    /// `GLOBAL` and `WRITABLE` flags for a page might result in the corruption
+    /// of values stored in that page from processes running in other address
+    /// spaces.
+    #[inline]
+    unsafe fn update_flags_range(
+        &mut self,
+        pages: PageRangeInclusive<S>,
+        flags: PageTableFlags,
+    ) -> Result<MapperFlushRange<S>, FlagUpdateRangeError<S>> {
+        let mut last_updated = None;
+        for page in pages {
+            match unsafe { self.update_flags(page, flags) } {
+                Ok(flush) => flush.ignore(),
+                Err(error) => {
+                    return Err(FlagUpdateRangeError::new(error, pages.start, last_updated))
+                }
+            }
+            last_updated = Some(page);
+        }
+        Ok(MapperFlushRange::new(pages))
+    }
+
     /// Set the flags of an existing page level 4 table entry
     ///
     /// ## Safety
@@ -374,6 +426,75 @@ pub trait Mapper<S: PageSize> {
         let page = Page::containing_address(VirtAddr::new(frame.start_address().as_u64()));
         unsafe { self.map_to(page, frame, flags, frame_allocator) }
     }
+
+    /// Creates a new mapping like [`map_to`](Mapper::map_to) and assigns the protection key
+    /// `pkey` to the page.
+    ///
+    /// The key is stored in bits 59 to 62 of the page table entry, replacing the corresponding
+    /// bits of `flags`, and is written together with the other flags, so the page is never
+    /// mapped with a different key. See [`PageTableEntry::protection_key`] for when the key
+    /// takes effect.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `pkey` is not smaller than 16.
+    ///
+    /// ## Safety
+    ///
+    /// This is a convenience function that invokes [`Mapper::map_to`] internally, so
+    /// all safety requirements of it also apply for this function.
+    #[inline]
+    unsafe fn map_to_with_pkey<A>(
+        &mut self,
+        page: Page<S>,
+        frame: PhysFrame<S>,
+        flags: PageTableFlags,
+        pkey: u8,
+        frame_allocator: &mut A,
+    ) -> Result<MapperFlush<S>, MapToError<S>>
+    where
+        Self: Sized,
+        A: FrameAllocator<Size4KiB> + ?Sized,
+    {
+        assert!(pkey < 16, "protection key must be smaller than 16");
+        let flags = PageTableFlags::from_bits_truncate(
+            (flags.bits() & !PageTableEntry::PROTECTION_KEY_MASK) | (u64::from(pkey) << 59),
+        );
+        unsafe { self.map_to(page, frame, flags, frame_allocator) }
+    }
+
+    /// Allocates physically contiguous frames for the given pages using
+    /// [`FrameAllocator::allocate_frames`] and maps each page to the corresponding frame.
+    ///
+    /// Returns the allocated frames and a flush promise for the mapped pages. This is useful
+    /// for DMA buffers, which need to be physically contiguous. The frames are only
+    /// guaranteed to be aligned to the page size `S`.
+    ///
+    /// If mapping a page fails, the pages mapped before it are not unmapped and the allocated
+    /// frames are not deallocated.
+    ///
+    /// ## Safety
+    ///
+    /// This is a convenience function that invokes [`Mapper::map_to`] internally, so
+    /// all safety requirements of it also apply for this function.
+    unsafe fn map_range_to_contiguous<A>(
+        &mut self,
+        pages: PageRangeInclusive<S>,
+        flags: PageTableFlags,
+        frame_allocator: &mut A,
+    ) -> Result<(PhysFrameRange<S>, MapperFlushRange<S>), MapToError<S>>
+    where
+        Self: Sized,
+        A: FrameAllocator<S> + FrameAllocator<Size4KiB> + ?Sized,
+    {
+        let count = usize::try_from(pages.len()).map_err(|_| MapToError::FrameAllocationFailed)?;
+        let frames = FrameAllocator::<S>::allocate_frames(frame_allocator, count)
+            .ok_or(MapToError::FrameAllocationFailed)?;
+        for (page, frame) in pages.zip(frames) {
+            unsafe { self.map_to(page, frame, flags, frame_allocator) }?.ignore();
+        }
+        Ok((frames, MapperFlushRange::new(pages)))
+    }
 }
 
 /// This type represents a page whose mapping has changed in the page table.
@@ -383,10 +504,6 @@ pub trait Mapper<S: PageSize> {
 /// changed the mapping of a page to ensure that the TLB flush is not forgotten.
 #[derive(Debug)]
 #[must_use = "Page Table changes must be flushed or ignored."]
-#[cfg_attr(
-    not(all(feature = "instructions", target_arch = "x86_64")),
-    allow(dead_code)
-)] // FIXME
 pub struct MapperFlush<S: PageSize>(Page<S>);
 
 impl<S: PageSize> MapperFlush<S> {
@@ -417,6 +534,48 @@ impl<S: PageSize> MapperFlush<S> {
     }
 }
 
+/// This type represents a range of pages whose mappings have changed in the page table.
+///
+/// Like [`MapperFlush`], but for multiple pages at once. It is returned from
+/// [`Mapper::update_flags_range`].
+#[derive(Debug)]
+#[must_use = "Page Table changes must be flushed or ignored."]
+pub struct MapperFlushRange<S: PageSize>(PageRangeInclusive<S>);
+
+impl<S: PageSize> MapperFlushRange<S> {
+    /// Create a new flush promise
+    ///
+    /// Note that this method is intended for implementing the [`Mapper`] trait and no other uses
+    /// are expected.
+    #[inline]
+    pub fn new(pages: PageRangeInclusive<S>) -> Self {
+        MapperFlushRange(pages)
+    }
+
+    /// Flush the pages from the TLB to ensure that the newest mappings are used.
+    ///
+    /// If the range contains more than [`FLUSH_ALL_THRESHOLD`]
+    /// pages, the complete TLB, including global pages, is flushed using
+    /// [`tlb::flush_all_global`] instead of flushing every page individually.
+    ///
+    /// [`tlb::flush_all_global`]: crate::instructions::tlb::flush_all_global
+    #[cfg(all(feature = "instructions", target_arch = "x86_64"))]
+    #[inline]
+    pub fn flush(self) {
+        crate::instructions::tlb::flush_page_range(self.0, FLUSH_ALL_THRESHOLD);
+    }
+
+    /// Don't flush the TLB and silence the “must be used” warning.
+    #[inline]
+    pub fn ignore(self) {}
+
+    /// Returns the range of pages to be flushed.
+    #[inline]
+    pub fn pages(&self) -> PageRangeInclusive<S> {
+        self.0
+    }
+}
+
 /// This type represents a change of a page table requiring a complete TLB flush
 ///
 /// The old mapping might be still cached in the translation lookaside buffer (TLB), so it needs
@@ -448,6 +607,232 @@ impl MapperFlushAll {
     pub fn ignore(self) {}
 }
 
+/// Collects multiple pending TLB flushes and executes them at once.
+///
+/// Instead of flushing every [`MapperFlush`] individually, the flushes can be added to a batch,
+/// which takes ownership of them just like [`MapperFlush::ignore`]. When the batch is
+/// committed, every collected page is flushed using `invlpg`, unless more than
+/// `N` pages were added. In that case, the complete TLB, including global pages, is flushed
+/// using `tlb::flush_all_global` instead, which is cheaper than a large number of individual
+/// flushes.
+///
+/// ## Example
+///
+/// ```no_run
+/// use x86_64::structures::paging::{
+///     mapper::TlbFlushBatch, FrameAllocator, Mapper, Page, PageTableFlags, PhysFrame, Size4KiB,
+/// };
+///
+/// unsafe fn map_region(
+///     mapper: &mut impl Mapper<Size4KiB>,
+///     allocator: &mut impl FrameAllocator<Size4KiB>,
+///     start: Page,
+///     frames: impl Iterator<Item = PhysFrame>,
+/// ) {
+///     let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+///     let mut batch = TlbFlushBatch::<64>::new();
+///     for (i, frame) in frames.enumerate() {
+///         let flush = unsafe { mapper.map_to(start + i as u64, frame, flags, allocator) }
+///             .expect("failed to map page");
+///         batch.add(flush);
+///     }
+///     // At most 64 `invlpg` instructions or one full flush.
+///     # #[cfg(all(feature = "instructions", target_arch = "x86_64"))]
+///     batch.commit();
+///     # #[cfg(not(all(feature = "instructions", target_arch = "x86_64")))]
+///     # batch.ignore();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+#[must_use = "Page Table changes must be flushed or ignored."]
+pub struct TlbFlushBatch<const N: usize = FLUSH_ALL_THRESHOLD> {
+    addrs: [VirtAddr; N],
+    len: usize,
+}
+
+impl<const N: usize> TlbFlushBatch<N> {
+    /// Creates an empty batch that escalates to a full TLB flush once more than `N` pages are
+    /// added.
+    #[inline]
+    pub const fn new() -> Self {
+        TlbFlushBatch {
+            addrs: [VirtAddr::zero(); N],
+            len: 0,
+        }
+    }
+
+    /// Adds a pending page flush to the batch.
+    #[inline]
+    pub fn add<S: PageSize>(&mut self, flush: MapperFlush<S>) {
+        self.add_addr(flush.page().start_address());
+    }
+
+    /// Adds a virtual address whose page needs to be flushed to the batch.
+    #[inline]
+    pub fn add_addr(&mut self, addr: VirtAddr) {
+        if self.len < N {
+            self.addrs[self.len] = addr;
+        }
+        self.len = self.len.saturating_add(1);
+    }
+
+    /// Returns the number of page flushes that were added to the batch.
+    ///
+    /// This includes the pages added after the batch escalated to a full TLB flush.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the batch doesn't contain any flushes.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns whether committing the batch flushes the complete TLB.
+    #[inline]
+    pub fn is_flush_all(&self) -> bool {
+        self.len > N
+    }
+
+    /// Executes all collected flushes.
+    #[cfg(all(feature = "instructions", target_arch = "x86_64"))]
+    #[inline]
+    pub fn commit(self) {
+        if self.is_flush_all() {
+            crate::instructions::tlb::flush_all_global();
+        } else {
+            for &addr in &self.addrs[..self.len] {
+                crate::instructions::tlb::flush(addr);
+            }
+        }
+    }
+
+    /// Don't flush the TLB and silence the “must be used” warning.
+    #[inline]
+    pub fn ignore(self) {}
+}
+
+impl<const N: usize> Default for TlbFlushBatch<N> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The number of pages of each size that were mapped by [`map_region_auto`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MappedPageCounts {
+    /// The number of 1GiB pages.
+    pub size_1gib: u64,
+    /// The number of 2MiB pages.
+    pub size_2mib: u64,
+    /// The number of 4KiB pages.
+    pub size_4kib: u64,
+}
+
+/// Maps the virtual region `range` to the physical memory starting at `phys_base`, using the
+/// largest possible pages.
+///
+/// The region is mapped from start to end. At every address, a 1GiB page is used if both the
+/// virtual and the physical address are 1GiB aligned and at least 1GiB of the region remains.
+/// Otherwise a 2MiB page is used under the same conditions, and a 4KiB page if neither fits.
+/// Note that huge pages are only possible if `range.start` and `phys_base` have the same offset
+/// into a 2MiB (or 1GiB) page.
+///
+/// Returns the number of mapped pages of each size and a flush promise for the mapped pages.
+/// If mapping a page fails, the pages mapped before it are not unmapped and their pending TLB
+/// flushes are discarded. Since these pages were not mapped before, the TLB contains no stale
+/// entries for them. A [`MapToError::PageAlreadyMapped`] error for a huge page contains the
+/// first 4KiB frame of the huge frame.
+///
+/// ## Panics
+///
+/// Panics if `range.start`, `range.end` or `phys_base` are not 4KiB aligned or if `range.end`
+/// is smaller than `range.start`.
+///
+/// ## Safety
+///
+/// This is a convenience function that invokes [`Mapper::map_to`] internally, so
+/// all safety requirements of it also apply for this function.
+pub unsafe fn map_region_auto<M, A>(
+    mapper: &mut M,
+    range: core::ops::Range<VirtAddr>,
+    phys_base: PhysAddr,
+    flags: PageTableFlags,
+    frame_allocator: &mut A,
+) -> Result<(MappedPageCounts, TlbFlushBatch), MapToError<Size4KiB>>
+where
+    M: MapperAllSizes,
+    A: FrameAllocator<Size4KiB> + ?Sized,
+{
+    fn fits<S: PageSize>(virt: VirtAddr, phys: PhysAddr, remaining: u64) -> bool {
+        virt.is_aligned(S::SIZE) && phys.is_aligned(S::SIZE) && remaining >= S::SIZE
+    }
+
+    fn into_4kib<S: PageSize>(err: MapToError<S>) -> MapToError<Size4KiB> {
+        match err {
+            MapToError::FrameAllocationFailed => MapToError::FrameAllocationFailed,
+            MapToError::ParentEntryHugePage => MapToError::ParentEntryHugePage,
+            MapToError::PageAlreadyMapped(frame) => {
+                MapToError::PageAlreadyMapped(PhysFrame::containing_address(frame.start_address()))
+            }
+        }
+    }
+
+    assert!(
+        range.start.is_aligned(Size4KiB::SIZE),
+        "range start must be 4KiB aligned"
+    );
+    assert!(
+        range.end.is_aligned(Size4KiB::SIZE),
+        "range end must be 4KiB aligned"
+    );
+    assert!(
+        phys_base.is_aligned(Size4KiB::SIZE),
+        "physical base must be 4KiB aligned"
+    );
+    assert!(
+        range.start <= range.end,
+        "range end must not be smaller than its start"
+    );
+
+    let mut counts = MappedPageCounts::default();
+    let mut batch = TlbFlushBatch::new();
+    let mut virt = range.start;
+    let mut phys = phys_base;
+    while virt < range.end {
+        let remaining = range.end - virt;
+        let size = if fits::<Size1GiB>(virt, phys, remaining) {
+            let page = Page::<Size1GiB>::containing_address(virt);
+            let frame = PhysFrame::<Size1GiB>::containing_address(phys);
+            batch.add(
+                unsafe { mapper.map_to(page, frame, flags, frame_allocator) }.map_err(into_4kib)?,
+            );
+            counts.size_1gib += 1;
+            Size1GiB::SIZE
+        } else if fits::<Size2MiB>(virt, phys, remaining) {
+            let page = Page::<Size2MiB>::containing_address(virt);
+            let frame = PhysFrame::<Size2MiB>::containing_address(phys);
+            batch.add(
+                unsafe { mapper.map_to(page, frame, flags, frame_allocator) }.map_err(into_4kib)?,
+            );
+            counts.size_2mib += 1;
+            Size2MiB::SIZE
+        } else {
+            let page = Page::<Size4KiB>::containing_address(virt);
+            let frame = PhysFrame::<Size4KiB>::containing_address(phys);
+            batch.add(unsafe { mapper.map_to(page, frame, flags, frame_allocator) }?);
+            counts.size_4kib += 1;
+            Size4KiB::SIZE
+        };
+        virt += size;
+        phys += size;
+    }
+    Ok((counts, batch))
+}
+
 /// This error is returned from `map_to` and similar methods.
 #[derive(Debug)]
 pub enum MapToError<S: PageSize> {
@@ -483,6 +868,34 @@ pub enum FlagUpdateError {
     ParentEntryHugePage,
 }
 
+/// An error indicating that an `update_flags_range` call failed.
+#[derive(Debug)]
+pub struct FlagUpdateRangeError<S: PageSize> {
+    /// The error that occurred for the first page that could not be updated.
+    pub error: FlagUpdateError,
+    /// The pages before the failing page, whose flags were already updated.
+    ///
+    /// These pages still need to be flushed from the TLB. This is `None` if the first page of
+    /// the range failed.
+    pub updated: Option<MapperFlushRange<S>>,
+}
+
+impl<S: PageSize> FlagUpdateRangeError<S> {
+    /// Creates a new error for a range starting at `start` where all pages up to and including
+    /// `last_updated` were updated.
+    pub(crate) fn new(
+        error: FlagUpdateError,
+        start: Page<S>,
+        last_updated: Option<Page<S>>,
+    ) -> Self {
+        FlagUpdateRangeError {
+            error,
+            updated: last_updated
+                .map(|end| MapperFlushRange::new(Page::range_inclusive(start, end))),
+        }
+    }
+}
+
 /// An error indicating that an `translate` call failed.
 #[derive(Debug)]
 pub enum TranslateError {
@@ -538,3 +951,28 @@ pub trait CleanUp {
     ) where
         D: FrameDeallocator<Size4KiB>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flush_batch_threshold() {
+        let page = Page::<Size4KiB>::containing_address(VirtAddr::new(0x1000));
+        let mut batch = TlbFlushBatch::<4>::new();
+        assert!(batch.is_empty());
+
+        for i in 0..4 {
+            batch.add(MapperFlush::new(page + i));
+        }
+        assert_eq!(batch.len(), 4);
+        assert!(!batch.is_flush_all());
+        assert_eq!(batch.addrs[3], VirtAddr::new(0x4000));
+
+        batch.add_addr(VirtAddr::new(0x5000));
+        assert!(batch.is_flush_all());
+        assert_eq!(batch.len(), 5);
+        assert!(!batch.is_empty());
+        batch.ignore();
+    }
+}
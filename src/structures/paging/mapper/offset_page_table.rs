@@ -35,20 +35,76 @@ impl<'a> OffsetPageTable<'a> {
         }
     }
 
+    /// Creates a new `OffsetPageTable` for a 5-level page table hierarchy that uses the given
+    /// offset for converting virtual to physical addresses.
+    ///
+    /// This is the same as [`OffsetPageTable::new`], but for use when 5-level paging is enabled
+    /// (see [`Cr4Flags::L5_PAGING`](crate::registers::control::Cr4Flags::L5_PAGING)).
+    ///
+    /// ## Safety
+    ///
+    /// This function is unsafe because the caller must guarantee that the passed `phys_offset`
+    /// is correct. Also, the passed `level_5_table` must point to the level 5 page table
+    /// of a valid page table hierarchy. Otherwise this function might break memory safety, e.g.
+    /// by writing to an illegal memory location.
+    #[inline]
+    pub unsafe fn new_5_level(level_5_table: &'a mut PageTable, phys_offset: VirtAddr) -> Self {
+        let phys_offset = PhysOffset {
+            offset: phys_offset,
+        };
+        Self {
+            inner: unsafe { MappedPageTable::new_5_level(level_5_table, phys_offset) },
+        }
+    }
+
     /// Returns an immutable reference to the wrapped level 4 `PageTable` instance.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if this `OffsetPageTable` was created with [`new_5_level`](Self::new_5_level). Use
+    /// [`level_5_table`](Self::level_5_table) in that case.
     pub fn level_4_table(&self) -> &PageTable {
         self.inner.level_4_table()
     }
 
     /// Returns a mutable reference to the wrapped level 4 `PageTable` instance.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if this `OffsetPageTable` was created with [`new_5_level`](Self::new_5_level). Use
+    /// [`level_5_table_mut`](Self::level_5_table_mut) in that case.
     pub fn level_4_table_mut(&mut self) -> &mut PageTable {
         self.inner.level_4_table_mut()
     }
 
+    /// Returns an immutable reference to the wrapped level 5 `PageTable` instance, or `None` if
+    /// this `OffsetPageTable` was not created with [`new_5_level`](Self::new_5_level).
+    pub fn level_5_table(&self) -> Option<&PageTable> {
+        self.inner.level_5_table()
+    }
+
+    /// Returns a mutable reference to the wrapped level 5 `PageTable` instance, or `None` if
+    /// this `OffsetPageTable` was not created with [`new_5_level`](Self::new_5_level).
+    pub fn level_5_table_mut(&mut self) -> Option<&mut PageTable> {
+        self.inner.level_5_table_mut()
+    }
+
     /// Returns the offset used for converting virtual to physical addresses.
     pub fn phys_offset(&self) -> VirtAddr {
         self.inner.page_table_frame_mapping().offset
     }
+
+    /// Returns a pointer to the page table stored in the given frame, i.e.
+    /// `phys_offset + frame.start_address()`.
+    ///
+    /// This is useful to inspect the page tables referenced by the entries of
+    /// [`level_4_table`](Self::level_4_table).
+    #[inline]
+    pub fn frame_to_pointer(&self, frame: PhysFrame) -> *mut PageTable {
+        self.inner
+            .page_table_frame_mapping()
+            .frame_to_pointer(frame)
+    }
 }
 
 #[derive(Debug)]
@@ -101,6 +157,15 @@ impl Mapper<Size1GiB> for OffsetPageTable<'_> {
         unsafe { self.inner.update_flags(page, flags) }
     }
 
+    #[inline]
+    unsafe fn update_flags_range(
+        &mut self,
+        pages: PageRangeInclusive<Size1GiB>,
+        flags: PageTableFlags,
+    ) -> Result<MapperFlushRange<Size1GiB>, FlagUpdateRangeError<Size1GiB>> {
+        unsafe { self.inner.update_flags_range(pages, flags) }
+    }
+
     #[inline]
     unsafe fn set_flags_p4_entry(
         &mut self,
@@ -170,6 +235,15 @@ impl Mapper<Size2MiB> for OffsetPageTable<'_> {
         unsafe { self.inner.update_flags(page, flags) }
     }
 
+    #[inline]
+    unsafe fn update_flags_range(
+        &mut self,
+        pages: PageRangeInclusive<Size2MiB>,
+        flags: PageTableFlags,
+    ) -> Result<MapperFlushRange<Size2MiB>, FlagUpdateRangeError<Size2MiB>> {
+        unsafe { self.inner.update_flags_range(pages, flags) }
+    }
+
     #[inline]
     unsafe fn set_flags_p4_entry(
         &mut self,
@@ -239,6 +313,15 @@ impl Mapper<Size4KiB> for OffsetPageTable<'_> {
         unsafe { self.inner.update_flags(page, flags) }
     }
 
+    #[inline]
+    unsafe fn update_flags_range(
+        &mut self,
+        pages: PageRangeInclusive<Size4KiB>,
+        flags: PageTableFlags,
+    ) -> Result<MapperFlushRange<Size4KiB>, FlagUpdateRangeError<Size4KiB>> {
+        unsafe { self.inner.update_flags_range(pages, flags) }
+    }
+
     #[inline]
     unsafe fn set_flags_p4_entry(
         &mut self,
@@ -299,3 +382,20 @@ impl CleanUp for OffsetPageTable<'_> {
         unsafe { self.inner.clean_up_addr_range(range, frame_deallocator) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phys_offset() {
+        let mut tables = vec![PageTable::new(); 3];
+        let ptr = tables.as_mut_ptr();
+        let offset = VirtAddr::from_ptr(ptr);
+        let mapper = unsafe { OffsetPageTable::new(&mut *ptr, offset) };
+
+        assert_eq!(mapper.phys_offset(), offset);
+        let frame = PhysFrame::containing_address(PhysAddr::new(0x2000));
+        assert_eq!(mapper.frame_to_pointer(frame), unsafe { ptr.add(2) });
+    }
+}
@@ -42,9 +42,17 @@ impl<'a> RecursivePageTable<'a> {
     ///   itself.
     ///     - The reference must use that “loop”, i.e. be of the form `0o_xxx_xxx_xxx_xxx_0000`
     ///       where `xxx` is the recursive entry.
+    ///     - The recursive entry must be `PRESENT` and `WRITABLE`, otherwise the mapper can't
+    ///       modify the page tables.
     /// - The page table must be active, i.e. the CR3 register must contain its physical address.
     ///
-    /// Otherwise `Err(())` is returned.
+    /// Otherwise an [`InvalidPageTable`] error is returned.
+    ///
+    /// To set up the recursive entry, choose an unused index `i` of the level 4 table, point
+    /// entry `i` to the frame of the table itself with the `PRESENT` and `WRITABLE` flags
+    /// (e.g. `table[i].set_frame(Cr3::read().0, PRESENT | WRITABLE)`), and pass a reference
+    /// to the table obtained through the address `0o_xxxxxx_i_i_i_i_0000`, where the upper bits
+    /// `xxxxxx` are the sign extension of `i` (i.e. `177777` if `i >= 256`, else `000000`).
     ///
     /// ## Safety
     ///
@@ -55,7 +63,25 @@ impl<'a> RecursivePageTable<'a> {
     /// and [in the `unsafe-code-guidelines ` repo](https://github.com/rust-lang/unsafe-code-guidelines/issues/420).
     #[inline]
     pub fn new(table: &'a mut PageTable) -> Result<Self, InvalidPageTable> {
-        let page = Page::containing_address(VirtAddr::new(table as *const _ as u64));
+        let addr = VirtAddr::new(table as *const _ as u64);
+        let recursive_index = Self::check_recursive_entry(addr, table)?;
+        if Ok(Cr3::read().0) != table[recursive_index].frame() {
+            return Err(InvalidPageTable::NotActive);
+        }
+
+        Ok(RecursivePageTable {
+            p4: table,
+            recursive_index,
+        })
+    }
+
+    /// Returns the recursive index implied by the address `addr` of `table` if the entry at
+    /// that index is usable as recursive entry.
+    fn check_recursive_entry(
+        addr: VirtAddr,
+        table: &PageTable,
+    ) -> Result<PageTableIndex, InvalidPageTable> {
+        let page = Page::<Size4KiB>::containing_address(addr);
         let recursive_index = page.p4_index();
 
         if page.p3_index() != recursive_index
@@ -64,14 +90,12 @@ impl<'a> RecursivePageTable<'a> {
         {
             return Err(InvalidPageTable::NotRecursive);
         }
-        if Ok(Cr3::read().0) != table[recursive_index].frame() {
-            return Err(InvalidPageTable::NotActive);
+        let required_flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+        let flags = table[recursive_index].flags();
+        if !flags.contains(required_flags) || flags.contains(PageTableFlags::HUGE_PAGE) {
+            return Err(InvalidPageTable::NotRecursive);
         }
-
-        Ok(RecursivePageTable {
-            p4: table,
-            recursive_index,
-        })
+        Ok(recursive_index)
     }
 
     /// Creates a new RecursivePageTable without performing any checks.
@@ -362,11 +386,14 @@ impl Mapper<Size1GiB> for RecursivePageTable<'_> {
         }
 
         let p3 = unsafe { &mut *(p3_ptr(page, self.recursive_index)) };
+        let p3_entry = &mut p3[page.p3_index()];
 
-        if p3[page.p3_index()].is_unused() {
+        // An entry without the `HUGE_PAGE` flag points to a level 2 table instead of a 1GiB
+        // frame, so converting it into a huge page would corrupt the mapping.
+        if p3_entry.is_unused() || !p3_entry.flags().contains(Flags::HUGE_PAGE) {
             return Err(FlagUpdateError::PageNotMapped);
         }
-        p3[page.p3_index()].set_flags(flags | Flags::HUGE_PAGE);
+        p3_entry.set_flags(flags | Flags::HUGE_PAGE);
 
         Ok(MapperFlush::new(page))
     }
@@ -414,7 +441,8 @@ impl Mapper<Size1GiB> for RecursivePageTable<'_> {
         let p3 = unsafe { &*(p3_ptr(page, self.recursive_index)) };
         let p3_entry = &p3[page.p3_index()];
 
-        if p3_entry.is_unused() {
+        // Without the `HUGE_PAGE` flag, the entry points to a level 2 table.
+        if p3_entry.is_unused() || !p3_entry.flags().contains(PageTableFlags::HUGE_PAGE) {
             return Err(TranslateError::PageNotMapped);
         }
 
@@ -959,12 +987,13 @@ impl CleanUp for RecursivePageTable<'_> {
 }
 
 /// The given page table was not suitable to create a `RecursivePageTable`.
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum InvalidPageTable {
     /// The given page table was not at an recursive address.
     ///
     /// The page table address must be of the form `0o_xxx_xxx_xxx_xxx_0000` where `xxx`
-    /// is the recursive entry.
+    /// is the recursive entry. The recursive entry must also be present and writable and
+    /// must not have the `HUGE_PAGE` flag set.
     NotRecursive,
     /// The given page table was not active on the CPU.
     ///
@@ -1029,3 +1058,45 @@ fn p1_page(page: Page<Size4KiB>, recursive_index: PageTableIndex) -> Page {
         page.p2_index(),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_recursive_entry() {
+        let index = PageTableIndex::new(0o776);
+        let recursive_addr =
+            Page::from_page_table_indices(index, index, index, index).start_address();
+        let frame = PhysFrame::containing_address(PhysAddr::new(0x1000));
+        let mut table = PageTable::new();
+
+        table[index].set_frame(frame, PageTableFlags::PRESENT | PageTableFlags::WRITABLE);
+        assert_eq!(
+            RecursivePageTable::check_recursive_entry(recursive_addr, &table),
+            Ok(index)
+        );
+
+        // The address doesn't loop through the same index four times.
+        assert_eq!(
+            RecursivePageTable::check_recursive_entry(
+                Page::from_page_table_indices(index, index, index, PageTableIndex::new(0o775))
+                    .start_address(),
+                &table
+            ),
+            Err(InvalidPageTable::NotRecursive)
+        );
+
+        table[index].set_frame(frame, PageTableFlags::PRESENT);
+        assert_eq!(
+            RecursivePageTable::check_recursive_entry(recursive_addr, &table),
+            Err(InvalidPageTable::NotRecursive)
+        );
+
+        table[index].set_unused();
+        assert_eq!(
+            RecursivePageTable::check_recursive_entry(recursive_addr, &table),
+            Err(InvalidPageTable::NotRecursive)
+        );
+    }
+}
@@ -14,10 +14,14 @@ pub use self::mapper::OffsetPageTable;
 pub use self::mapper::RecursivePageTable;
 pub use self::mapper::{Mapper, Translate};
 pub use self::page::{Page, PageSize, Size1GiB, Size2MiB, Size4KiB};
-pub use self::page_table::{PageOffset, PageTable, PageTableFlags, PageTableIndex};
+pub use self::page_table::{
+    AtomicPageTableEntry, PageOffset, PageTable, PageTableDebug, PageTableFlags, PageTableIndex,
+    TranslationPath,
+};
 
 pub mod frame;
 mod frame_alloc;
 pub mod mapper;
 pub mod page;
 pub mod page_table;
+mod range;
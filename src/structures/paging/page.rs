@@ -2,6 +2,7 @@
 
 use crate::sealed::Sealed;
 use crate::structures::paging::page_table::PageTableLevel;
+use crate::structures::paging::range;
 use crate::structures::paging::PageTableIndex;
 use crate::VirtAddr;
 use core::fmt;
@@ -123,6 +124,15 @@ impl<S: PageSize> Page<S> {
         S::SIZE
     }
 
+    /// Returns the level 5 page table index of this page.
+    ///
+    /// This index is only used when 5-level paging is enabled.
+    #[inline]
+    #[rustversion::attr(since(1.61), const)]
+    pub fn p5_index(self) -> PageTableIndex {
+        self.start_address().p5_index()
+    }
+
     /// Returns the level 4 page table index of this page.
     #[inline]
     #[rustversion::attr(since(1.61), const)]
@@ -199,6 +209,9 @@ impl<S: NotGiantPageSize> Page<S> {
 
 impl Page<Size1GiB> {
     /// Returns the 1GiB memory page with the specified page table indices.
+    ///
+    /// The address is sign extended from bit 47, so a `p4_index` of 256 or
+    /// above yields a page in the higher half.
     #[inline]
     #[rustversion::attr(since(1.61), const)]
     pub fn from_page_table_indices_1gib(
@@ -214,6 +227,9 @@ impl Page<Size1GiB> {
 
 impl Page<Size2MiB> {
     /// Returns the 2MiB memory page with the specified page table indices.
+    ///
+    /// The address is sign extended from bit 47, so a `p4_index` of 256 or
+    /// above yields a page in the higher half.
     #[inline]
     #[rustversion::attr(since(1.61), const)]
     pub fn from_page_table_indices_2mib(
@@ -231,6 +247,9 @@ impl Page<Size2MiB> {
 
 impl Page<Size4KiB> {
     /// Returns the 4KiB memory page with the specified page table indices.
+    ///
+    /// The address is sign extended from bit 47, so a `p4_index` of 256 or
+    /// above yields a page in the higher half.
     #[inline]
     #[rustversion::attr(since(1.61), const)]
     pub fn from_page_table_indices(
@@ -324,8 +343,33 @@ impl<S: PageSize> Step for Page<S> {
     }
 }
 
+#[cfg(feature = "serde")]
+impl<S: PageSize> serde::Serialize for Page<S> {
+    fn serialize<T: serde::Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        serde::Serialize::serialize(&self.start_address, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, S: PageSize> serde::Deserialize<'de> for Page<S> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let addr = <VirtAddr as serde::Deserialize>::deserialize(deserializer)?;
+        Page::from_start_address(addr).map_err(|AddressNotAligned| {
+            serde::de::Error::invalid_value(
+                serde::de::Unexpected::Unsigned(addr.as_u64()),
+                &"a page aligned address",
+            )
+        })
+    }
+}
+
 /// A range of pages with exclusive upper bound.
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
 #[repr(C)]
 pub struct PageRange<S: PageSize = Size4KiB> {
     /// The start of the range, inclusive.
@@ -356,6 +400,33 @@ impl<S: PageSize> PageRange<S> {
     pub fn size(&self) -> u64 {
         S::SIZE * self.len()
     }
+
+    /// Returns the pages contained in both `self` and `other`, or `None` if
+    /// the ranges have no page in common.
+    #[inline]
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        range::intersection((self.start, self.end), (other.start, other.end), false)
+            .map(|(start, end)| Self { start, end })
+    }
+
+    /// Returns the range covering the pages of both `self` and `other`, or
+    /// `None` if the ranges neither overlap nor are adjacent.
+    ///
+    /// Empty ranges are ignored: the union with an empty range is the other
+    /// range.
+    #[inline]
+    pub fn union(&self, other: &Self) -> Option<Self> {
+        range::union((self.start, self.end), (other.start, other.end), false)
+            .map(|(start, end)| Self { start, end })
+    }
+
+    /// Returns whether all pages of `other` are contained in `self`.
+    ///
+    /// An empty range is contained in every range.
+    #[inline]
+    pub fn contains_range(&self, other: &Self) -> bool {
+        range::contains_range((self.start, self.end), (other.start, other.end), false)
+    }
 }
 
 impl<S: PageSize> Iterator for PageRange<S> {
@@ -395,6 +466,11 @@ impl<S: PageSize> fmt::Debug for PageRange<S> {
 
 /// A range of pages with inclusive upper bound.
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
 #[repr(C)]
 pub struct PageRangeInclusive<S: PageSize = Size4KiB> {
     /// The start of the range, inclusive.
@@ -425,6 +501,33 @@ impl<S: PageSize> PageRangeInclusive<S> {
     pub fn size(&self) -> u64 {
         S::SIZE * self.len()
     }
+
+    /// Returns the pages contained in both `self` and `other`, or `None` if
+    /// the ranges have no page in common.
+    #[inline]
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        range::intersection((self.start, self.end), (other.start, other.end), true)
+            .map(|(start, end)| Self { start, end })
+    }
+
+    /// Returns the range covering the pages of both `self` and `other`, or
+    /// `None` if the ranges neither overlap nor are adjacent.
+    ///
+    /// Empty ranges are ignored: the union with an empty range is the other
+    /// range.
+    #[inline]
+    pub fn union(&self, other: &Self) -> Option<Self> {
+        range::union((self.start, self.end), (other.start, other.end), true)
+            .map(|(start, end)| Self { start, end })
+    }
+
+    /// Returns whether all pages of `other` are contained in `self`.
+    ///
+    /// An empty range is contained in every range.
+    #[inline]
+    pub fn contains_range(&self, other: &Self) -> bool {
+        range::contains_range((self.start, self.end), (other.start, other.end), true)
+    }
 }
 
 impl<S: PageSize> Iterator for PageRangeInclusive<S> {
@@ -474,6 +577,50 @@ impl fmt::Display for AddressNotAligned {
 mod tests {
     use super::*;
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_page() {
+        use serde_test::{assert_de_tokens_error, assert_tokens, Token};
+
+        let start = Page::<Size4KiB>::containing_address(VirtAddr::new(0x1000));
+        assert_tokens(&start, &[Token::U64(0x1000)]);
+        assert_de_tokens_error::<Page<Size2MiB>>(
+            &[Token::U64(0x1000)],
+            "invalid value: integer `4096`, expected a page aligned address",
+        );
+
+        let range = Page::range(start, start + 2);
+        assert_tokens(
+            &range,
+            &[
+                Token::Struct {
+                    name: "PageRange",
+                    len: 2,
+                },
+                Token::Str("start"),
+                Token::U64(0x1000),
+                Token::Str("end"),
+                Token::U64(0x3000),
+                Token::StructEnd,
+            ],
+        );
+        let range = Page::range_inclusive(start, start + 2);
+        assert_tokens(
+            &range,
+            &[
+                Token::Struct {
+                    name: "PageRangeInclusive",
+                    len: 2,
+                },
+                Token::Str("start"),
+                Token::U64(0x1000),
+                Token::Str("end"),
+                Token::U64(0x3000),
+                Token::StructEnd,
+            ],
+        );
+    }
+
     fn test_is_hash<T: core::hash::Hash>() {}
 
     #[test]
@@ -543,6 +690,71 @@ mod tests {
         assert_eq!(range_inclusive.len(), 51);
     }
 
+    #[test]
+    pub fn test_page_from_page_table_indices() {
+        for &addr in &[
+            0x0000_1234_5678_9000,
+            0xffff_8000_0000_0000,
+            0xffff_ffff_c020_1000,
+        ] {
+            let page = Page::<Size4KiB>::containing_address(VirtAddr::new(addr));
+            assert_eq!(
+                Page::from_page_table_indices(
+                    page.p4_index(),
+                    page.p3_index(),
+                    page.p2_index(),
+                    page.p1_index()
+                ),
+                page
+            );
+
+            let page = Page::<Size2MiB>::containing_address(VirtAddr::new(addr));
+            assert_eq!(
+                Page::from_page_table_indices_2mib(
+                    page.p4_index(),
+                    page.p3_index(),
+                    page.p2_index()
+                ),
+                page
+            );
+
+            let page = Page::<Size1GiB>::containing_address(VirtAddr::new(addr));
+            assert_eq!(
+                Page::from_page_table_indices_1gib(page.p4_index(), page.p3_index()),
+                page
+            );
+        }
+
+        let page = Page::from_page_table_indices(
+            PageTableIndex::new(511),
+            PageTableIndex::new(0),
+            PageTableIndex::new(0),
+            PageTableIndex::new(0),
+        );
+        assert_eq!(page.start_address(), VirtAddr::new(0xffff_ff80_0000_0000));
+    }
+
+    #[test]
+    pub fn test_page_range_set_ops() {
+        let page = |n: u64| Page::<Size4KiB>::containing_address(VirtAddr::new(n * 0x1000));
+
+        let range = |start, end| PageRange {
+            start: page(start),
+            end: page(end),
+        };
+        assert_eq!(range(0, 4).union(&range(4, 6)), Some(range(0, 6)));
+        assert_eq!(range(0, 4).intersection(&range(4, 6)), None);
+        assert!(range(0, 6).contains_range(&range(2, 6)));
+
+        let range = |start, end| PageRangeInclusive {
+            start: page(start),
+            end: page(end),
+        };
+        assert_eq!(range(0, 3).union(&range(4, 6)), Some(range(0, 6)));
+        assert_eq!(range(0, 4).intersection(&range(4, 6)), Some(range(4, 4)));
+        assert!(!range(0, 5).contains_range(&range(2, 6)));
+    }
+
     #[test]
     #[cfg(feature = "step_trait")]
     fn page_step_forward() {
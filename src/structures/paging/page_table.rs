@@ -4,9 +4,11 @@ use core::fmt;
 #[cfg(feature = "step_trait")]
 use core::iter::Step;
 use core::ops::{Index, IndexMut};
+use core::sync::atomic::{AtomicU64, Ordering};
 
+use super::mapper::{MappedFrame, TranslateResult};
 use super::{PageSize, PhysFrame, Size4KiB};
-use crate::addr::PhysAddr;
+use crate::addr::{PhysAddr, VirtAddr};
 
 use bitflags::bitflags;
 
@@ -28,6 +30,12 @@ pub struct PageTableEntry {
 }
 
 impl PageTableEntry {
+    /// The bits of an entry containing the physical address.
+    const ADDRESS_MASK: u64 = 0x000f_ffff_ffff_f000;
+
+    /// The bits of an entry containing the protection key.
+    pub(crate) const PROTECTION_KEY_MASK: u64 = 0xf << 59;
+
     /// Creates an unused page table entry.
     #[inline]
     pub const fn new() -> Self {
@@ -55,7 +63,7 @@ impl PageTableEntry {
     /// Returns the physical address mapped by this entry, might be zero.
     #[inline]
     pub fn addr(&self) -> PhysAddr {
-        PhysAddr::new(self.entry & 0x000f_ffff_ffff_f000)
+        PhysAddr::new(self.entry & Self::ADDRESS_MASK)
     }
 
     /// Returns the physical frame mapped by this entry.
@@ -90,6 +98,67 @@ impl PageTableEntry {
     pub fn set_flags(&mut self, flags: PageTableFlags) {
         self.entry = self.addr().as_u64() | flags.bits();
     }
+
+    /// Returns whether the `ACCESSED` flag is set, i.e. whether the CPU accessed the page or
+    /// table referenced by this entry since the flag was last cleared.
+    #[inline]
+    pub const fn is_accessed(&self) -> bool {
+        self.flags().contains(PageTableFlags::ACCESSED)
+    }
+
+    /// Returns whether the `DIRTY` flag is set, i.e. whether the CPU wrote to the page
+    /// referenced by this entry since the flag was last cleared.
+    #[inline]
+    pub const fn is_dirty(&self) -> bool {
+        self.flags().contains(PageTableFlags::DIRTY)
+    }
+
+    /// Clears the `ACCESSED` flag without changing the address or the other flags.
+    ///
+    /// Since the CPU may set this flag concurrently, use
+    /// [`AtomicPageTableEntry::clear_accessed`] for entries of active page tables.
+    #[inline]
+    pub fn clear_accessed(&mut self) {
+        self.entry &= !PageTableFlags::ACCESSED.bits();
+    }
+
+    /// Clears the `DIRTY` flag without changing the address or the other flags.
+    ///
+    /// Since the CPU may set this flag concurrently, use
+    /// [`AtomicPageTableEntry::clear_dirty`] for entries of active page tables.
+    #[inline]
+    pub fn clear_dirty(&mut self) {
+        self.entry &= !PageTableFlags::DIRTY.bits();
+    }
+
+    /// Returns the protection key of this entry (bits 59 to 62).
+    ///
+    /// The protection key is only used for entries that map a page and only if protection keys
+    /// are enabled through [`Cr4Flags::PROTECTION_KEY_USER`] for user-mode pages or
+    /// [`Cr4Flags::PROTECTION_KEY_SUPERVISOR`] for supervisor-mode pages. Otherwise these bits
+    /// are ignored. See [`Pkru`](crate::registers::pkru::Pkru) for configuring the access
+    /// rights of each key.
+    ///
+    /// [`Cr4Flags::PROTECTION_KEY_USER`]: crate::registers::control::Cr4Flags::PROTECTION_KEY_USER
+    /// [`Cr4Flags::PROTECTION_KEY_SUPERVISOR`]: crate::registers::control::Cr4Flags::PROTECTION_KEY_SUPERVISOR
+    #[inline]
+    pub const fn protection_key(&self) -> u8 {
+        ((self.entry & Self::PROTECTION_KEY_MASK) >> 59) as u8
+    }
+
+    /// Sets the protection key of this entry (bits 59 to 62) without changing the address or
+    /// the other flags.
+    ///
+    /// See [`protection_key`](Self::protection_key) for details.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `key` is not smaller than 16.
+    #[inline]
+    pub fn set_protection_key(&mut self, key: u8) {
+        assert!(key < 16, "protection key must be smaller than 16");
+        self.entry = (self.entry & !Self::PROTECTION_KEY_MASK) | (u64::from(key) << 59);
+    }
 }
 
 impl Default for PageTableEntry {
@@ -108,6 +177,100 @@ impl fmt::Debug for PageTableEntry {
     }
 }
 
+/// An atomic view of a [`PageTableEntry`].
+///
+/// Page table entries can be accessed by other cores and by the CPU itself
+/// (which sets the [`ACCESSED`](PageTableFlags::ACCESSED) and
+/// [`DIRTY`](PageTableFlags::DIRTY) flags) at the same time. This type
+/// allows updating such entries without losing concurrent modifications.
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct AtomicPageTableEntry {
+    entry: AtomicU64,
+}
+
+impl AtomicPageTableEntry {
+    /// Creates an atomic view of the given page table entry.
+    #[inline]
+    pub fn from_mut(entry: &mut PageTableEntry) -> &Self {
+        // SAFETY: `AtomicPageTableEntry` has the same layout as `PageTableEntry`
+        // and the exclusive reference guarantees that there are no other accesses.
+        unsafe { Self::from_ptr(entry) }
+    }
+
+    /// Creates an atomic view of the page table entry at the given pointer.
+    ///
+    /// ## Safety
+    ///
+    /// The pointer must be valid and properly aligned for the lifetime `'a` and
+    /// the entry must only be accessed atomically during that lifetime.
+    #[inline]
+    pub unsafe fn from_ptr<'a>(entry: *mut PageTableEntry) -> &'a Self {
+        unsafe { &*(entry as *const Self) }
+    }
+
+    /// Loads the entry.
+    #[inline]
+    pub fn load(&self, order: Ordering) -> PageTableEntry {
+        PageTableEntry {
+            entry: self.entry.load(order),
+        }
+    }
+
+    /// Stores the given entry.
+    #[inline]
+    pub fn store(&self, entry: PageTableEntry, order: Ordering) {
+        self.entry.store(entry.entry, order)
+    }
+
+    /// Sets the given flags, returning the previous flags.
+    #[inline]
+    pub fn fetch_or_flags(&self, flags: PageTableFlags, order: Ordering) -> PageTableFlags {
+        PageTableFlags::from_bits_truncate(self.entry.fetch_or(flags.bits(), order))
+    }
+
+    /// Clears all flags that are not contained in `flags`, returning the previous
+    /// flags. The address is not modified.
+    #[inline]
+    pub fn fetch_and_flags(&self, flags: PageTableFlags, order: Ordering) -> PageTableFlags {
+        let mask = flags.bits() | PageTableEntry::ADDRESS_MASK;
+        PageTableFlags::from_bits_truncate(self.entry.fetch_and(mask, order))
+    }
+
+    /// Atomically clears the `ACCESSED` flag and returns whether it was set.
+    #[inline]
+    pub fn clear_accessed(&self, order: Ordering) -> bool {
+        let old = self.fetch_and_flags(!PageTableFlags::ACCESSED, order);
+        old.contains(PageTableFlags::ACCESSED)
+    }
+
+    /// Atomically clears the `DIRTY` flag and returns whether it was set.
+    #[inline]
+    pub fn clear_dirty(&self, order: Ordering) -> bool {
+        let old = self.fetch_and_flags(!PageTableFlags::DIRTY, order);
+        old.contains(PageTableFlags::DIRTY)
+    }
+
+    /// Stores `new` if the entry is equal to `current`.
+    ///
+    /// Returns the previous entry, wrapped in `Ok` if it was replaced and in
+    /// `Err` otherwise. See [`AtomicU64::compare_exchange`] for the meaning of
+    /// the orderings.
+    #[inline]
+    pub fn compare_exchange(
+        &self,
+        current: PageTableEntry,
+        new: PageTableEntry,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<PageTableEntry, PageTableEntry> {
+        self.entry
+            .compare_exchange(current.entry, new.entry, success, failure)
+            .map(|entry| PageTableEntry { entry })
+            .map_err(|entry| PageTableEntry { entry })
+    }
+}
+
 bitflags! {
     /// Possible flags for a page table entry.
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
@@ -181,6 +344,16 @@ bitflags! {
     }
 }
 
+impl PageTableFlags {
+    /// `PRESENT | WRITABLE`, the flags of a writable kernel mapping.
+    pub const PRESENT_WRITABLE: Self = Self::PRESENT.union(Self::WRITABLE);
+    /// `PRESENT | WRITABLE | USER_ACCESSIBLE`, the flags of a writable user mapping.
+    pub const USER_RW: Self = Self::PRESENT_WRITABLE.union(Self::USER_ACCESSIBLE);
+}
+
+#[cfg(feature = "defmt")]
+impl_defmt_format_for_flags!(PageTableFlags);
+
 /// The number of entries in a page table.
 const ENTRY_COUNT: usize = 512;
 
@@ -250,6 +423,165 @@ impl PageTable {
     pub fn is_empty(&self) -> bool {
         self.iter().all(|entry| entry.is_unused())
     }
+
+    /// Returns a wrapper whose [`Debug`](fmt::Debug) implementation only prints the present
+    /// entries, together with their index.
+    ///
+    /// The `Debug` implementation of `PageTable` itself prints all 512 entries, which is hard to
+    /// read when only a few of them are used.
+    ///
+    /// ```
+    /// use x86_64::structures::paging::{PageTable, PageTableFlags};
+    /// use x86_64::PhysAddr;
+    ///
+    /// let mut table = PageTable::new();
+    /// table[3].set_addr(PhysAddr::new(0x5000), PageTableFlags::PRESENT);
+    /// println!("{:?}", table.debug_present());
+    /// ```
+    #[inline]
+    pub fn debug_present(&self) -> PageTableDebug<'_> {
+        PageTableDebug { table: self }
+    }
+
+    /// The flags that are combined across all levels by [`walk`](Self::walk).
+    const EFFECTIVE_FLAGS: PageTableFlags = PageTableFlags::WRITABLE
+        .union(PageTableFlags::USER_ACCESSIBLE)
+        .union(PageTableFlags::NO_EXECUTE);
+
+    /// Walks the page table hierarchy for the given virtual address, treating `self` as the
+    /// level 4 table, and returns all entries encountered on the way.
+    ///
+    /// The walk stops at the first entry that is not present or that maps a huge page. The
+    /// `HUGE_PAGE` flag is ignored for level 4 entries. The `phys_to_virt` closure converts
+    /// the physical frame of a lower level table to a pointer to that table.
+    ///
+    /// Unlike [`Translate::translate`](super::Translate::translate), the flags of a mapped
+    /// address in the returned [`TranslationPath::result`] are the effective flags: `WRITABLE`
+    /// and `USER_ACCESSIBLE` are only set if they are set on all levels, and `NO_EXECUTE` is set
+    /// if it is set on any level.
+    ///
+    /// This method is intended for debugging and introspection.
+    ///
+    /// ## Safety
+    ///
+    /// The pointers returned by `phys_to_virt` for all frames referenced by present entries
+    /// must point to valid page tables.
+    pub unsafe fn walk(
+        &self,
+        addr: VirtAddr,
+        phys_to_virt: impl Fn(PhysFrame) -> *const PageTable,
+    ) -> TranslationPath {
+        let mut entries = [None, None, None, None];
+        let mut table = self;
+        let mut level = PageTableLevel::Four;
+        let mut effective = Self::EFFECTIVE_FLAGS - PageTableFlags::NO_EXECUTE;
+
+        loop {
+            let entry = &table[addr.page_table_index(level)];
+            entries[TranslationPath::entry_index(level)] = Some(entry.clone());
+            let flags = entry.flags();
+
+            if !flags.contains(PageTableFlags::PRESENT) {
+                return TranslationPath {
+                    entries,
+                    level,
+                    result: TranslateResult::NotMapped,
+                };
+            }
+
+            effective &= flags | !(PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE);
+            effective |= flags & PageTableFlags::NO_EXECUTE;
+
+            let is_leaf = match level {
+                PageTableLevel::One => true,
+                PageTableLevel::Two | PageTableLevel::Three => {
+                    flags.contains(PageTableFlags::HUGE_PAGE)
+                }
+                PageTableLevel::Four => false,
+            };
+            if is_leaf {
+                let offset = addr.as_u64() & (level.entry_address_space_alignment() - 1);
+                let flags = (flags - Self::EFFECTIVE_FLAGS) | effective;
+                let start = entry.addr();
+                let frame = match level {
+                    PageTableLevel::One => PhysFrame::from_start_address(start)
+                        .ok()
+                        .map(MappedFrame::Size4KiB),
+                    PageTableLevel::Two => PhysFrame::from_start_address(start)
+                        .ok()
+                        .map(MappedFrame::Size2MiB),
+                    _ => PhysFrame::from_start_address(start)
+                        .ok()
+                        .map(MappedFrame::Size1GiB),
+                };
+                let result = match frame {
+                    Some(frame) => TranslateResult::Mapped {
+                        frame,
+                        offset,
+                        flags,
+                    },
+                    None => TranslateResult::InvalidFrameAddress(start),
+                };
+                return TranslationPath {
+                    entries,
+                    level,
+                    result,
+                };
+            }
+
+            let next = phys_to_virt(PhysFrame::containing_address(entry.addr()));
+            table = unsafe { &*next };
+            level = level.next_lower_level().unwrap();
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for PageTableFlags {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.bits())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PageTableFlags {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bits = <u64 as serde::Deserialize>::deserialize(deserializer)?;
+        PageTableFlags::from_bits(bits).ok_or_else(|| {
+            serde::de::Error::invalid_value(
+                serde::de::Unexpected::Unsigned(bits),
+                &"valid page table flags",
+            )
+        })
+    }
+}
+
+/// The page table entries encountered while translating a virtual address.
+///
+/// This type is returned by [`PageTable::walk`].
+#[derive(Debug)]
+pub struct TranslationPath {
+    entries: [Option<PageTableEntry>; 4],
+    /// The level of the last visited entry, i.e. the level at which the walk stopped.
+    pub level: PageTableLevel,
+    /// The result of the translation.
+    ///
+    /// For mapped addresses, this contains the effective flags of all levels.
+    pub result: TranslateResult,
+}
+
+impl TranslationPath {
+    #[inline]
+    fn entry_index(level: PageTableLevel) -> usize {
+        PageTableLevel::Four as usize - level as usize
+    }
+
+    /// Returns the entry visited at the given level, or `None` if the walk stopped before
+    /// reaching that level.
+    #[inline]
+    pub fn entry(&self, level: PageTableLevel) -> Option<&PageTableEntry> {
+        self.entries[Self::entry_index(level)].as_ref()
+    }
 }
 
 impl Index<usize> for PageTable {
@@ -297,6 +629,27 @@ impl fmt::Debug for PageTable {
     }
 }
 
+/// Formats the present entries of a [`PageTable`] as a map from their index to the entry.
+///
+/// This type is returned by [`PageTable::debug_present`].
+#[derive(Clone, Copy)]
+pub struct PageTableDebug<'a> {
+    table: &'a PageTable,
+}
+
+impl fmt::Debug for PageTableDebug<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                self.table
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, entry)| entry.flags().contains(PageTableFlags::PRESENT)),
+            )
+            .finish()
+    }
+}
+
 /// A 9-bit index into a page table.
 ///
 /// Can be used to select one of the 512 entries of a page table.
@@ -319,6 +672,41 @@ impl PageTableIndex {
         Self(index % ENTRY_COUNT as u16)
     }
 
+    /// Returns an iterator over all 512 indices in ascending order.
+    ///
+    /// ```
+    /// use x86_64::structures::paging::{PageTable, PageTableIndex};
+    ///
+    /// let table = PageTable::new();
+    /// for index in PageTableIndex::iter() {
+    ///     assert!(table[index].is_unused());
+    /// }
+    /// ```
+    #[inline]
+    pub fn iter() -> impl DoubleEndedIterator<Item = Self> + ExactSizeIterator {
+        (0..ENTRY_COUNT as u16).map(Self)
+    }
+
+    /// Returns the next index, or `None` if this is the last index (511).
+    #[inline]
+    pub const fn next(self) -> Option<Self> {
+        if (self.0 as usize) + 1 < ENTRY_COUNT {
+            Some(Self(self.0 + 1))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the previous index, or `None` if this is the first index (0).
+    #[inline]
+    pub const fn prev(self) -> Option<Self> {
+        if self.0 > 0 {
+            Some(Self(self.0 - 1))
+        } else {
+            None
+        }
+    }
+
     #[inline]
     pub(crate) const fn into_u64(self) -> u64 {
         self.0 as u64
@@ -468,3 +856,243 @@ impl PageTableLevel {
         1u64 << (((self as u8 - 1) * 9) + 12)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_table_flags_combinations() {
+        let flags = PageTableFlags::USER_RW | PageTableFlags::NO_EXECUTE;
+        assert!(flags.contains(PageTableFlags::PRESENT_WRITABLE));
+        assert!(flags.intersects(PageTableFlags::USER_ACCESSIBLE | PageTableFlags::GLOBAL));
+        assert!(!flags.intersects(PageTableFlags::GLOBAL | PageTableFlags::HUGE_PAGE));
+        assert_eq!(
+            flags.iter().collect::<Vec<_>>(),
+            [
+                PageTableFlags::PRESENT,
+                PageTableFlags::WRITABLE,
+                PageTableFlags::USER_ACCESSIBLE,
+                PageTableFlags::NO_EXECUTE,
+            ]
+        );
+        assert_eq!(
+            format!("{:?}", flags),
+            "PageTableFlags(PRESENT | WRITABLE | USER_ACCESSIBLE | NO_EXECUTE)"
+        );
+    }
+
+    #[test]
+    fn page_table_index_iter() {
+        assert_eq!(PageTableIndex::iter().len(), 512);
+        assert!(PageTableIndex::iter()
+            .map(u16::from)
+            .eq(0..ENTRY_COUNT as u16));
+
+        let mut table = PageTable::new();
+        for index in PageTableIndex::iter() {
+            table[index].set_addr(
+                PhysAddr::new(u64::from(index) * 0x2000),
+                PageTableFlags::PRESENT,
+            );
+        }
+        for (i, entry) in table.iter().enumerate() {
+            assert_eq!(entry.addr(), PhysAddr::new(i as u64 * 0x2000));
+        }
+    }
+
+    #[test]
+    fn page_table_index_next_prev() {
+        assert_eq!(PageTableIndex::new(0).next(), Some(PageTableIndex::new(1)));
+        assert_eq!(PageTableIndex::new(511).next(), None);
+        assert_eq!(
+            PageTableIndex::new(511).prev(),
+            Some(PageTableIndex::new(510))
+        );
+        assert_eq!(PageTableIndex::new(0).prev(), None);
+        assert_eq!(PageTableIndex::new_truncate(512), PageTableIndex::new(0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_flags() {
+        use serde_test::{assert_de_tokens_error, assert_tokens, Token};
+
+        let flags = PageTableFlags::PRESENT | PageTableFlags::NO_EXECUTE;
+        assert_tokens(&flags, &[Token::U64(0x8000_0000_0000_0001)]);
+        assert_de_tokens_error::<PageTableFlags>(
+            &[Token::U64(1 << 20)],
+            "invalid value: integer `1048576`, expected valid page table flags",
+        );
+    }
+
+    #[test]
+    fn clear_accessed_dirty() {
+        let flags = PageTableFlags::PRESENT | PageTableFlags::ACCESSED | PageTableFlags::DIRTY;
+        let mut entry = PageTableEntry::new();
+        entry.set_addr(PhysAddr::new(0x2000), flags);
+        assert!(entry.is_accessed());
+        assert!(entry.is_dirty());
+
+        entry.clear_dirty();
+        assert!(!entry.is_dirty());
+        assert!(entry.is_accessed());
+        assert_eq!(entry.addr(), PhysAddr::new(0x2000));
+
+        entry.clear_accessed();
+        assert!(!entry.is_accessed());
+        assert_eq!(entry.flags(), PageTableFlags::PRESENT);
+        assert_eq!(entry.addr(), PhysAddr::new(0x2000));
+    }
+
+    #[test]
+    fn atomic_clear_dirty() {
+        let flags = PageTableFlags::PRESENT | PageTableFlags::ACCESSED | PageTableFlags::DIRTY;
+        let mut entry = PageTableEntry::new();
+        entry.set_addr(PhysAddr::new(0x2000), flags);
+        let atomic = AtomicPageTableEntry::from_mut(&mut entry);
+
+        assert!(atomic.clear_dirty(Ordering::Relaxed));
+        assert!(!atomic.clear_dirty(Ordering::Relaxed));
+        let entry = atomic.load(Ordering::Relaxed);
+        assert!(entry.is_accessed());
+        assert_eq!(entry.addr(), PhysAddr::new(0x2000));
+    }
+
+    #[test]
+    fn protection_key() {
+        let flags = PageTableFlags::PRESENT | PageTableFlags::NO_EXECUTE;
+        let mut entry = PageTableEntry::new();
+        entry.set_addr(PhysAddr::new(0x2000), flags);
+        assert_eq!(entry.protection_key(), 0);
+
+        entry.set_protection_key(5);
+        assert_eq!(entry.protection_key(), 5);
+        assert_eq!(
+            entry.flags(),
+            flags | PageTableFlags::BIT_59 | PageTableFlags::BIT_61
+        );
+        assert_eq!(entry.addr(), PhysAddr::new(0x2000));
+
+        entry.set_protection_key(0xf);
+        entry.set_protection_key(2);
+        assert_eq!(entry.flags(), flags | PageTableFlags::BIT_60);
+    }
+
+    #[test]
+    fn debug_present() {
+        let mut table = PageTable::new();
+        table[3].set_addr(PhysAddr::new(0x5000), PageTableFlags::PRESENT);
+        table[7].set_addr(PhysAddr::new(0x6000), PageTableFlags::WRITABLE);
+        table[511].set_addr(
+            PhysAddr::new(0x7000),
+            PageTableFlags::PRESENT | PageTableFlags::NO_EXECUTE,
+        );
+
+        assert_eq!(
+            format!("{:?}", table.debug_present()),
+            format!("{{3: {:?}, 511: {:?}}}", table[3], table[511])
+        );
+        let output = format!("{:#?}", table.debug_present());
+        assert!(output.contains("0x5000"));
+        assert!(output.contains("NO_EXECUTE"));
+        assert!(!output.contains("0x6000"));
+        assert_eq!(format!("{:?}", PageTable::new().debug_present()), "{}");
+    }
+
+    #[test]
+    fn walk_huge_page() {
+        let mut tables = vec![PageTable::new(); 3];
+        let ptr = tables.as_ptr();
+        let phys_to_virt = |frame: PhysFrame| unsafe {
+            ptr.add((frame.start_address().as_u64() / 0x1000) as usize)
+        };
+        let table_flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+
+        let addr = VirtAddr::new(0x0000_0080_4021_1234);
+        tables[0][addr.p4_index()].set_addr(PhysAddr::new(0x1000), table_flags);
+        tables[1][addr.p3_index()].set_addr(
+            PhysAddr::new(0x2000),
+            PageTableFlags::PRESENT | PageTableFlags::NO_EXECUTE,
+        );
+        tables[2][addr.p2_index()].set_addr(
+            PhysAddr::new(0x4000_0000),
+            table_flags | PageTableFlags::HUGE_PAGE,
+        );
+
+        let path = unsafe { tables[0].walk(addr, phys_to_virt) };
+        assert_eq!(path.level, PageTableLevel::Two);
+        assert_eq!(
+            path.entry(PageTableLevel::Four).unwrap().addr(),
+            PhysAddr::new(0x1000)
+        );
+        assert_eq!(
+            path.entry(PageTableLevel::Three).unwrap().addr(),
+            PhysAddr::new(0x2000)
+        );
+        assert!(path.entry(PageTableLevel::Two).is_some());
+        assert!(path.entry(PageTableLevel::One).is_none());
+        match path.result {
+            TranslateResult::Mapped {
+                frame,
+                offset,
+                flags,
+            } => {
+                assert!(matches!(frame, MappedFrame::Size2MiB(_)));
+                assert_eq!(frame.start_address(), PhysAddr::new(0x4000_0000));
+                assert_eq!(offset, 0x1_1234);
+                assert_eq!(
+                    flags,
+                    PageTableFlags::PRESENT
+                        | PageTableFlags::HUGE_PAGE
+                        | PageTableFlags::NO_EXECUTE
+                );
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        // An address in the same level 2 table that is not mapped.
+        let path = unsafe { tables[0].walk(addr + 0x20_0000u64, phys_to_virt) };
+        assert_eq!(path.level, PageTableLevel::Two);
+        assert!(matches!(path.result, TranslateResult::NotMapped));
+
+        let path = unsafe { tables[0].walk(VirtAddr::new(0), phys_to_virt) };
+        assert_eq!(path.level, PageTableLevel::Four);
+        assert!(path.entry(PageTableLevel::Three).is_none());
+        assert!(matches!(path.result, TranslateResult::NotMapped));
+    }
+
+    #[test]
+    fn atomic_entry_set_accessed() {
+        let frame = PhysFrame::containing_address(PhysAddr::new(0x2000));
+        let mut entry = PageTableEntry::new();
+        entry.set_frame(frame, PageTableFlags::PRESENT | PageTableFlags::WRITABLE);
+        let atomic = AtomicPageTableEntry::from_mut(&mut entry);
+
+        let mut current = atomic.load(Ordering::Relaxed);
+        loop {
+            let mut new = current.clone();
+            new.set_flags(current.flags() | PageTableFlags::ACCESSED);
+            match atomic.compare_exchange(current, new, Ordering::AcqRel, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(actual) => current = actual,
+            }
+        }
+
+        let entry = atomic.load(Ordering::Relaxed);
+        assert_eq!(entry.addr(), frame.start_address());
+        assert!(entry.flags().contains(PageTableFlags::ACCESSED));
+
+        let old = atomic.fetch_and_flags(PageTableFlags::PRESENT, Ordering::Relaxed);
+        assert!(old.contains(PageTableFlags::ACCESSED | PageTableFlags::WRITABLE));
+        let old = atomic.fetch_or_flags(PageTableFlags::NO_EXECUTE, Ordering::Relaxed);
+        assert_eq!(old, PageTableFlags::PRESENT);
+
+        let entry = atomic.load(Ordering::Relaxed);
+        assert_eq!(entry.addr(), frame.start_address());
+        assert_eq!(
+            entry.flags(),
+            PageTableFlags::PRESENT | PageTableFlags::NO_EXECUTE
+        );
+    }
+}
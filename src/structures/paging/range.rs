@@ -0,0 +1,106 @@
+//! Set operations shared by the page and frame range types.
+//!
+//! Ranges are passed as `(start, end)` bounds. If `inclusive` is set, `end` is part of the
+//! range, otherwise it is the first item after the range.
+
+use core::cmp;
+use core::ops::Sub;
+
+#[inline]
+fn is_empty<T: Ord>(range: &(T, T), inclusive: bool) -> bool {
+    if inclusive {
+        range.0 > range.1
+    } else {
+        range.0 >= range.1
+    }
+}
+
+/// Returns the items contained in both `a` and `b`, or `None` if the ranges have no item in
+/// common.
+#[inline]
+pub(crate) fn intersection<T: Ord>(a: (T, T), b: (T, T), inclusive: bool) -> Option<(T, T)> {
+    let range = (cmp::max(a.0, b.0), cmp::min(a.1, b.1));
+    if is_empty(&range, inclusive) {
+        None
+    } else {
+        Some(range)
+    }
+}
+
+/// Returns the range covering the items of both `a` and `b`, or `None` if the ranges neither
+/// overlap nor are adjacent. Empty ranges are ignored.
+#[inline]
+pub(crate) fn union<T>(a: (T, T), b: (T, T), inclusive: bool) -> Option<(T, T)>
+where
+    T: Ord + Copy + Sub<Output = u64>,
+{
+    if is_empty(&b, inclusive) {
+        return Some(a);
+    }
+    if is_empty(&a, inclusive) {
+        return Some(b);
+    }
+    // Whether a range starting at `start` overlaps or directly follows one ending at `end`.
+    let touches = |end: T, start: T| start <= end || (inclusive && start - end == 1);
+    if touches(a.1, b.0) && touches(b.1, a.0) {
+        Some((cmp::min(a.0, b.0), cmp::max(a.1, b.1)))
+    } else {
+        None
+    }
+}
+
+/// Returns whether all items of `other` are contained in `range`. An empty range is contained
+/// in every range.
+#[inline]
+pub(crate) fn contains_range<T: Ord>(range: (T, T), other: (T, T), inclusive: bool) -> bool {
+    is_empty(&other, inclusive) || (range.0 <= other.0 && other.1 <= range.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exclusive() {
+        let intersection = |a, b| intersection(a, b, false);
+        let union = |a: (u64, u64), b| union(a, b, false);
+        let contains_range = |a, b| contains_range(a, b, false);
+
+        // overlapping
+        assert_eq!(intersection((0u64, 4), (2, 6)), Some((2, 4)));
+        assert_eq!(union((0, 4), (2, 6)), Some((0, 6)));
+        // adjacent
+        assert_eq!(intersection((0, 4), (4, 6)), None);
+        assert_eq!(union((4, 6), (0, 4)), Some((0, 6)));
+        // disjoint
+        assert_eq!(intersection((0, 2), (4, 6)), None);
+        assert_eq!(union((0, 2), (4, 6)), None);
+        // fully contained
+        assert_eq!(intersection((0, 6), (2, 4)), Some((2, 4)));
+        assert_eq!(union((0, 6), (2, 4)), Some((0, 6)));
+        assert!(contains_range((0, 6), (2, 4)));
+        assert!(contains_range((0, 6), (0, 6)));
+        assert!(!contains_range((2, 4), (0, 6)));
+        assert!(!contains_range((0, 4), (2, 6)));
+        // empty
+        assert_eq!(intersection((0, 6), (3, 3)), None);
+        assert_eq!(union((3, 3), (8, 9)), Some((8, 9)));
+        assert!(contains_range((0, 1), (5, 5)));
+    }
+
+    #[test]
+    fn inclusive() {
+        let intersection = |a, b| intersection(a, b, true);
+        let union = |a: (u64, u64), b| union(a, b, true);
+        let contains_range = |a, b| contains_range(a, b, true);
+
+        assert_eq!(intersection((0u64, 4), (4, 6)), Some((4, 4)));
+        assert_eq!(union((0, 4), (2, 6)), Some((0, 6)));
+        assert_eq!(union((5, 6), (0, 4)), Some((0, 6)));
+        assert_eq!(union((0, 3), (5, 6)), None);
+        assert_eq!(intersection((0, 3), (5, 6)), None);
+        assert!(contains_range((0, 6), (6, 6)));
+        assert!(!contains_range((0, 6), (6, 7)));
+        assert!(contains_range((0, 1), (5, 4)));
+    }
+}
@@ -0,0 +1,235 @@
+//! Support for the legacy 8259 programmable interrupt controllers (PICs).
+//!
+//! PC compatible systems contain two cascaded PICs, each handling 8 IRQ lines. The slave PIC is
+//! connected to IRQ line 2 of the master PIC, so the master handles IRQs 0 to 7 and the slave
+//! handles IRQs 8 to 15.
+//!
+//! By default, the master PIC delivers its IRQs as interrupt vectors 8 to 15, which collide with
+//! CPU exceptions in protected and long mode. The PICs therefore have to be remapped with
+//! [`ChainedPics::initialize`] before interrupts are enabled, usually to vectors 32 to 47 right
+//! after the exceptions. Systems that use the local APIC and I/O APIC instead should remap and
+//! then [`disable`](ChainedPics::disable) the PICs, so that spurious IRQs do not arrive as
+//! exceptions.
+
+use crate::instructions::port::{io_wait, Port};
+
+const MASTER_COMMAND: u16 = 0x20;
+const MASTER_DATA: u16 = 0x21;
+const SLAVE_COMMAND: u16 = 0xA0;
+const SLAVE_DATA: u16 = 0xA1;
+
+/// ICW1: start initialization, ICW4 follows.
+const ICW1_INIT: u8 = 0x11;
+/// ICW3 of the master: a slave is attached to IRQ line 2.
+const ICW3_MASTER: u8 = 1 << 2;
+/// ICW3 of the slave: its cascade identity is 2.
+const ICW3_SLAVE: u8 = 2;
+/// ICW4: 8086/8088 mode.
+const ICW4_8086: u8 = 0x01;
+/// OCW2: non-specific end of interrupt.
+const END_OF_INTERRUPT: u8 = 0x20;
+
+/// The master and slave 8259 PICs of a PC compatible system.
+///
+/// See the [module level documentation](self) for details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChainedPics {
+    offsets: [u8; 2],
+}
+
+impl ChainedPics {
+    /// Creates an instance for the PICs with the vector offsets set up by the BIOS (8 for the
+    /// master and `0x70` for the slave).
+    ///
+    /// This does not access the PICs. Use [`initialize`](Self::initialize) to remap them.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            offsets: [0x08, 0x70],
+        }
+    }
+
+    /// Returns the vector offsets of the master and the slave PIC.
+    #[inline]
+    pub const fn offsets(&self) -> (u8, u8) {
+        (self.offsets[0], self.offsets[1])
+    }
+
+    /// Returns the interrupt vector of the given IRQ line.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `irq` is not smaller than 16.
+    #[inline]
+    pub const fn vector(&self, irq: u8) -> u8 {
+        assert!(irq < 16, "the PICs only have 16 IRQ lines");
+        if irq < 8 {
+            self.offsets[0] + irq
+        } else {
+            self.offsets[1] + irq - 8
+        }
+    }
+
+    /// Returns the IRQ line of the given interrupt vector, if it is delivered by the PICs.
+    #[inline]
+    pub const fn irq(&self, vector: u8) -> Option<u8> {
+        if vector >= self.offsets[0] && vector - self.offsets[0] < 8 {
+            Some(vector - self.offsets[0])
+        } else if vector >= self.offsets[1] && vector - self.offsets[1] < 8 {
+            Some(vector - self.offsets[1] + 8)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the port writes that remap the PICs to `offset1` and `offset2` and then restore
+    /// the interrupt `masks`.
+    fn initialization_sequence(offset1: u8, offset2: u8, masks: [u8; 2]) -> [(u16, u8); 10] {
+        [
+            (MASTER_COMMAND, ICW1_INIT),
+            (SLAVE_COMMAND, ICW1_INIT),
+            (MASTER_DATA, offset1),
+            (SLAVE_DATA, offset2),
+            (MASTER_DATA, ICW3_MASTER),
+            (SLAVE_DATA, ICW3_SLAVE),
+            (MASTER_DATA, ICW4_8086),
+            (SLAVE_DATA, ICW4_8086),
+            (MASTER_DATA, masks[0]),
+            (SLAVE_DATA, masks[1]),
+        ]
+    }
+
+    /// Returns the command ports that have to be notified of the end of the given IRQ.
+    ///
+    /// IRQs of the slave PIC are also acknowledged at the master, as they arrive through its
+    /// cascade line.
+    fn end_of_interrupt_ports(irq: u8) -> &'static [u16] {
+        if irq >= 8 {
+            &[SLAVE_COMMAND, MASTER_COMMAND]
+        } else {
+            &[MASTER_COMMAND]
+        }
+    }
+
+    /// Remaps the PICs to deliver IRQs 0 to 7 as vectors `offset1` to `offset1 + 7` and IRQs 8
+    /// to 15 as vectors `offset2` to `offset2 + 7`.
+    ///
+    /// This sends the initialization command words ICW1 to ICW4 to both PICs, configuring the
+    /// slave to cascade through IRQ line 2 of the master. The interrupt masks are preserved.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if an offset is not a multiple of 8, as the PICs ignore the lower 3 bits.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that interrupts are disabled during the initialization and that
+    /// handlers are installed for the new vectors before IRQs are unmasked.
+    pub unsafe fn initialize(&mut self, offset1: u8, offset2: u8) {
+        assert!(
+            offset1 % 8 == 0 && offset2 % 8 == 0,
+            "PIC vector offsets must be multiples of 8"
+        );
+        let masks = self.read_masks();
+        for (port, value) in Self::initialization_sequence(offset1, offset2, masks) {
+            unsafe { Port::new(port).write(value) };
+            io_wait();
+        }
+        self.offsets = [offset1, offset2];
+    }
+
+    /// Notifies the PICs that the handler for the given IRQ line has finished.
+    ///
+    /// No further IRQs of the same or lower priority are delivered before this.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that `irq` is the IRQ that is currently being handled.
+    #[inline]
+    pub unsafe fn notify_end_of_interrupt(&mut self, irq: u8) {
+        for &port in Self::end_of_interrupt_ports(irq) {
+            unsafe { Port::new(port).write(END_OF_INTERRUPT) };
+        }
+    }
+
+    /// Reads the interrupt masks of the master and the slave PIC.
+    ///
+    /// A set bit means that the corresponding IRQ line is masked.
+    #[inline]
+    pub fn read_masks(&self) -> [u8; 2] {
+        unsafe { [Port::new(MASTER_DATA).read(), Port::new(SLAVE_DATA).read()] }
+    }
+
+    /// Writes the interrupt masks of the master and the slave PIC.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that handlers are installed for all unmasked IRQs.
+    #[inline]
+    pub unsafe fn write_masks(&mut self, masks: [u8; 2]) {
+        unsafe {
+            Port::new(MASTER_DATA).write(masks[0]);
+            Port::new(SLAVE_DATA).write(masks[1]);
+        }
+    }
+
+    /// Masks all IRQ lines of both PICs, e.g. to use the APIC instead.
+    ///
+    /// The PICs can still raise spurious IRQs (7 and 15), so they should be remapped with
+    /// [`initialize`](Self::initialize) before.
+    #[inline]
+    pub fn disable(&mut self) {
+        unsafe { self.write_masks([0xff, 0xff]) };
+    }
+}
+
+impl Default for ChainedPics {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn initialization_sequence() {
+        assert_eq!(
+            ChainedPics::initialization_sequence(32, 40, [0xfb, 0xff]),
+            [
+                (0x20, 0x11),
+                (0xa0, 0x11),
+                (0x21, 32),
+                (0xa1, 40),
+                (0x21, 0x04),
+                (0xa1, 0x02),
+                (0x21, 0x01),
+                (0xa1, 0x01),
+                (0x21, 0xfb),
+                (0xa1, 0xff),
+            ]
+        );
+    }
+
+    #[test]
+    fn end_of_interrupt() {
+        assert_eq!(ChainedPics::end_of_interrupt_ports(0), &[0x20]);
+        assert_eq!(ChainedPics::end_of_interrupt_ports(7), &[0x20]);
+        assert_eq!(ChainedPics::end_of_interrupt_ports(8), &[0xa0, 0x20]);
+        assert_eq!(ChainedPics::end_of_interrupt_ports(15), &[0xa0, 0x20]);
+    }
+
+    #[test]
+    fn vectors() {
+        let pics = ChainedPics { offsets: [32, 40] };
+        assert_eq!(pics.vector(0), 32);
+        assert_eq!(pics.vector(9), 41);
+        assert_eq!(pics.irq(47), Some(15));
+        assert_eq!(pics.irq(33), Some(1));
+        assert_eq!(pics.irq(31), None);
+        assert_eq!(pics.irq(48), None);
+        assert_eq!(ChainedPics::new().vector(15), 0x77);
+    }
+}
@@ -0,0 +1,178 @@
+//! Support for channel 0 of the 8254 programmable interval timer (PIT).
+//!
+//! Channel 0 of the PIT is connected to IRQ 0 and counts down from a programmable divisor at a
+//! fixed input frequency of [`BASE_FREQUENCY`]. It is commonly used as the system timer until the
+//! local APIC timer is set up, or to calibrate it.
+
+use crate::instructions::port::Port;
+
+/// The input frequency of the PIT in Hz.
+pub const BASE_FREQUENCY: u32 = 1_193_182;
+
+const CHANNEL_0: u16 = 0x40;
+const COMMAND: u16 = 0x43;
+
+/// Command byte bits selecting low byte then high byte access.
+const ACCESS_LOW_HIGH: u8 = 0b11 << 4;
+
+/// The operating modes of the PIT that generate a periodic output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum PitMode {
+    /// Mode 2: the output goes low for one input cycle whenever the counter reaches 1, which
+    /// raises IRQ 0 once per period. This is the usual mode for a periodic timer interrupt.
+    RateGenerator = 2,
+    /// Mode 3: the output is a square wave with the given frequency, which raises IRQ 0 on every
+    /// rising edge, i.e. also once per period. This is the mode used by the BIOS and for
+    /// generating tones on the PC speaker through channel 2.
+    SquareWave = 3,
+}
+
+/// Channel 0 of the 8254 programmable interval timer.
+///
+/// See the [module level documentation](self) for details.
+#[derive(Debug)]
+pub struct Pit(());
+
+impl Pit {
+    /// Creates an instance for channel 0 of the PIT.
+    ///
+    /// ## Safety
+    ///
+    /// Only one instance may be used at a time, as reading the counter requires two consecutive
+    /// accesses to the same port.
+    #[inline]
+    pub const unsafe fn new() -> Self {
+        Self(())
+    }
+
+    /// Returns the divisor that comes closest to the given frequency.
+    ///
+    /// The result is clamped to the supported range of 2 to 65536, so frequencies below about
+    /// 18.2 Hz yield the slowest and frequencies above about 596.6 kHz the fastest possible rate.
+    /// A frequency of 0 yields the slowest rate.
+    #[inline]
+    pub const fn divisor(hz: u32) -> u32 {
+        if hz == 0 {
+            return 0x1_0000;
+        }
+        let divisor = (BASE_FREQUENCY + hz / 2) / hz;
+        if divisor < 2 {
+            2
+        } else if divisor > 0x1_0000 {
+            0x1_0000
+        } else {
+            divisor
+        }
+    }
+
+    /// Returns the frequency in Hz resulting from the given divisor, rounded to the nearest
+    /// integer.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `divisor` is 0.
+    #[inline]
+    pub const fn frequency(divisor: u32) -> u32 {
+        assert!(divisor != 0, "PIT divisor must not be 0");
+        (BASE_FREQUENCY + divisor / 2) / divisor
+    }
+
+    /// Returns the command byte for channel 0 in the given mode.
+    const fn command(mode: PitMode) -> u8 {
+        ACCESS_LOW_HIGH | (mode as u8) << 1
+    }
+
+    /// Configures channel 0 to raise IRQ 0 periodically at approximately `hz` Hz using the
+    /// [rate generator](PitMode::RateGenerator) mode.
+    ///
+    /// Returns the actual frequency, which differs from `hz` because the divisor is an integer
+    /// (see [`divisor`](Self::divisor)).
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that a handler for the vector of IRQ 0 is installed before the
+    /// IRQ is unmasked.
+    #[inline]
+    pub unsafe fn set_frequency(&mut self, hz: u32) -> u32 {
+        let divisor = Self::divisor(hz);
+        unsafe { self.set_divisor(PitMode::RateGenerator, divisor) };
+        Self::frequency(divisor)
+    }
+
+    /// Configures channel 0 to count down from `divisor` in the given mode.
+    ///
+    /// The divisor is written as low byte then high byte, where 0 stands for 65536.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `divisor` is not in the range of 2 to 65536.
+    ///
+    /// ## Safety
+    ///
+    /// The caller must ensure that a handler for the vector of IRQ 0 is installed before the
+    /// IRQ is unmasked.
+    #[inline]
+    pub unsafe fn set_divisor(&mut self, mode: PitMode, divisor: u32) {
+        assert!(
+            (2..=0x1_0000).contains(&divisor),
+            "PIT divisor must be in the range of 2 to 65536"
+        );
+        let [low, high, ..] = divisor.to_le_bytes();
+        unsafe {
+            Port::new(COMMAND).write(Self::command(mode));
+            Port::new(CHANNEL_0).write(low);
+            Port::new(CHANNEL_0).write(high);
+        }
+    }
+
+    /// Reads the current value of the counter of channel 0.
+    ///
+    /// The value is latched first, so that the low and high byte belong to the same count. A
+    /// value of 0 means that the counter is about to reload.
+    #[inline]
+    pub fn read_count(&mut self) -> u16 {
+        unsafe {
+            // Latch command for channel 0.
+            Port::new(COMMAND).write(0u8);
+            let low: u8 = Port::new(CHANNEL_0).read();
+            let high: u8 = Port::new(CHANNEL_0).read();
+            u16::from_le_bytes([low, high])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn divisor() {
+        assert_eq!(Pit::divisor(100), 11932);
+        assert_eq!(Pit::divisor(1000), 1193);
+        // 1193182 / 1500 = 795.45
+        assert_eq!(Pit::divisor(1500), 795);
+        // 1193182 / 1501 = 794.93
+        assert_eq!(Pit::divisor(1501), 795);
+        assert_eq!(Pit::divisor(19), 62799);
+        assert_eq!(Pit::divisor(18), 0x1_0000);
+        assert_eq!(Pit::divisor(0), 0x1_0000);
+        assert_eq!(Pit::divisor(1_000_000), 2);
+        assert_eq!(Pit::divisor(u32::MAX), 2);
+
+        assert_eq!(Pit::frequency(11932), 100);
+        assert_eq!(Pit::frequency(0x1_0000), 18);
+    }
+
+    #[test]
+    #[should_panic]
+    fn frequency_of_zero_divisor() {
+        Pit::frequency(0);
+    }
+
+    #[test]
+    fn command() {
+        assert_eq!(Pit::command(PitMode::RateGenerator), 0x34);
+        assert_eq!(Pit::command(PitMode::SquareWave), 0x36);
+    }
+}
@@ -12,6 +12,22 @@ pub trait PortRead {
     /// This function is unsafe because the I/O port could have side effects that violate memory
     /// safety.
     unsafe fn read_from_port(port: u16) -> Self;
+
+    /// Reads `buf.len()` values from the given port into `buf`.
+    ///
+    /// ## Safety
+    ///
+    /// This function is unsafe because the I/O port could have side effects that violate memory
+    /// safety.
+    #[inline]
+    unsafe fn read_from_port_buffer(port: u16, buf: &mut [Self])
+    where
+        Self: Sized,
+    {
+        for value in buf {
+            *value = unsafe { Self::read_from_port(port) };
+        }
+    }
 }
 
 /// A helper trait that implements the write port operation.
@@ -26,4 +42,21 @@ pub trait PortWrite {
     /// This function is unsafe because the I/O port could have side effects that violate memory
     /// safety.
     unsafe fn write_to_port(port: u16, value: Self);
+
+    /// Writes all values in `buf` to the given port.
+    ///
+    /// ## Safety
+    ///
+    /// This function is unsafe because the I/O port could have side effects that violate memory
+    /// safety. Unless it is overridden, it writes bitwise copies of the values, so `Self` must
+    /// be a plain integer type like `u8`, `u16` or `u32`.
+    #[inline]
+    unsafe fn write_to_port_buffer(port: u16, buf: &[Self])
+    where
+        Self: Sized,
+    {
+        for value in buf {
+            unsafe { Self::write_to_port(port, core::ptr::read(value)) };
+        }
+    }
 }
@@ -1,6 +1,6 @@
 //! Provides a type for the task state segment structure.
 
-use crate::VirtAddr;
+use crate::{PrivilegeLevel, VirtAddr};
 use core::mem::size_of;
 
 /// In 64-bit mode the TSS holds information that is not
@@ -42,6 +42,58 @@ impl TaskStateSegment {
             reserved_4: 0,
         }
     }
+
+    /// Sets the interrupt stack table entry with the given index to `stack_top`.
+    ///
+    /// Like [`EntryOptions::set_stack_index`](crate::structures::idt::EntryOptions::set_stack_index),
+    /// the index starts at 0, i.e. index 0 is used by IDT entries with the hardware IST index 1.
+    /// As stacks grow downwards, `stack_top` must be the address right after the end of the stack.
+    ///
+    /// This function is `const`, so it can be used to set up the TSS in a `static`:
+    ///
+    /// ```
+    /// use x86_64::structures::tss::TaskStateSegment;
+    /// use x86_64::{PrivilegeLevel, VirtAddr};
+    ///
+    /// static TSS: TaskStateSegment = TaskStateSegment::new()
+    ///     .with_ist(0, VirtAddr::new_truncate(0xffff_8000_0001_0000))
+    ///     .with_privilege_stack(PrivilegeLevel::Ring0, VirtAddr::new_truncate(0xffff_8000_0002_0000));
+    /// ```
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the index is not in the range 0..7.
+    #[inline]
+    #[must_use]
+    pub const fn with_ist(mut self, index: u16, stack_top: VirtAddr) -> Self {
+        assert!(index < 7, "IST index must be in the range 0..7");
+        self.interrupt_stack_table[index as usize] = stack_top;
+        self
+    }
+
+    /// Sets the stack that is loaded when an interrupt or a call gate switches to the given
+    /// privilege level to `stack_top`.
+    ///
+    /// As stacks grow downwards, `stack_top` must be the address right after the end of the stack.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `level` is [`Ring3`](PrivilegeLevel::Ring3), as there is no stack switch to the
+    /// least privileged level.
+    #[inline]
+    #[must_use]
+    pub const fn with_privilege_stack(
+        mut self,
+        level: PrivilegeLevel,
+        stack_top: VirtAddr,
+    ) -> Self {
+        assert!(
+            !matches!(level, PrivilegeLevel::Ring3),
+            "the TSS has no stack for privilege level 3"
+        );
+        self.privilege_stack_table[level as usize] = stack_top;
+        self
+    }
 }
 
 impl Default for TaskStateSegment {
@@ -51,6 +103,90 @@ impl Default for TaskStateSegment {
     }
 }
 
+/// A [`TaskStateSegment`] followed by an I/O permission bitmap of `N` bytes.
+///
+/// Each bit of the bitmap corresponds to an I/O port: if it is clear, the port
+/// can be accessed from privilege levels above the I/O privilege level
+/// (IOPL), otherwise such accesses cause a general protection exception. The
+/// bitmap covers ports `0..N * 8`; accesses to higher ports are always denied.
+/// As there are 65536 ports, `N` must be at most 8192 (8 KiB).
+///
+/// The processor may read one byte past the bitmap, which therefore has to be
+/// followed by a byte with all bits set. This byte is part of this type.
+///
+/// Use [`Descriptor::tss_segment_with_io_bitmap`](crate::structures::gdt::Descriptor::tss_segment_with_io_bitmap)
+/// to create a TSS descriptor whose limit includes the bitmap.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct TaskStateSegmentWithIoBitmap<const N: usize> {
+    /// The task state segment. Its [`iomap_base`](TaskStateSegment::iomap_base)
+    /// points to the bitmap following it.
+    pub tss: TaskStateSegment,
+    io_bitmap: [u8; N],
+    terminator: u8,
+}
+
+impl<const N: usize> TaskStateSegmentWithIoBitmap<N> {
+    /// Creates a new TSS with zeroed privilege and interrupt stack table and an
+    /// I/O permission bitmap that denies access to all ports.
+    #[inline]
+    pub const fn new() -> Self {
+        assert!(N <= 8192, "the I/O permission bitmap can be at most 8 KiB");
+        TaskStateSegmentWithIoBitmap {
+            tss: TaskStateSegment::new(),
+            io_bitmap: [0xff; N],
+            terminator: 0xff,
+        }
+    }
+
+    /// Allows accessing the given port from all privilege levels.
+    ///
+    /// Panics if the port is not covered by the bitmap.
+    #[inline]
+    pub fn allow_port(&mut self, port: u16) {
+        let (byte, bit) = Self::bit_position(port);
+        self.io_bitmap[byte] &= !(1 << bit);
+    }
+
+    /// Denies accessing the given port from privilege levels above the IOPL.
+    ///
+    /// Panics if the port is not covered by the bitmap.
+    #[inline]
+    pub fn deny_port(&mut self, port: u16) {
+        let (byte, bit) = Self::bit_position(port);
+        self.io_bitmap[byte] |= 1 << bit;
+    }
+
+    /// Returns whether accessing the given port is allowed from all privilege levels.
+    #[inline]
+    pub fn is_port_allowed(&self, port: u16) -> bool {
+        let byte = usize::from(port / 8);
+        byte < N && self.io_bitmap[byte] & (1 << (port % 8)) == 0
+    }
+
+    /// Returns the I/O permission bitmap.
+    #[inline]
+    pub fn io_bitmap(&self) -> &[u8; N] {
+        &self.io_bitmap
+    }
+
+    /// The size of the TSS including the bitmap and its terminating byte.
+    pub(crate) const SIZE: usize = size_of::<TaskStateSegment>() + N + 1;
+
+    fn bit_position(port: u16) -> (usize, u16) {
+        let byte = usize::from(port / 8);
+        assert!(byte < N, "port is not covered by the I/O permission bitmap");
+        (byte, port % 8)
+    }
+}
+
+impl<const N: usize> Default for TaskStateSegmentWithIoBitmap<N> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -61,4 +197,48 @@ mod tests {
         // minimum limit of 0x67.
         assert_eq!(size_of::<TaskStateSegment>(), 0x68);
     }
+
+    #[test]
+    pub fn const_stacks() {
+        const IST0: VirtAddr = VirtAddr::new_truncate(0xffff_8000_0001_0000);
+        const PL0: VirtAddr = VirtAddr::new_truncate(0xffff_8000_0002_0000);
+        static TSS: TaskStateSegment = TaskStateSegment::new()
+            .with_ist(0, IST0)
+            .with_privilege_stack(PrivilegeLevel::Ring0, PL0);
+
+        let ist = TSS.interrupt_stack_table;
+        let privilege_stacks = TSS.privilege_stack_table;
+        assert_eq!(ist[0], IST0);
+        assert!(ist[1..].iter().all(|addr| addr.is_null()));
+        assert_eq!(privilege_stacks, [PL0, VirtAddr::zero(), VirtAddr::zero()]);
+        assert_eq!({ TSS.iomap_base }, 0x68);
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn ist_index_out_of_range() {
+        let _ = TaskStateSegment::new().with_ist(7, VirtAddr::zero());
+    }
+
+    #[test]
+    pub fn io_bitmap() {
+        let mut tss = TaskStateSegmentWithIoBitmap::<16>::new();
+        assert_eq!({ tss.tss.iomap_base }, 0x68);
+        assert!(!tss.is_port_allowed(0x60));
+
+        tss.allow_port(0x60);
+        assert_eq!(tss.io_bitmap()[0x0c], 0b1111_1110);
+        assert!(tss.is_port_allowed(0x60));
+        assert!(!tss.is_port_allowed(0x61));
+        assert!(!tss.is_port_allowed(0x80));
+
+        tss.deny_port(0x60);
+        assert_eq!(tss.io_bitmap()[0x0c], 0xff);
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn io_bitmap_port_out_of_range() {
+        TaskStateSegmentWithIoBitmap::<16>::new().allow_port(0x80);
+    }
 }
@@ -4,3 +4,56 @@ fn example_test() {
     assert_eq!(0, 0);
     serial_println!("[ok]");
 }
+
+#[test_case]
+fn rflags_without_interrupts() {
+    use x86_64::instructions::interrupts;
+    use x86_64::registers::rflags::{self, RFlags};
+
+    serial_print!("rflags_without_interrupts... ");
+    interrupts::disable();
+    let original = rflags::read();
+    rflags::without_interrupts(|| {
+        assert!(!interrupts::are_enabled());
+        // The ID flag has no side effects.
+        unsafe { rflags::write(rflags::read() ^ RFlags::ID) };
+    });
+    // Only the interrupt flag is restored.
+    assert!(!interrupts::are_enabled());
+    assert_ne!(rflags::read() & RFlags::ID, original & RFlags::ID);
+    unsafe { rflags::write(original) };
+    serial_println!("[ok]");
+}
+
+#[test_case]
+fn per_cpu_base() {
+    use x86_64::registers::model_specific::{GsBase, KernelGsBase, PerCpuBase};
+    use x86_64::VirtAddr;
+
+    serial_print!("per_cpu_base... ");
+    let (gs_base, kernel_gs_base) = (GsBase::read(), KernelGsBase::read());
+    let user = VirtAddr::new(0x1234_5000);
+    let per_cpu = PerCpuBase::new(VirtAddr::new(0xffff_8000_0010_0000));
+    GsBase::write(user);
+    unsafe { per_cpu.install() };
+    assert_eq!(KernelGsBase::read(), per_cpu.base());
+
+    unsafe { GsBase::swap() };
+    assert!(per_cpu.is_active());
+    assert_eq!(KernelGsBase::read(), user);
+    unsafe { GsBase::swap() };
+    assert_eq!(GsBase::read(), user);
+
+    unsafe { per_cpu.activate_on_entry() };
+    assert!(per_cpu.is_active());
+    assert_eq!(KernelGsBase::read(), user);
+
+    unsafe { per_cpu.activate_on_exit() };
+    assert!(!per_cpu.is_active());
+    assert_eq!(GsBase::read(), user);
+    assert_eq!(KernelGsBase::read(), per_cpu.base());
+
+    GsBase::write(gs_base);
+    KernelGsBase::write(kernel_gs_base);
+    serial_println!("[ok]");
+}
@@ -41,9 +41,11 @@ pub extern "C" fn _start() -> ! {
     unsafe { interrupts::software_interrupt::<77>() };
     serial_print!("interrupt 42... ");
     unsafe { interrupts::software_interrupt::<42>() };
+    serial_print!("interrupt 77 (dynamic)... ");
+    unsafe { interrupts::software_interrupt_dynamic(77) };
 
     match INTERRUPT_HANDLER_CALLED.load(Ordering::SeqCst) {
-        3 => {}
+        4 => {}
         0 => {
             serial_println!("[failed]");
             serial_println!("    Interrupt handler was not called.");
@@ -0,0 +1,232 @@
+#![no_std]
+#![no_main]
+
+use bootloader::bootinfo::{MemoryMap, MemoryRegionType};
+use bootloader::{entry_point, BootInfo};
+use core::panic::PanicInfo;
+use testing::{exit_qemu, serial_print, serial_println, QemuExitCode};
+use x86_64::structures::paging::mapper::{
+    FlagUpdateError, MapToError, MappedFrame, TranslateError, TranslateResult,
+};
+use x86_64::structures::paging::{
+    FrameAllocator, Mapper, Page, PageSize, PageTable, PageTableFlags, PageTableIndex, PhysFrame,
+    RecursivePageTable, Size1GiB, Size2MiB, Size4KiB, Translate,
+};
+use x86_64::{PhysAddr, VirtAddr};
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    serial_print!("recursive_page_table... ");
+
+    let level_4_addr = VirtAddr::new(boot_info.recursive_page_table_addr);
+    let level_4_table = unsafe { &mut *level_4_addr.as_mut_ptr::<PageTable>() };
+    let mut mapper = RecursivePageTable::new(level_4_table).unwrap();
+    let mut allocator = BootInfoFrameAllocator::new(&boot_info.memory_map);
+
+    translate_page_sizes(&mut mapper, &mut allocator);
+    map_user_page_parent_flags(&mut mapper, &mut allocator, level_4_addr.p4_index());
+    map_1gib_page(&mut mapper, &mut allocator);
+
+    serial_println!("[ok]");
+    exit_qemu(QemuExitCode::Success);
+    loop {}
+}
+
+fn translate_page_sizes(mapper: &mut RecursivePageTable, allocator: &mut BootInfoFrameAllocator) {
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+
+    // The pages are never accessed, so the huge frames don't need to exist.
+    let page_1gib = Page::<Size1GiB>::containing_address(VirtAddr::new(0x4000_0000_0000));
+    let frame_1gib = PhysFrame::containing_address(PhysAddr::new(0x1_0000_0000));
+    let page_2mib = Page::<Size2MiB>::containing_address(VirtAddr::new(0x4000_4000_0000));
+    let frame_2mib = PhysFrame::containing_address(PhysAddr::new(0x4000_0000));
+    let page_4kib = Page::<Size4KiB>::containing_address(VirtAddr::new(0x4000_8000_0000));
+    let frame_4kib = allocator.allocate_frame().unwrap();
+    unsafe {
+        mapper
+            .map_to(page_1gib, frame_1gib, flags, allocator)
+            .unwrap()
+            .flush();
+        mapper
+            .map_to(page_2mib, frame_2mib, flags, allocator)
+            .unwrap()
+            .flush();
+        mapper
+            .map_to(page_4kib, frame_4kib, flags, allocator)
+            .unwrap()
+            .flush();
+    }
+
+    let expected = [
+        (page_1gib.start_address(), MappedFrame::Size1GiB(frame_1gib)),
+        (page_2mib.start_address(), MappedFrame::Size2MiB(frame_2mib)),
+        (page_4kib.start_address(), MappedFrame::Size4KiB(frame_4kib)),
+    ];
+    for (addr, expected_frame) in expected {
+        let offset = expected_frame.size() - 8;
+        match mapper.translate(addr + offset) {
+            TranslateResult::Mapped {
+                frame,
+                offset: actual_offset,
+                flags: actual_flags,
+            } => {
+                assert_eq!(frame, expected_frame);
+                assert_eq!(actual_offset, offset);
+                assert!(actual_flags.contains(flags));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(
+            mapper.translate_addr(addr + 0x123u64),
+            Some(expected_frame.start_address() + 0x123u64)
+        );
+    }
+    assert!(matches!(
+        mapper.translate(VirtAddr::new(0x4000_8000_1000)),
+        TranslateResult::NotMapped
+    ));
+    assert_eq!(mapper.translate_addr(VirtAddr::new(0x4001_0000_0000)), None);
+}
+
+fn map_user_page_parent_flags(
+    mapper: &mut RecursivePageTable,
+    allocator: &mut BootInfoFrameAllocator,
+    recursive_index: PageTableIndex,
+) {
+    // A kernel page creates the parent tables without `USER_ACCESSIBLE`.
+    let kernel_page = Page::<Size4KiB>::containing_address(VirtAddr::new(0x5000_0000_0000));
+    let frame = allocator.allocate_frame().unwrap();
+    unsafe { mapper.map_to(kernel_page, frame, PageTableFlags::PRESENT, allocator) }
+        .unwrap()
+        .flush();
+
+    let user_page = kernel_page + 1;
+    let flags =
+        PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE;
+    unsafe { mapper.map_to(user_page, frame, flags, allocator) }
+        .unwrap()
+        .flush();
+
+    // The parent tables of the page, accessed through the recursive entry.
+    let r = recursive_index;
+    let (p4, p3, p2) = (
+        user_page.p4_index(),
+        user_page.p3_index(),
+        user_page.p2_index(),
+    );
+    let table = |page: Page| unsafe { &*page.start_address().as_ptr::<PageTable>() };
+    let entries = [
+        &mapper.level_4_table()[p4],
+        &table(Page::from_page_table_indices(r, r, r, p4))[p3],
+        &table(Page::from_page_table_indices(r, r, p4, p3))[p2],
+        &table(Page::from_page_table_indices(r, p4, p3, p2))[user_page.p1_index()],
+    ];
+    for entry in entries {
+        assert!(entry.flags().contains(flags), "{:?}", entry);
+    }
+}
+
+fn map_1gib_page(mapper: &mut RecursivePageTable, allocator: &mut BootInfoFrameAllocator) {
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE;
+
+    // The pages are never accessed, so the frames don't need to exist.
+    let page = Page::<Size1GiB>::containing_address(VirtAddr::new(0x6000_0000_0000));
+    let frame = PhysFrame::containing_address(PhysAddr::new(0x2_0000_0000));
+    unsafe { mapper.map_to(page, frame, flags, allocator) }
+        .unwrap()
+        .flush();
+    assert_eq!(mapper.translate_page(page).unwrap(), frame);
+    assert_eq!(
+        mapper.translate_addr(page.start_address() + 0x1234_5678u64),
+        Some(frame.start_address() + 0x1234_5678u64)
+    );
+    assert!(matches!(
+        unsafe { mapper.map_to(page, frame, flags, allocator) },
+        Err(MapToError::PageAlreadyMapped(_))
+    ));
+
+    unsafe { mapper.update_flags(page, PageTableFlags::PRESENT) }
+        .unwrap()
+        .flush();
+    match mapper.translate(page.start_address()) {
+        TranslateResult::Mapped {
+            frame: MappedFrame::Size1GiB(mapped),
+            flags: mapped_flags,
+            ..
+        } => {
+            assert_eq!(mapped, frame);
+            assert!(!mapped_flags.contains(PageTableFlags::WRITABLE));
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let (unmapped, flush) = mapper.unmap(page).unwrap();
+    flush.flush();
+    assert_eq!(unmapped, frame);
+    assert!(matches!(
+        mapper.translate_page(page),
+        Err(TranslateError::PageNotMapped)
+    ));
+
+    // A level 3 entry that points to a level 2 table is not a 1GiB page.
+    let page_2mib = Page::<Size2MiB>::containing_address(VirtAddr::new(0x6000_4000_0000));
+    let frame_2mib = PhysFrame::containing_address(PhysAddr::new(0x4000_0000));
+    unsafe { mapper.map_to(page_2mib, frame_2mib, flags, allocator) }
+        .unwrap()
+        .flush();
+    let page = Page::<Size1GiB>::containing_address(page_2mib.start_address());
+    assert!(matches!(
+        mapper.translate_page(page),
+        Err(TranslateError::PageNotMapped)
+    ));
+    assert!(matches!(
+        unsafe { mapper.update_flags(page, flags) },
+        Err(FlagUpdateError::PageNotMapped)
+    ));
+    assert!(matches!(
+        unsafe { mapper.map_to(page, frame, flags, allocator) },
+        Err(MapToError::PageAlreadyMapped(_))
+    ));
+    assert!(mapper.unmap(page).is_err());
+    assert_eq!(mapper.translate_page(page_2mib).unwrap(), frame_2mib);
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    testing::test_panic_handler(info)
+}
+
+/// Hands out the usable frames of the memory map reported by the bootloader.
+struct BootInfoFrameAllocator {
+    memory_map: &'static MemoryMap,
+    next: usize,
+}
+
+impl BootInfoFrameAllocator {
+    fn new(memory_map: &'static MemoryMap) -> Self {
+        BootInfoFrameAllocator {
+            memory_map,
+            next: 0,
+        }
+    }
+
+    fn usable_frames(&self) -> impl Iterator<Item = PhysFrame> {
+        self.memory_map
+            .iter()
+            .filter(|region| region.region_type == MemoryRegionType::Usable)
+            .flat_map(|region| {
+                (region.range.start_addr()..region.range.end_addr())
+                    .step_by(Size4KiB::SIZE as usize)
+            })
+            .map(|addr| PhysFrame::containing_address(PhysAddr::new(addr)))
+    }
+}
+
+unsafe impl FrameAllocator<Size4KiB> for BootInfoFrameAllocator {
+    fn allocate_frame(&mut self) -> Option<PhysFrame> {
+        let frame = self.usable_frames().nth(self.next);
+        self.next += 1;
+        frame
+    }
+}